@@ -0,0 +1,200 @@
+//! Runs the per-tick movement, repulsion, collision and targeting systems with 2,000 enemies
+//! packed into the arena and checks that a tick fits into a 60 FPS frame.
+//!
+//! Run with `cargo bench --bench spatial_index`.
+
+use std::time::{Duration, Instant};
+
+use bevy::ecs::message::Messages;
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use vamita::audio::SEVolume;
+use vamita::game::GameState;
+use vamita::game::arena::ArenaMap;
+use vamita::game::behavior::{EnemyBehavior, steer_enemies};
+use vamita::game::character::Character;
+use vamita::game::combat::handle_collisions;
+use vamita::game::components::{Lifetime, Projectile, Velocity};
+use vamita::game::enemy::{EnemyAttributes, spawn_enemy};
+use vamita::game::events::{BossDefeated, DamageEnemy, EnemyDamaged, EnemyKilled, PlayerHit};
+use vamita::game::experience::{self, experience_orb_behavior};
+use vamita::game::movement::{decay_lifetimes, enemy_repulsion, update_velocity};
+use vamita::game::pickup::EnemyFreeze;
+use vamita::game::player::{Player, PlayerStats, spawn_player};
+use vamita::game::powerup::{PlayerUpgrades, PowerUpProgress, StartingBonuses};
+use vamita::game::replay::AimInput;
+use vamita::game::resources::{
+    DefeatSound, ExperienceOrbSound, HitSelfSound, HitSound, ShootSound,
+};
+use vamita::game::rng::RunRng;
+use vamita::game::spatial::{SpatialIndex, rebuild_spatial_index};
+use vamita::game::status::ApplyStatus;
+use vamita::game::ui::Score;
+use vamita::game::weapon::{WeaponKind, fire_guns, spawn_weapon, tick_weapon_cooldowns};
+use vamita::{Difficulty, DisplayQuality};
+
+const ENEMY_COUNT: usize = 2_000;
+/// Player shots landing in the crowd each tick.
+const PROJECTILES_PER_TICK: usize = 100;
+const SPAWN_HALF_SIZE: f32 = 800.0;
+const WARMUP_TICKS: u32 = 60;
+const MEASURED_TICKS: u32 = 600;
+const TICK: Duration = Duration::from_micros(15_625);
+const FRAME_BUDGET: Duration = Duration::from_micros(16_667);
+
+const ATTRIBUTES: EnemyAttributes = EnemyAttributes {
+    health: 3,
+    size: Vec2::splat(24.0),
+    speed: 100.0,
+    damage: 1,
+    score_value: 1,
+    xp_value: 1,
+    color: Color::srgb(0.9, 0.2, 0.3),
+};
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut world = setup_world(&mut rng);
+
+    let mut schedule = Schedule::default();
+    schedule.add_systems(
+        (
+            update_velocity,
+            rebuild_spatial_index,
+            experience_orb_behavior,
+            steer_enemies,
+            enemy_repulsion,
+            handle_collisions,
+            tick_weapon_cooldowns,
+            fire_guns,
+            decay_lifetimes,
+        )
+            .chain(),
+    );
+
+    let mut tick_times = Vec::with_capacity(MEASURED_TICKS as usize);
+    for tick in 0..WARMUP_TICKS + MEASURED_TICKS {
+        let start = Instant::now();
+        spawn_projectiles(&mut world, &mut rng);
+        world.resource_mut::<Time>().advance_by(TICK);
+        schedule.run(&mut world);
+        update_messages(&mut world);
+        if tick >= WARMUP_TICKS {
+            tick_times.push(start.elapsed());
+        }
+    }
+
+    tick_times.sort();
+    let mean = tick_times.iter().sum::<Duration>() / tick_times.len() as u32;
+    let p99 = tick_times[tick_times.len() * 99 / 100];
+    let max = tick_times[tick_times.len() - 1];
+    println!("{ENEMY_COUNT} enemies, {MEASURED_TICKS} ticks");
+    println!("mean {mean:?}  p99 {p99:?}  max {max:?}  (budget {FRAME_BUDGET:?})");
+
+    if mean > FRAME_BUDGET {
+        eprintln!("mean tick time exceeds the 60 FPS budget");
+        std::process::exit(1);
+    }
+}
+
+fn setup_world(rng: &mut StdRng) -> World {
+    let mut world = World::new();
+    world.insert_resource(Time::<()>::default());
+    world.init_resource::<SpatialIndex>();
+    world.init_resource::<ArenaMap>();
+    world.init_resource::<EnemyFreeze>();
+    world.init_resource::<AimInput>();
+    world.init_resource::<Score>();
+    world.init_resource::<PlayerStats>();
+    world.init_resource::<PowerUpProgress>();
+    world.init_resource::<PlayerUpgrades>();
+    world.init_resource::<SEVolume>();
+    world.init_resource::<DisplayQuality>();
+    world.insert_resource(RunRng::new(7));
+    world.init_resource::<NextState<GameState>>();
+    world.init_resource::<Messages<PlayerHit>>();
+    world.init_resource::<Messages<EnemyKilled>>();
+    world.init_resource::<Messages<BossDefeated>>();
+    world.init_resource::<Messages<DamageEnemy>>();
+    world.init_resource::<Messages<EnemyDamaged>>();
+    world.init_resource::<Messages<ApplyStatus>>();
+    world.init_resource::<Assets<Mesh>>();
+    world.init_resource::<Assets<ColorMaterial>>();
+    world.insert_resource(HitSound(Handle::default()));
+    world.insert_resource(HitSelfSound(Handle::default()));
+    world.insert_resource(DefeatSound(Handle::default()));
+    world.insert_resource(ShootSound(Handle::default()));
+    world.insert_resource(ExperienceOrbSound(Handle::default()));
+    world
+        .run_system_cached(experience::setup)
+        .expect("orb mesh setup failed");
+
+    let mut commands = world.commands();
+    spawn_player(
+        &mut commands,
+        &Difficulty::Normal,
+        &StartingBonuses::default(),
+        Character::default(),
+    );
+    spawn_weapon(&mut commands, WeaponKind::Gun);
+    for _ in 0..ENEMY_COUNT {
+        let position = Vec2::new(
+            rng.random_range(-SPAWN_HALF_SIZE..SPAWN_HALF_SIZE),
+            rng.random_range(-SPAWN_HALF_SIZE..SPAWN_HALF_SIZE),
+        );
+        spawn_enemy(
+            &mut commands,
+            "Grunt".to_string(),
+            ATTRIBUTES,
+            EnemyBehavior::Chase,
+            position,
+            Vec2::ZERO,
+            rng,
+        );
+    }
+    world.flush();
+
+    // Keep the player out of reach so the crowd stays at full size for the whole run.
+    let mut player = world
+        .query_filtered::<&mut Transform, With<Player>>()
+        .single_mut(&mut world)
+        .expect("player was not spawned");
+    player.translation = Vec3::new(SPAWN_HALF_SIZE * 6.0, 0.0, 0.0);
+    world
+}
+
+/// Harmless shots, so every hit runs the full collision path without thinning the crowd.
+fn spawn_projectiles(world: &mut World, rng: &mut StdRng) {
+    world.spawn_batch(
+        (0..PROJECTILES_PER_TICK)
+            .map(|_| {
+                let position = Vec2::new(
+                    rng.random_range(-SPAWN_HALF_SIZE..SPAWN_HALF_SIZE),
+                    rng.random_range(-SPAWN_HALF_SIZE..SPAWN_HALF_SIZE),
+                );
+                (
+                    Transform::from_translation(position.extend(1.0)),
+                    Projectile {
+                        damage: 0,
+                        crit: false,
+                    },
+                    Velocity(Vec2::ZERO),
+                    Lifetime {
+                        timer: Timer::from_seconds(TICK.as_secs_f32(), TimerMode::Once),
+                    },
+                )
+            })
+            .collect::<Vec<_>>(),
+    );
+}
+
+fn update_messages(world: &mut World) {
+    world.resource_mut::<Messages<PlayerHit>>().update();
+    world.resource_mut::<Messages<EnemyKilled>>().update();
+    world.resource_mut::<Messages<BossDefeated>>().update();
+    world.resource_mut::<Messages<DamageEnemy>>().update();
+    world.resource_mut::<Messages<EnemyDamaged>>().update();
+    world.resource_mut::<Messages<ApplyStatus>>().update();
+}
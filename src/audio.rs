@@ -1,7 +1,10 @@
 use bevy::prelude::*;
+use bevy::transform::TransformSystems;
 use bevy_pkv::PersistentResourceAppExtensions;
 use serde::{Deserialize, Serialize};
 
+use crate::DisplayQuality;
+
 #[derive(Resource, Debug, Component, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub struct BGMVolume(pub u32);
 
@@ -23,19 +26,51 @@ impl Default for SEVolume {
 #[derive(Component)]
 pub struct BGM;
 
+/// Marks the entities `spawn_se` plays sound effects on.
+#[derive(Component)]
+pub struct SoundEffect;
+
 pub fn plugin(app: &mut App) {
     app.init_persistent_resource::<BGMVolume>()
         .init_persistent_resource::<SEVolume>()
-        .add_systems(Update, update_bgm_volume);
+        .add_systems(Update, update_bgm_volume)
+        // Audio starts playing after transform propagation.
+        .add_systems(
+            PostUpdate,
+            limit_sound_effects.before(TransformSystems::Propagate),
+        );
 }
 
 pub fn spawn_se(commands: &mut Commands, se_volume: &SEVolume, sound: &Handle<AudioSource>) {
     commands.spawn((
         AudioPlayer(sound.clone()),
         PlaybackSettings::DESPAWN.with_volume(volume_from_setting(se_volume.0)),
+        SoundEffect,
     ));
 }
 
+/// Drops sound effects that haven't started yet while more are around than the display
+/// quality allows.
+fn limit_sound_effects(
+    mut commands: Commands,
+    quality: Res<DisplayQuality>,
+    effects: Query<(Entity, Has<AudioSink>), With<SoundEffect>>,
+) {
+    let mut excess = effects
+        .iter()
+        .count()
+        .saturating_sub(quality.max_sound_effects());
+    for (entity, playing) in &effects {
+        if excess == 0 {
+            break;
+        }
+        if !playing {
+            commands.entity(entity).despawn();
+            excess -= 1;
+        }
+    }
+}
+
 pub fn update_bgm_volume(
     mut music_controller: Query<&mut AudioSink, With<BGM>>,
     volume: Res<BGMVolume>,
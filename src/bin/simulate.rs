@@ -0,0 +1,379 @@
+//! Headless balance simulator. Plays many seeded runs with the autopilot as fast as the CPU
+//! allows, without a window, GPU or audio, and writes one result per run as CSV or JSON.
+//!
+//! ```text
+//! cargo run --release --bin simulate -- --runs 200 --seed 1 --difficulty hard --map circle --character knight --out results.csv
+//! ```
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use bevy::log::{Level, LogPlugin};
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use serde::Serialize;
+
+use vamita::audio::SEVolume;
+use vamita::game::arena::{MAPS, MapCatalog, SelectedMap};
+use vamita::game::character::{ALL_CHARACTERS, Character};
+use vamita::game::components::LevelEntity;
+use vamita::game::enemy::EnemyCatalog;
+use vamita::game::player::PlayerStats;
+use vamita::game::powerup::{PowerUpProgress, StartingBonuses, apply_powerup_selection};
+use vamita::game::replay::{ActiveReplay, feed_move_input};
+use vamita::game::resources::{
+    BombSound, DefeatSound, ExperienceOrbSound, HitSelfSound, HitSound, ShootSound,
+};
+use vamita::game::ui::Score;
+use vamita::game::waves::WaveTimeline;
+use vamita::game::{GameState, autopilot, experience, reset_game, simulation_plugin};
+use vamita::{Difficulty, DisplayQuality, MainState};
+
+const USAGE: &str = "usage: simulate [--runs N] [--seed FIRST_SEED] [--difficulty easy|normal|hard] \
+[--map MAP] [--character CHARACTER] [--max-minutes M] [--out PATH] [--format csv|json]";
+const DATA_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Csv,
+    Json,
+}
+
+struct Options {
+    runs: u64,
+    first_seed: u64,
+    difficulty: Difficulty,
+    map: SelectedMap,
+    character: Character,
+    max_minutes: f32,
+    out: Option<PathBuf>,
+    format: Format,
+}
+
+#[derive(Serialize)]
+struct RunResult {
+    seed: u64,
+    difficulty: Difficulty,
+    map: &'static str,
+    character: &'static str,
+    /// False when the run hit the time limit.
+    died: bool,
+    time_survived: f32,
+    score: u32,
+    level: u32,
+    enemies_killed: u32,
+    damage_taken: u32,
+    upgrades: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    let options = match parse_options(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{message}\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut app = build_app();
+    if let Err(message) = wait_for_game_data(&mut app) {
+        eprintln!("{message}");
+        return ExitCode::FAILURE;
+    }
+
+    let max_seconds = options.max_minutes * 60.0;
+    let results: Vec<RunResult> = (0..options.runs)
+        .map(|index| {
+            let seed = options.first_seed + index;
+            let result = simulate_run(
+                &mut app,
+                options.difficulty,
+                options.map,
+                options.character,
+                seed,
+                max_seconds,
+            );
+            eprintln!(
+                "run {}/{} seed {seed}: score {} level {} survived {:.0}s",
+                index + 1,
+                options.runs,
+                result.score,
+                result.level,
+                result.time_survived,
+            );
+            result
+        })
+        .collect();
+
+    let output = match options.format {
+        Format::Csv => to_csv(&results),
+        Format::Json => serde_json::to_string_pretty(&results).expect("results serialize"),
+    };
+    match &options.out {
+        Some(path) => {
+            if let Err(error) = std::fs::write(path, output) {
+                eprintln!("could not write {}: {error}", path.display());
+                return ExitCode::FAILURE;
+            }
+        }
+        None => println!("{output}"),
+    }
+    ExitCode::SUCCESS
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        runs: 100,
+        first_seed: 1,
+        difficulty: Difficulty::Normal,
+        map: SelectedMap::default(),
+        character: Character::default(),
+        max_minutes: 30.0,
+        out: None,
+        format: Format::Csv,
+    };
+    let mut format = None;
+
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for {flag}"))?;
+        let invalid = || format!("invalid value `{value}` for {flag}");
+        match flag.as_str() {
+            "--runs" => options.runs = value.parse().map_err(|_| invalid())?,
+            "--seed" => options.first_seed = value.parse().map_err(|_| invalid())?,
+            "--difficulty" => {
+                options.difficulty = match value.as_str() {
+                    "easy" => Difficulty::Easy,
+                    "normal" => Difficulty::Normal,
+                    "hard" => Difficulty::Hard,
+                    _ => return Err(invalid()),
+                }
+            }
+            "--map" => {
+                options.map = SelectedMap::from_id(&value).ok_or_else(|| {
+                    let ids: Vec<&str> = MAPS.iter().map(|(id, _)| *id).collect();
+                    format!("unknown map `{value}`, expected one of {}", ids.join(", "))
+                })?
+            }
+            "--character" => {
+                options.character = Character::from_id(&value).ok_or_else(|| {
+                    let ids: Vec<&str> = ALL_CHARACTERS
+                        .iter()
+                        .map(|character| character.id())
+                        .collect();
+                    format!(
+                        "unknown character `{value}`, expected one of {}",
+                        ids.join(", ")
+                    )
+                })?
+            }
+            "--max-minutes" => options.max_minutes = value.parse().map_err(|_| invalid())?,
+            "--out" => options.out = Some(PathBuf::from(value)),
+            "--format" => {
+                format = Some(match value.as_str() {
+                    "csv" => Format::Csv,
+                    "json" => Format::Json,
+                    _ => return Err(invalid()),
+                })
+            }
+            _ => return Err(format!("unknown option {flag}")),
+        }
+    }
+
+    // Without an explicit format, go by the output file's extension.
+    let json_file = options
+        .out
+        .as_ref()
+        .is_some_and(|path| path.extension().is_some_and(|ext| ext == "json"));
+    options.format = format.unwrap_or(if json_file { Format::Json } else { Format::Csv });
+    Ok(options)
+}
+
+fn build_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        LogPlugin {
+            level: Level::WARN,
+            // The player's sprite children expect the render plugins' visibility components.
+            filter: "bevy_ecs::hierarchy=error,bevy_asset::io::source=error".to_string(),
+            ..default()
+        },
+        AssetPlugin::default(),
+        StatesPlugin,
+    ))
+    .init_asset::<Mesh>()
+    .init_asset::<ColorMaterial>()
+    // Stays out of the game until the first run starts, so no tick runs without a run's
+    // resources.
+    .insert_state(MainState::Menu)
+    .add_plugins(simulation_plugin)
+    .init_resource::<SEVolume>()
+    // Nothing is drawn, so keep the cosmetic effects to a minimum.
+    .insert_resource(DisplayQuality::Low)
+    .insert_resource(HitSound(Handle::default()))
+    .insert_resource(HitSelfSound(Handle::default()))
+    .insert_resource(ShootSound(Handle::default()))
+    .insert_resource(ExperienceOrbSound(Handle::default()))
+    .insert_resource(BombSound(Handle::default()))
+    .insert_resource(DefeatSound(Handle::default()))
+    .add_systems(Startup, experience::setup)
+    .add_systems(
+        FixedUpdate,
+        autopilot::steer_autopilot
+            .before(feed_move_input)
+            .run_if(in_state(GameState::Playing)),
+    )
+    .add_systems(
+        Update,
+        autopilot::choose_powerup
+            .before(apply_powerup_selection)
+            .run_if(in_state(GameState::SelectingPowerUp)),
+    )
+    .add_systems(Last, discard_sounds);
+
+    // Exactly one fixed tick per update, however long the update took.
+    let tick = app.world().resource::<Time<Fixed>>().timestep();
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(tick));
+    app.finish();
+    app.cleanup();
+    app
+}
+
+/// Nothing plays the sound effects the simulation spawns.
+fn discard_sounds(mut commands: Commands, sounds: Query<Entity, With<AudioPlayer>>) {
+    for entity in &sounds {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Waits for the enemy catalog and the maps, which every run depends on.
+fn wait_for_game_data(app: &mut App) -> Result<(), String> {
+    let started = Instant::now();
+    while app.world().resource::<EnemyCatalog>().is_empty()
+        || app.world().resource::<WaveTimeline>().is_empty()
+        || !app.world().resource::<MapCatalog>().is_loaded()
+    {
+        if started.elapsed() > DATA_TIMEOUT {
+            return Err(
+                "the enemy catalog, wave timeline or a map did not load, see the log for errors"
+                    .to_string(),
+            );
+        }
+        app.update();
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    Ok(())
+}
+
+fn start_run(
+    In((difficulty, map, character, seed)): In<(Difficulty, SelectedMap, Character, u64)>,
+    mut commands: Commands,
+    level_entities: Query<Entity, With<LevelEntity>>,
+) {
+    commands.set_state(MainState::Game);
+    commands.insert_resource(difficulty);
+    // Runs start without shop upgrades, so results compare across machines. Characters
+    // don't need unlocking here.
+    reset_game(
+        &mut commands,
+        Some(&level_entities),
+        difficulty,
+        map,
+        character,
+        StartingBonuses::default(),
+        seed,
+    );
+}
+
+fn simulate_run(
+    app: &mut App,
+    difficulty: Difficulty,
+    map: SelectedMap,
+    character: Character,
+    seed: u64,
+    max_seconds: f32,
+) -> RunResult {
+    app.world_mut()
+        .run_system_cached_with(start_run, (difficulty, map, character, seed))
+        .expect("run setup failed");
+
+    let died = loop {
+        app.update();
+        let world = app.world();
+        if *world.resource::<State<GameState>>().get() == GameState::GameOver {
+            break true;
+        }
+        if world.resource::<PlayerStats>().time_survived >= max_seconds {
+            break false;
+        }
+    };
+
+    let result = run_result(app.world(), difficulty, map, character, seed, died);
+
+    // Leave the game so nothing else is simulated, and let the messages written on the last
+    // tick expire before the next run could read them.
+    app.world_mut()
+        .resource_mut::<NextState<MainState>>()
+        .set(MainState::Menu);
+    for _ in 0..3 {
+        app.update();
+    }
+    result
+}
+
+fn run_result(
+    world: &World,
+    difficulty: Difficulty,
+    map: SelectedMap,
+    character: Character,
+    seed: u64,
+    died: bool,
+) -> RunResult {
+    let stats = world.resource::<PlayerStats>();
+    RunResult {
+        seed,
+        difficulty,
+        map: map.0,
+        character: character.id(),
+        died,
+        time_survived: stats.time_survived,
+        score: world.resource::<Score>().0,
+        level: world.resource::<PowerUpProgress>().level(),
+        enemies_killed: stats.enemies_killed,
+        damage_taken: stats.damage_taken,
+        upgrades: world
+            .resource::<ActiveReplay>()
+            .replay()
+            .choices()
+            .iter()
+            .map(|choice| format!("{choice:?}"))
+            .collect(),
+    }
+}
+
+fn to_csv(results: &[RunResult]) -> String {
+    let mut csv = String::from(
+        "seed,difficulty,map,character,died,time_survived,score,level,enemies_killed,damage_taken,upgrades\n",
+    );
+    for result in results {
+        csv.push_str(&format!(
+            "{},{:?},{},{},{},{:.2},{},{},{},{},{}\n",
+            result.seed,
+            result.difficulty,
+            result.map,
+            result.character,
+            result.died,
+            result.time_survived,
+            result.score,
+            result.level,
+            result.enemies_killed,
+            result.damage_taken,
+            result.upgrades.join(" "),
+        ));
+    }
+    csv
+}
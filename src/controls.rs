@@ -0,0 +1,448 @@
+//! What each input action is bound to and how the player aims, set from the settings menu
+//! and kept in the save data like the volume settings.
+
+use std::collections::BTreeMap;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_pkv::PersistentResourceAppExtensions;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Keyboard keys an action can have, on top of one gamepad button.
+pub const KEYS_PER_ACTION: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum InputAction {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Pause,
+    Confirm,
+    Back,
+    NewGame,
+    RetrySameSeed,
+    WatchReplay,
+    QuitToMenu,
+}
+
+pub const ALL_INPUT_ACTIONS: [InputAction; 11] = [
+    InputAction::MoveUp,
+    InputAction::MoveDown,
+    InputAction::MoveLeft,
+    InputAction::MoveRight,
+    InputAction::Pause,
+    InputAction::Confirm,
+    InputAction::Back,
+    InputAction::NewGame,
+    InputAction::RetrySameSeed,
+    InputAction::WatchReplay,
+    InputAction::QuitToMenu,
+];
+
+impl InputAction {
+    pub fn label(self) -> &'static str {
+        match self {
+            InputAction::MoveUp => "Move Up",
+            InputAction::MoveDown => "Move Down",
+            InputAction::MoveLeft => "Move Left",
+            InputAction::MoveRight => "Move Right",
+            InputAction::Pause => "Pause",
+            InputAction::Confirm => "Confirm / Resume",
+            InputAction::Back => "Back",
+            InputAction::NewGame => "New Game",
+            InputAction::RetrySameSeed => "Retry Same Seed",
+            InputAction::WatchReplay => "Watch Replay",
+            InputAction::QuitToMenu => "Back to Menu",
+        }
+    }
+
+    fn default_binding(self) -> Binding {
+        let (keys, button) = match self {
+            InputAction::MoveUp => (
+                [Some(KeyCode::KeyW), Some(KeyCode::ArrowUp)],
+                Some(GamepadButton::DPadUp),
+            ),
+            InputAction::MoveDown => (
+                [Some(KeyCode::KeyS), Some(KeyCode::ArrowDown)],
+                Some(GamepadButton::DPadDown),
+            ),
+            InputAction::MoveLeft => (
+                [Some(KeyCode::KeyA), Some(KeyCode::ArrowLeft)],
+                Some(GamepadButton::DPadLeft),
+            ),
+            InputAction::MoveRight => (
+                [Some(KeyCode::KeyD), Some(KeyCode::ArrowRight)],
+                Some(GamepadButton::DPadRight),
+            ),
+            InputAction::Pause => ([Some(KeyCode::Escape), None], Some(GamepadButton::Start)),
+            InputAction::Confirm => ([Some(KeyCode::Enter), None], Some(GamepadButton::South)),
+            InputAction::Back => ([Some(KeyCode::Backspace), None], Some(GamepadButton::East)),
+            InputAction::NewGame => ([Some(KeyCode::KeyN), None], None),
+            InputAction::RetrySameSeed => ([Some(KeyCode::KeyR), None], None),
+            InputAction::WatchReplay => ([Some(KeyCode::KeyV), None], None),
+            InputAction::QuitToMenu => ([Some(KeyCode::KeyQ), None], None),
+        };
+        Binding { keys, button }
+    }
+
+    /// Whether the action is read while a run is being played.
+    fn in_gameplay(self) -> bool {
+        matches!(
+            self,
+            InputAction::MoveUp
+                | InputAction::MoveDown
+                | InputAction::MoveLeft
+                | InputAction::MoveRight
+                | InputAction::Pause
+        )
+    }
+
+    /// Whether the action is read on menu screens. Pause also closes the pause menu.
+    fn in_menus(self) -> bool {
+        !self.in_gameplay() || self == InputAction::Pause
+    }
+
+    /// Two actions may share an input only if they are never read at the same time.
+    fn overlaps(self, other: InputAction) -> bool {
+        (self.in_gameplay() && other.in_gameplay()) || (self.in_menus() && other.in_menus())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Binding {
+    pub keys: [Option<KeyCode>; KEYS_PER_ACTION],
+    pub button: Option<GamepadButton>,
+}
+
+/// One place an input can be bound to: a key slot or the gamepad button of an action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BindingSlot {
+    Key(usize),
+    Button,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoundInput {
+    Key(KeyCode),
+    Button(GamepadButton),
+}
+
+#[derive(Debug, Error)]
+pub enum BindError {
+    #[error("{input} is already used by {}", .action.label())]
+    Conflict { input: String, action: InputAction },
+    #[error("the D-pad is reserved for moving between buttons in menus")]
+    ReservedForNavigation,
+    #[error("key slots take keys and gamepad slots take gamepad buttons")]
+    WrongDevice,
+}
+
+/// Bindings of every action. Actions missing from the saved data use their defaults.
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+pub struct Controls {
+    bindings: BTreeMap<InputAction, Binding>,
+}
+
+impl Controls {
+    pub fn binding(&self, action: InputAction) -> Binding {
+        self.bindings
+            .get(&action)
+            .copied()
+            .unwrap_or_else(|| action.default_binding())
+    }
+
+    /// Binds `input` to `slot` of `action`, unless another action read at the same time
+    /// already uses it.
+    pub fn bind(
+        &mut self,
+        action: InputAction,
+        slot: BindingSlot,
+        input: BoundInput,
+    ) -> Result<(), BindError> {
+        if matches!(input, BoundInput::Key(_)) != matches!(slot, BindingSlot::Key(_)) {
+            return Err(BindError::WrongDevice);
+        }
+        if let BoundInput::Button(button) = input
+            && action.in_menus()
+            && is_dpad(button)
+        {
+            return Err(BindError::ReservedForNavigation);
+        }
+        for other in ALL_INPUT_ACTIONS {
+            if other == action || !other.overlaps(action) {
+                continue;
+            }
+            let binding = self.binding(other);
+            let taken = match input {
+                BoundInput::Key(key) => binding.keys.contains(&Some(key)),
+                BoundInput::Button(button) => binding.button == Some(button),
+            };
+            if taken {
+                return Err(BindError::Conflict {
+                    input: input_label(input),
+                    action: other,
+                });
+            }
+        }
+
+        let mut binding = self.binding(action);
+        match (slot, input) {
+            (BindingSlot::Key(index), BoundInput::Key(key)) => {
+                // Binding a key already in the other slot moves it instead of doubling it.
+                for other_key in &mut binding.keys {
+                    if *other_key == Some(key) {
+                        *other_key = None;
+                    }
+                }
+                binding.keys[index] = Some(key);
+            }
+            (BindingSlot::Button, BoundInput::Button(button)) => binding.button = Some(button),
+            _ => unreachable!("checked above"),
+        }
+        self.bindings.insert(action, binding);
+        Ok(())
+    }
+
+    pub fn reset(&mut self) {
+        self.bindings.clear();
+    }
+
+    /// Short text naming the keys of `action`, for on-screen hints.
+    pub fn key_hint(&self, action: InputAction) -> String {
+        let keys: Vec<String> = self
+            .binding(action)
+            .keys
+            .into_iter()
+            .flatten()
+            .map(key_label)
+            .collect();
+        if keys.is_empty() {
+            "-".to_string()
+        } else {
+            keys.join(" / ")
+        }
+    }
+}
+
+fn is_dpad(button: GamepadButton) -> bool {
+    matches!(
+        button,
+        GamepadButton::DPadUp
+            | GamepadButton::DPadDown
+            | GamepadButton::DPadLeft
+            | GamepadButton::DPadRight
+    )
+}
+
+pub fn input_label(input: BoundInput) -> String {
+    match input {
+        BoundInput::Key(key) => key_label(key),
+        BoundInput::Button(button) => button_label(button),
+    }
+}
+
+pub fn key_label(key: KeyCode) -> String {
+    let name = format!("{key:?}");
+    match key {
+        KeyCode::ArrowUp => "↑".to_string(),
+        KeyCode::ArrowDown => "↓".to_string(),
+        KeyCode::ArrowLeft => "←".to_string(),
+        KeyCode::ArrowRight => "→".to_string(),
+        KeyCode::Escape => "Esc".to_string(),
+        _ => name
+            .strip_prefix("Key")
+            .or_else(|| name.strip_prefix("Digit"))
+            .unwrap_or(&name)
+            .to_string(),
+    }
+}
+
+/// Names the face buttons after an Xbox pad, which most pads follow.
+pub fn button_label(button: GamepadButton) -> String {
+    match button {
+        GamepadButton::South => "A".to_string(),
+        GamepadButton::East => "B".to_string(),
+        GamepadButton::West => "X".to_string(),
+        GamepadButton::North => "Y".to_string(),
+        GamepadButton::LeftTrigger => "LB".to_string(),
+        GamepadButton::RightTrigger => "RB".to_string(),
+        GamepadButton::LeftTrigger2 => "LT".to_string(),
+        GamepadButton::RightTrigger2 => "RT".to_string(),
+        GamepadButton::DPadUp => "D-pad Up".to_string(),
+        GamepadButton::DPadDown => "D-pad Down".to_string(),
+        GamepadButton::DPadLeft => "D-pad Left".to_string(),
+        GamepadButton::DPadRight => "D-pad Right".to_string(),
+        other => format!("{other:?}"),
+    }
+}
+
+/// Reads actions through the current bindings, from the keyboard and every gamepad.
+#[derive(SystemParam)]
+pub struct ActionInput<'w, 's> {
+    controls: Res<'w, Controls>,
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    gamepads: Query<'w, 's, &'static Gamepad>,
+}
+
+impl ActionInput<'_, '_> {
+    pub fn pressed(&self, action: InputAction) -> bool {
+        let binding = self.controls.binding(action);
+        binding
+            .keys
+            .iter()
+            .flatten()
+            .any(|key| self.keys.pressed(*key))
+            || binding
+                .button
+                .is_some_and(|button| self.gamepads.iter().any(|pad| pad.pressed(button)))
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.key_just_pressed(action) || self.button_just_pressed(action)
+    }
+
+    pub fn key_just_pressed(&self, action: InputAction) -> bool {
+        let binding = self.controls.binding(action);
+        binding
+            .keys
+            .iter()
+            .flatten()
+            .any(|key| self.keys.just_pressed(*key))
+    }
+
+    pub fn button_just_pressed(&self, action: InputAction) -> bool {
+        self.controls
+            .binding(action)
+            .button
+            .is_some_and(|button| self.gamepads.iter().any(|pad| pad.just_pressed(button)))
+    }
+
+    pub fn controls(&self) -> &Controls {
+        &self.controls
+    }
+
+    pub fn gamepads(&self) -> impl Iterator<Item = &Gamepad> {
+        self.gamepads.iter()
+    }
+}
+
+/// How the weapons that shoot in a direction pick it.
+#[derive(
+    Resource, Debug, Component, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize,
+)]
+pub enum AimMode {
+    /// At the nearest enemy.
+    #[default]
+    Auto,
+    /// Towards the mouse cursor or wherever the right stick points.
+    Manual,
+}
+
+/// The binding slot waiting for an input on the controls screen.
+#[derive(Resource, Default)]
+pub struct Rebinding(pub Option<(InputAction, BindingSlot)>);
+
+/// Run condition: false while the controls screen is waiting for an input, so the input
+/// isn't also taken as a menu command.
+pub fn not_rebinding(rebinding: Res<Rebinding>) -> bool {
+    rebinding.0.is_none()
+}
+
+pub fn plugin(app: &mut App) {
+    app.init_persistent_resource::<Controls>()
+        .init_persistent_resource::<AimMode>()
+        .init_resource::<Rebinding>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind_rejects_key_used_by_overlapping_action() {
+        let mut controls = Controls::default();
+        let result = controls.bind(
+            InputAction::MoveUp,
+            BindingSlot::Key(1),
+            BoundInput::Key(KeyCode::KeyS),
+        );
+        assert!(matches!(
+            result,
+            Err(BindError::Conflict {
+                action: InputAction::MoveDown,
+                ..
+            })
+        ));
+        assert_eq!(
+            controls.binding(InputAction::MoveUp),
+            InputAction::MoveUp.default_binding()
+        );
+    }
+
+    #[test]
+    fn default_keys_are_unique() {
+        let mut seen = Vec::new();
+        for action in ALL_INPUT_ACTIONS {
+            for key in action.default_binding().keys.into_iter().flatten() {
+                assert!(!seen.contains(&key), "{key:?} is bound twice by default");
+                seen.push(key);
+            }
+        }
+    }
+
+    #[test]
+    fn bind_allows_key_shared_with_action_read_elsewhere() {
+        // Back to Menu is only read on menu screens, so Move Up can share its key.
+        let mut controls = Controls::default();
+        controls
+            .bind(
+                InputAction::MoveUp,
+                BindingSlot::Key(1),
+                BoundInput::Key(KeyCode::KeyQ),
+            )
+            .unwrap();
+        assert_eq!(
+            controls.binding(InputAction::MoveUp).keys,
+            [Some(KeyCode::KeyW), Some(KeyCode::KeyQ)]
+        );
+    }
+
+    #[test]
+    fn bind_rejects_dpad_for_menu_actions_and_wrong_device() {
+        let mut controls = Controls::default();
+        assert!(matches!(
+            controls.bind(
+                InputAction::Confirm,
+                BindingSlot::Button,
+                BoundInput::Button(GamepadButton::DPadUp),
+            ),
+            Err(BindError::ReservedForNavigation)
+        ));
+        assert!(matches!(
+            controls.bind(
+                InputAction::Confirm,
+                BindingSlot::Key(0),
+                BoundInput::Button(GamepadButton::North),
+            ),
+            Err(BindError::WrongDevice)
+        ));
+    }
+
+    #[test]
+    fn bind_moves_key_between_slots_of_same_action() {
+        let mut controls = Controls::default();
+        controls
+            .bind(
+                InputAction::MoveUp,
+                BindingSlot::Key(0),
+                BoundInput::Key(KeyCode::ArrowUp),
+            )
+            .unwrap();
+        assert_eq!(
+            controls.binding(InputAction::MoveUp).keys,
+            [Some(KeyCode::ArrowUp), None]
+        );
+    }
+}
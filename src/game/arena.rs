@@ -0,0 +1,430 @@
+//! The world the run takes place in: the map picked in the menu, the part of it on screen,
+//! and the camera that follows the player around it.
+
+use std::collections::BTreeMap;
+use std::f32::consts::TAU;
+
+use bevy::camera::ScalingMode;
+use bevy::prelude::*;
+use rand::Rng;
+
+use super::constants::VIEW_HALF_SIZE;
+use super::obstacles::{Obstacle, steer_around};
+use super::player::Player;
+use crate::MainState;
+
+/// Distance between the lines drawn on the floor.
+const FLOOR_GRID_SPACING: f32 = 200.0;
+const FLOOR_LINE_WIDTH: f32 = 2.0;
+const FLOOR_LINE_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.05);
+const WALL_WIDTH: f32 = 12.0;
+const WALL_COLOR: Color = Color::srgba(0.8, 0.3, 0.3, 0.6);
+/// How far outside the view enemies appear.
+const SPAWN_MARGIN: f32 = 40.0;
+/// Tries at finding a spawn point that is off screen, inside the bounds and clear of
+/// obstacles, before settling for the last one.
+const SPAWN_ATTEMPTS: u32 = 8;
+
+/// Maps shipped with the game, as `(id, file)`, in the order the menu lists them. The id is
+/// what replays and the simulator's `--map` refer to.
+pub const MAPS: [(&str, &str); 3] = [
+    ("square", "data/maps/square.map.ron"),
+    ("circle", "data/maps/circle.map.ron"),
+    ("corridor", "data/maps/corridor.map.ron"),
+];
+
+/// The map runs are played on. Set from the main menu, or by a replay to the map it was
+/// recorded on.
+#[derive(Resource, Debug, Component, PartialEq, Eq, Clone, Copy)]
+pub struct SelectedMap(pub &'static str);
+
+impl Default for SelectedMap {
+    fn default() -> Self {
+        Self(MAPS[0].0)
+    }
+}
+
+impl SelectedMap {
+    pub fn from_id(id: &str) -> Option<Self> {
+        MAPS.iter()
+            .find(|(map_id, _)| *map_id == id)
+            .map(|(map_id, _)| Self(map_id))
+    }
+}
+
+/// Edge of the playable area. The player and enemies stay inside it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArenaBounds {
+    Rect { half_size: Vec2 },
+    Circle { radius: f32 },
+}
+
+impl Default for ArenaBounds {
+    fn default() -> Self {
+        ArenaBounds::Rect {
+            half_size: Vec2::splat(3000.0),
+        }
+    }
+}
+
+impl ArenaBounds {
+    /// Moves a circle at `position` back inside the bounds.
+    pub fn clamp(&self, position: Vec2, radius: f32) -> Vec2 {
+        match *self {
+            ArenaBounds::Rect { half_size } => {
+                let inner = (half_size - radius).max(Vec2::ZERO);
+                position.clamp(-inner, inner)
+            }
+            ArenaBounds::Circle { radius: bounds } => {
+                position.clamp_length_max((bounds - radius).max(0.0))
+            }
+        }
+    }
+
+    pub fn contains(&self, position: Vec2) -> bool {
+        self.clamp(position, 0.0) == position
+    }
+
+    /// Distance from `position` to the nearest edge, and the direction pointing back in
+    /// from it.
+    pub fn edge(&self, position: Vec2) -> (f32, Vec2) {
+        match *self {
+            ArenaBounds::Rect { half_size } => {
+                let room = half_size - position.abs();
+                if room.x < room.y {
+                    (room.x, Vec2::new(-position.x.signum(), 0.0))
+                } else {
+                    (room.y, Vec2::new(0.0, -position.y.signum()))
+                }
+            }
+            ArenaBounds::Circle { radius } => {
+                (radius - position.length(), -position.normalize_or(Vec2::Y))
+            }
+        }
+    }
+
+    /// Half extents of the box around the bounds.
+    pub fn half_extents(&self) -> Vec2 {
+        match *self {
+            ArenaBounds::Rect { half_size } => half_size,
+            ArenaBounds::Circle { radius } => Vec2::splat(radius),
+        }
+    }
+}
+
+/// A circle enemies may spawn in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpawnZone {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+impl SpawnZone {
+    fn random_point(&self, rng: &mut impl Rng) -> Vec2 {
+        let angle = rng.random_range(0.0..TAU);
+        // Square root keeps points evenly spread over the disc.
+        let distance = self.radius * rng.random_range(0.0f32..=1.0).sqrt();
+        self.center + Vec2::new(angle.cos(), angle.sin()) * distance
+    }
+}
+
+/// The map of the current run, copied from [`MapCatalog`] whenever [`SelectedMap`] or the
+/// map files change.
+#[derive(Resource, Clone, Debug)]
+pub struct ArenaMap {
+    pub name: String,
+    pub bounds: ArenaBounds,
+    pub background: Color,
+    /// Where enemies spawn. Empty spawns them anywhere just outside the view.
+    pub spawn_zones: Vec<SpawnZone>,
+    /// Multipliers on the enemy catalog's spawn weights, by enemy name.
+    pub enemy_weights: BTreeMap<String, f32>,
+    pub obstacles: Vec<Obstacle>,
+}
+
+impl Default for ArenaMap {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            bounds: ArenaBounds::default(),
+            background: Color::srgb(0.1, 0.1, 0.15),
+            spawn_zones: Vec::new(),
+            enemy_weights: BTreeMap::new(),
+            obstacles: Vec::new(),
+        }
+    }
+}
+
+impl ArenaMap {
+    pub fn enemy_weight(&self, name: &str) -> f32 {
+        self.enemy_weights.get(name).copied().unwrap_or(1.0)
+    }
+
+    /// Whether a projectile at `position` has flown into an obstacle.
+    pub fn blocks_projectile(&self, position: Vec2) -> bool {
+        self.overlaps(position, super::obstacles::PROJECTILE_RADIUS)
+    }
+
+    /// Whether a circle at `position` touches any obstacle.
+    pub fn overlaps(&self, position: Vec2, radius: f32) -> bool {
+        self.obstacles
+            .iter()
+            .any(|obstacle| obstacle.separation(position).0 < radius)
+    }
+
+    /// Moves a circle at `position` out of every obstacle it overlaps. Obstacles are walked in
+    /// map order, so overlapping ones resolve the same way when a run is replayed.
+    pub fn push_out(&self, mut position: Vec2, radius: f32) -> Vec2 {
+        for obstacle in &self.obstacles {
+            let (distance, normal) = obstacle.separation(position);
+            if distance < radius {
+                position += normal * (radius - distance);
+            }
+        }
+        position
+    }
+
+    /// Turns `velocity` along the edge of any obstacle it is about to run into, keeping its
+    /// speed, so a circle at `position` slides around instead of stopping against it.
+    pub fn steer_around(&self, position: Vec2, radius: f32, velocity: Vec2) -> Vec2 {
+        steer_around(&self.obstacles, position, radius, velocity)
+    }
+
+    /// How far a ray from `origin` along `direction` (a unit vector) travels before hitting
+    /// an obstacle, up to `max_distance`.
+    pub fn ray_distance(&self, origin: Vec2, direction: Vec2, max_distance: f32) -> f32 {
+        self.obstacles
+            .iter()
+            .filter_map(|obstacle| obstacle.ray_distance(origin, direction))
+            .fold(max_distance, f32::min)
+    }
+
+    /// A random point for an enemy to walk in from: in a spawn zone or just outside the view,
+    /// inside the bounds and clear of obstacles. Zones that are off screen are preferred.
+    pub fn random_spawn_point(&self, view: &CameraView, rng: &mut impl Rng) -> Vec2 {
+        let hidden_zones: Vec<&SpawnZone> = self
+            .spawn_zones
+            .iter()
+            .filter(|zone| !view.shows(zone.center, zone.radius))
+            .collect();
+        let zones = if hidden_zones.is_empty() {
+            self.spawn_zones.iter().collect()
+        } else {
+            hidden_zones
+        };
+
+        let mut point = view.center;
+        for _ in 0..SPAWN_ATTEMPTS {
+            let candidate = if zones.is_empty() {
+                view.random_point_outside(rng)
+            } else {
+                zones[rng.random_range(0..zones.len())].random_point(rng)
+            };
+            point = self.bounds.clamp(candidate, SPAWN_MARGIN);
+            if !view.shows(point, 0.0) && !self.overlaps(point, SPAWN_MARGIN) {
+                break;
+            }
+        }
+        point
+    }
+}
+
+/// Every map in [`MAPS`] that has loaded so far, by id.
+#[derive(Resource, Default)]
+pub struct MapCatalog {
+    maps: BTreeMap<&'static str, ArenaMap>,
+}
+
+impl MapCatalog {
+    pub fn get(&self, id: &str) -> Option<&ArenaMap> {
+        self.maps.get(id)
+    }
+
+    pub fn insert(&mut self, id: &'static str, map: ArenaMap) {
+        self.maps.insert(id, map);
+    }
+
+    /// True once every map has loaded.
+    pub fn is_loaded(&self) -> bool {
+        self.maps.len() == MAPS.len()
+    }
+
+    /// Name to show for a map, falling back to its id until it has loaded.
+    pub fn display_name(&self, id: &'static str) -> &str {
+        self.get(id).map_or(id, |map| map.name.as_str())
+    }
+}
+
+/// Runs before the fixed-timestep loop, so a run always ticks on the map it was started on.
+pub fn apply_selected_map(
+    selected: Res<SelectedMap>,
+    catalog: Res<MapCatalog>,
+    mut map: ResMut<ArenaMap>,
+) {
+    if let Some(selected_map) = catalog.get(selected.0) {
+        *map = selected_map.clone();
+    }
+}
+
+/// How the view follows the player. The player moves freely inside the dead zone around its
+/// centre; past it, the view catches up, closing most of the gap in `lag` seconds.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct CameraFollow {
+    /// Half extents of the dead zone.
+    pub dead_zone: Vec2,
+    /// Seconds for the view to close 63% of the distance the player is past the dead zone.
+    /// Zero keeps the player pinned to the edge of the dead zone.
+    pub lag: f32,
+}
+
+impl Default for CameraFollow {
+    fn default() -> Self {
+        Self {
+            dead_zone: Vec2::new(80.0, 60.0),
+            lag: 0.2,
+        }
+    }
+}
+
+/// Centre of the part of the world on screen. It moves with the simulation rather than the
+/// camera, so enemies spawn at the same place in replays and in the headless simulator.
+#[derive(Resource, Default)]
+pub struct CameraView {
+    pub center: Vec2,
+}
+
+impl CameraView {
+    /// A random point just outside the view.
+    pub fn random_point_outside(&self, rng: &mut impl Rng) -> Vec2 {
+        let half = VIEW_HALF_SIZE + SPAWN_MARGIN;
+        let offset = match rng.random_range(0..4) {
+            0 => Vec2::new(-half.x, rng.random_range(-half.y..=half.y)),
+            1 => Vec2::new(half.x, rng.random_range(-half.y..=half.y)),
+            2 => Vec2::new(rng.random_range(-half.x..=half.x), -half.y),
+            _ => Vec2::new(rng.random_range(-half.x..=half.x), half.y),
+        };
+        self.center + offset
+    }
+
+    /// Whether any of a circle at `position` can be on screen.
+    pub fn shows(&self, position: Vec2, radius: f32) -> bool {
+        let offset = (position - self.center).abs();
+        offset.x < VIEW_HALF_SIZE.x + radius && offset.y < VIEW_HALF_SIZE.y + radius
+    }
+}
+
+/// Last movement system of a tick, once the player is where it ends up.
+pub fn follow_player(
+    time: Res<Time>,
+    follow: Res<CameraFollow>,
+    mut view: ResMut<CameraView>,
+    player: Single<&Transform, With<Player>>,
+) {
+    let offset = player.translation.xy() - view.center;
+    let past_dead_zone = offset - offset.clamp(-follow.dead_zone, follow.dead_zone);
+    let catch_up = if follow.lag > 0.0 {
+        1.0 - (-time.delta_secs() / follow.lag).exp()
+    } else {
+        1.0
+    };
+    view.center += past_dead_zone * catch_up;
+}
+
+pub fn move_camera_to_view(
+    view: Res<CameraView>,
+    mut camera: Single<&mut Transform, With<Camera2d>>,
+) {
+    camera.translation = view.center.extend(camera.translation.z);
+}
+
+pub fn reset_camera(mut camera: Single<(&mut Transform, &mut Camera), With<Camera2d>>) {
+    let (transform, camera) = &mut *camera;
+    transform.translation = Vec3::new(0.0, 0.0, transform.translation.z);
+    camera.clear_color = ClearColorConfig::Default;
+}
+
+/// Shows at most `VIEW_HALF_SIZE` of the world whatever the window size, so spawns just
+/// outside the view are never on screen.
+pub fn view_projection() -> Projection {
+    Projection::Orthographic(OrthographicProjection {
+        scaling_mode: ScalingMode::AutoMax {
+            max_width: VIEW_HALF_SIZE.x * 2.0,
+            max_height: VIEW_HALF_SIZE.y * 2.0,
+        },
+        ..OrthographicProjection::default_2d()
+    })
+}
+
+/// Parts of the floor and the walls around it, redrawn when the map changes.
+#[derive(Component)]
+pub struct FloorSprite;
+
+/// Grid lines on the floor, so movement shows while the camera follows the player, the walls
+/// along the bounds, and the map's background colour.
+pub fn draw_floor(
+    mut commands: Commands,
+    map: Res<ArenaMap>,
+    sprites: Query<Entity, With<FloorSprite>>,
+    mut camera: Single<&mut Camera, With<Camera2d>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !map.is_changed() && !sprites.is_empty() {
+        return;
+    }
+    for entity in &sprites {
+        commands.entity(entity).despawn();
+    }
+    camera.clear_color = ClearColorConfig::Custom(map.background);
+
+    let mut spawn_sprite = |position: Vec2, size: Vec2, color: Color, z: f32| {
+        commands.spawn((
+            DespawnOnExit(MainState::Game),
+            FloorSprite,
+            Sprite::from_color(color, size),
+            Transform::from_translation(position.extend(z)),
+        ));
+    };
+
+    // Grid lines, cut to the bounds.
+    let extents = map.bounds.half_extents();
+    for (axis, cross) in [(0, 1), (1, 0)] {
+        let lines = (extents[axis] / FLOOR_GRID_SPACING) as i32;
+        for step in -lines..=lines {
+            let along = step as f32 * FLOOR_GRID_SPACING;
+            let half_length = match map.bounds {
+                ArenaBounds::Rect { half_size } => half_size[cross],
+                ArenaBounds::Circle { radius } => (radius * radius - along * along).sqrt(),
+            };
+            let mut position = Vec2::ZERO;
+            position[axis] = along;
+            let mut size = Vec2::splat(FLOOR_LINE_WIDTH);
+            size[cross] = half_length * 2.0;
+            spawn_sprite(position, size, FLOOR_LINE_COLOR, -1.0);
+        }
+    }
+
+    match map.bounds {
+        ArenaBounds::Rect { half_size } => {
+            let edge = half_size + WALL_WIDTH * 0.5;
+            let length = half_size * 2.0 + WALL_WIDTH * 2.0;
+            for (position, size) in [
+                (Vec2::new(-edge.x, 0.0), Vec2::new(WALL_WIDTH, length.y)),
+                (Vec2::new(edge.x, 0.0), Vec2::new(WALL_WIDTH, length.y)),
+                (Vec2::new(0.0, -edge.y), Vec2::new(length.x, WALL_WIDTH)),
+                (Vec2::new(0.0, edge.y), Vec2::new(length.x, WALL_WIDTH)),
+            ] {
+                spawn_sprite(position, size, WALL_COLOR, -0.9);
+            }
+        }
+        ArenaBounds::Circle { radius } => {
+            commands.spawn((
+                DespawnOnExit(MainState::Game),
+                FloorSprite,
+                Mesh2d(meshes.add(Annulus::new(radius, radius + WALL_WIDTH))),
+                MeshMaterial2d(materials.add(WALL_COLOR)),
+                Transform::from_translation(Vec3::new(0.0, 0.0, -0.9)),
+            ));
+        }
+    }
+}
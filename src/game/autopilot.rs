@@ -0,0 +1,80 @@
+//! A simple computer player for the headless simulator: it keeps away from enemies and the
+//! arena walls, slides around obstacles, collects experience when nothing is close, and
+//! picks power-ups by a fixed priority.
+
+use bevy::prelude::*;
+
+use super::arena::ArenaMap;
+use super::components::EnemyProjectile;
+use super::events::PowerUpSelected;
+use super::obstacles::PLAYER_RADIUS;
+use super::player::{MoveIntent, Player};
+use super::powerup::{PowerUpChoice, PowerUpOffer};
+use super::spatial::SpatialIndex;
+
+/// Enemies and enemy shots closer than this push the autopilot away.
+const DANGER_RADIUS: f32 = 160.0;
+/// Distance from the walls at which the autopilot starts steering back to the middle.
+const WALL_MARGIN: f32 = 120.0;
+const ORB_ATTRACTION: f32 = 0.6;
+
+/// Runs at the start of every tick, before the intent is turned into movement input.
+pub fn steer_autopilot(
+    mut intent: ResMut<MoveIntent>,
+    player: Single<&Transform, With<Player>>,
+    index: Res<SpatialIndex>,
+    enemy_projectiles: Query<&Transform, With<EnemyProjectile>>,
+    map: Res<ArenaMap>,
+) {
+    let position = player.translation.xy();
+
+    let threats = index
+        .enemies
+        .candidates(position, DANGER_RADIUS + index.enemies.max_radius())
+        .map(|entry| entry.position)
+        .chain(
+            enemy_projectiles
+                .iter()
+                .map(|transform| transform.translation.xy()),
+        );
+    let mut danger = Vec2::ZERO;
+    for threat in threats {
+        let away = position - threat;
+        let distance = away.length();
+        if distance < DANGER_RADIUS {
+            let closeness = 1.0 - distance / DANGER_RADIUS;
+            danger += away.normalize_or(Vec2::X) * closeness * closeness;
+        }
+    }
+
+    let mut steer = danger;
+    let (to_edge, inward) = map.bounds.edge(position);
+    if to_edge < WALL_MARGIN {
+        steer += inward * (WALL_MARGIN - to_edge) / WALL_MARGIN;
+    }
+    if danger.length_squared() < 0.01
+        && let Some(orb) = index.orbs.nearest(position, |_| true)
+    {
+        steer += (orb.position - position).normalize_or_zero() * ORB_ATTRACTION;
+    }
+
+    intent.0 = map
+        .steer_around(position, PLAYER_RADIUS, steer.normalize_or_zero())
+        .normalize_or_zero();
+}
+
+/// New weapons first, then the lowest-level weapon, then infusions, then crits, then
+/// movement speed.
+pub fn choose_powerup(offer: Res<PowerUpOffer>, mut selections: MessageWriter<PowerUpSelected>) {
+    let best = offer.0.iter().min_by_key(|(choice, level)| match choice {
+        PowerUpChoice::NewWeapon(_) => (0, 0),
+        PowerUpChoice::LevelUp(_) => (1, *level),
+        PowerUpChoice::Infusion(_) => (2, *level),
+        PowerUpChoice::CritChance | PowerUpChoice::CritDamage => (3, *level),
+        PowerUpChoice::MoveSpeed => (4, *level),
+        PowerUpChoice::Reroll => (5, *level),
+    });
+    if let Some(&(choice, _)) = best {
+        selections.write(PowerUpSelected(choice));
+    }
+}
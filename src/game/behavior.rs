@@ -0,0 +1,386 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::components::{EnemyProjectile, LevelEntity, Lifetime, Velocity};
+use super::enemy::{Enemy, EnemyAttributes, spawn_enemy};
+use super::events::EnemyKilled;
+use super::player::Player;
+use super::rng::RunRng;
+use super::status::StatusEffects;
+use crate::MainState;
+
+const CHARGER_TELEGRAPH_SCALE: f32 = 1.35;
+const CHARGER_RECOVER_SPEED_FACTOR: f32 = 0.4;
+const ORBITER_RADIAL_GAIN: f32 = 2.0;
+const RANGED_DISTANCE_TOLERANCE: f32 = 24.0;
+const RANGED_STRAFE_FACTOR: f32 = 0.35;
+const ENEMY_PROJECTILE_SIZE: Vec2 = Vec2::splat(8.0);
+const ENEMY_PROJECTILE_COLOR: Color = Color::srgb(0.85, 0.35, 1.0);
+const ENEMY_PROJECTILE_LIFETIME: f32 = 3.0;
+const SPLIT_SIZE_FACTOR: f32 = 0.6;
+const SPLIT_SPEED_FACTOR: f32 = 1.25;
+const SPLIT_SCATTER_SPEED: f32 = 160.0;
+
+/// How an enemy moves and attacks. Selected per prototype in the enemy catalog.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum EnemyBehavior {
+    /// Walks straight at the player.
+    #[default]
+    Chase,
+    /// Chases until within `range`, winds up for `windup` seconds, then dashes in a straight
+    /// line.
+    Charger {
+        range: f32,
+        windup: f32,
+        dash_speed: f32,
+        dash_duration: f32,
+        cooldown: f32,
+    },
+    /// Circles the player at `radius`, completing `angular_speed` radians per second.
+    Orbiter { radius: f32, angular_speed: f32 },
+    /// Chases, and breaks into `count` smaller chasers on death.
+    Splitter { count: u32 },
+    /// Keeps `preferred_distance` from the player and fires projectiles every `fire_interval`.
+    Ranged {
+        preferred_distance: f32,
+        fire_interval: f32,
+        projectile_speed: f32,
+        projectile_damage: i32,
+    },
+}
+
+impl EnemyBehavior {
+    /// Returns the name of the first parameter that is out of range, if any.
+    pub fn invalid_parameter(&self) -> Option<&'static str> {
+        let positive = |value: f32| value.is_finite() && value > 0.0;
+        match *self {
+            EnemyBehavior::Chase => None,
+            EnemyBehavior::Charger {
+                range,
+                windup,
+                dash_speed,
+                dash_duration,
+                cooldown,
+            } => [
+                ("range", range),
+                ("windup", windup),
+                ("dash_speed", dash_speed),
+                ("dash_duration", dash_duration),
+                ("cooldown", cooldown),
+            ]
+            .into_iter()
+            .find(|(_, value)| !positive(*value))
+            .map(|(name, _)| name),
+            EnemyBehavior::Orbiter {
+                radius,
+                angular_speed,
+            } => {
+                if !positive(radius) {
+                    Some("radius")
+                } else if !angular_speed.is_finite() {
+                    Some("angular_speed")
+                } else {
+                    None
+                }
+            }
+            EnemyBehavior::Splitter { count } => (count == 0).then_some("count"),
+            EnemyBehavior::Ranged {
+                preferred_distance,
+                fire_interval,
+                projectile_speed,
+                projectile_damage,
+            } => {
+                if !positive(preferred_distance) {
+                    Some("preferred_distance")
+                } else if !positive(fire_interval) {
+                    Some("fire_interval")
+                } else if !positive(projectile_speed) {
+                    Some("projectile_speed")
+                } else if projectile_damage < 0 {
+                    Some("projectile_damage")
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// Runtime state of a [`EnemyBehavior::Charger`].
+#[derive(Component, Default)]
+pub enum ChargeState {
+    #[default]
+    Approach,
+    Windup(Timer),
+    Dash {
+        timer: Timer,
+        direction: Vec2,
+    },
+    Recover(Timer),
+}
+
+/// Fire cooldown of a [`EnemyBehavior::Ranged`] enemy.
+#[derive(Component)]
+pub struct RangedAttack {
+    pub timer: Timer,
+}
+
+/// Which way a [`EnemyBehavior::Orbiter`] circles the player, `1.0` or `-1.0`.
+#[derive(Component)]
+pub struct OrbitSpin(pub f32);
+
+/// Inserts the runtime state a behavior needs on a freshly spawned enemy.
+pub fn insert_behavior_state(
+    entity: &mut EntityCommands,
+    behavior: EnemyBehavior,
+    rng: &mut impl Rng,
+) {
+    match behavior {
+        EnemyBehavior::Charger { .. } => {
+            entity.insert(ChargeState::default());
+        }
+        EnemyBehavior::Ranged { fire_interval, .. } => {
+            entity.insert(RangedAttack {
+                timer: Timer::from_seconds(fire_interval, TimerMode::Repeating),
+            });
+        }
+        EnemyBehavior::Orbiter { .. } => {
+            // Mix orbit directions between enemies so they don't all bunch up.
+            entity.insert(OrbitSpin(if rng.random_bool(0.5) { 1.0 } else { -1.0 }));
+        }
+        EnemyBehavior::Chase | EnemyBehavior::Splitter { .. } => {}
+    }
+}
+
+/// Sets each enemy's velocity from its behavior. Repulsion is applied on top afterwards.
+pub fn steer_enemies(
+    time: Res<Time>,
+    mut enemies: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &EnemyAttributes,
+            &EnemyBehavior,
+            Option<&mut ChargeState>,
+            Option<&OrbitSpin>,
+        ),
+        With<Enemy>,
+    >,
+    player: Query<&Transform, (With<Player>, Without<Enemy>)>,
+) {
+    let Ok(player_transform) = player.single() else {
+        return;
+    };
+    let player_pos = player_transform.translation.xy();
+
+    for (mut transform, mut velocity, attributes, behavior, charge_state, orbit_spin) in
+        &mut enemies
+    {
+        let to_player = player_pos - transform.translation.xy();
+        let distance = to_player.length();
+        let dir = to_player.normalize_or_zero();
+
+        velocity.0 = match *behavior {
+            EnemyBehavior::Chase | EnemyBehavior::Splitter { .. } => dir * attributes.speed,
+            EnemyBehavior::Charger {
+                range,
+                windup,
+                dash_speed,
+                dash_duration,
+                cooldown,
+            } => {
+                let Some(mut state) = charge_state else {
+                    continue;
+                };
+                steer_charger(
+                    &mut state,
+                    &mut transform,
+                    time.delta(),
+                    dir,
+                    distance,
+                    attributes.speed,
+                    (range, windup, dash_speed, dash_duration, cooldown),
+                )
+            }
+            EnemyBehavior::Orbiter {
+                radius,
+                angular_speed,
+            } => {
+                let Some(&OrbitSpin(spin)) = orbit_spin else {
+                    continue;
+                };
+                let tangent = dir.perp() * spin;
+                let tangential_speed = (angular_speed.abs() * radius).min(attributes.speed);
+                let radial_error = ((distance - radius) / radius).clamp(-1.0, 1.0);
+                (tangent * tangential_speed
+                    + dir * radial_error * ORBITER_RADIAL_GAIN * attributes.speed)
+                    .clamp_length_max(attributes.speed)
+            }
+            EnemyBehavior::Ranged {
+                preferred_distance, ..
+            } => {
+                if distance > preferred_distance + RANGED_DISTANCE_TOLERANCE {
+                    dir * attributes.speed
+                } else if distance < preferred_distance - RANGED_DISTANCE_TOLERANCE {
+                    -dir * attributes.speed
+                } else {
+                    dir.perp() * attributes.speed * RANGED_STRAFE_FACTOR
+                }
+            }
+        };
+    }
+}
+
+fn steer_charger(
+    state: &mut ChargeState,
+    transform: &mut Transform,
+    delta: std::time::Duration,
+    dir: Vec2,
+    distance: f32,
+    speed: f32,
+    (range, windup, dash_speed, dash_duration, cooldown): (f32, f32, f32, f32, f32),
+) -> Vec2 {
+    match state {
+        ChargeState::Approach => {
+            if distance <= range {
+                *state = ChargeState::Windup(Timer::from_seconds(windup, TimerMode::Once));
+                Vec2::ZERO
+            } else {
+                dir * speed
+            }
+        }
+        ChargeState::Windup(timer) => {
+            timer.tick(delta);
+            // Swell up while winding up so the dash can be seen coming.
+            transform.scale = Vec3::splat(1.0 + (CHARGER_TELEGRAPH_SCALE - 1.0) * timer.fraction());
+            if timer.is_finished() {
+                transform.scale = Vec3::ONE;
+                *state = ChargeState::Dash {
+                    timer: Timer::from_seconds(dash_duration, TimerMode::Once),
+                    direction: dir,
+                };
+            }
+            Vec2::ZERO
+        }
+        ChargeState::Dash { timer, direction } => {
+            let velocity = *direction * dash_speed;
+            if timer.tick(delta).is_finished() {
+                *state = ChargeState::Recover(Timer::from_seconds(cooldown, TimerMode::Once));
+            }
+            velocity
+        }
+        ChargeState::Recover(timer) => {
+            if timer.tick(delta).is_finished() {
+                *state = ChargeState::Approach;
+            }
+            dir * speed * CHARGER_RECOVER_SPEED_FACTOR
+        }
+    }
+}
+
+pub fn enemy_ranged_attack(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut enemies: Query<
+        (
+            &Transform,
+            &EnemyBehavior,
+            &mut RangedAttack,
+            Option<&StatusEffects>,
+        ),
+        With<Enemy>,
+    >,
+    player: Query<&Transform, (With<Player>, Without<Enemy>)>,
+) {
+    let Ok(player_transform) = player.single() else {
+        return;
+    };
+    let player_pos = player_transform.translation.xy();
+
+    for (transform, behavior, mut attack, effects) in &mut enemies {
+        // Frozen enemies hold their fire, and their attack timer.
+        if effects.is_some_and(StatusEffects::is_frozen) {
+            continue;
+        }
+        let EnemyBehavior::Ranged {
+            preferred_distance,
+            projectile_speed,
+            projectile_damage,
+            ..
+        } = *behavior
+        else {
+            continue;
+        };
+        if !attack.timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        let origin = transform.translation.xy();
+        let to_player = player_pos - origin;
+        // Hold fire until the player is roughly within range.
+        if to_player.length() > preferred_distance * 1.5 {
+            continue;
+        }
+        spawn_enemy_projectile(
+            &mut commands,
+            origin,
+            to_player.normalize_or_zero() * projectile_speed,
+            projectile_damage,
+        );
+    }
+}
+
+pub fn spawn_enemy_projectile(commands: &mut Commands, origin: Vec2, velocity: Vec2, damage: i32) {
+    commands.spawn((
+        DespawnOnExit(MainState::Game),
+        LevelEntity,
+        Sprite::from_color(ENEMY_PROJECTILE_COLOR, ENEMY_PROJECTILE_SIZE),
+        Transform::from_translation(origin.extend(0.9)),
+        EnemyProjectile { damage },
+        Velocity(velocity),
+        Lifetime {
+            timer: Timer::from_seconds(ENEMY_PROJECTILE_LIFETIME, TimerMode::Once),
+        },
+    ));
+}
+
+pub fn split_on_death(
+    mut commands: Commands,
+    mut enemy_killed_messages: MessageReader<EnemyKilled>,
+    mut run_rng: ResMut<RunRng>,
+) {
+    let rng = run_rng.gameplay();
+    for killed in enemy_killed_messages.read() {
+        let EnemyBehavior::Splitter { count } = killed.behavior else {
+            continue;
+        };
+
+        let parent = killed.attributes;
+        let attributes = EnemyAttributes {
+            health: (parent.health / 2).max(1),
+            size: parent.size * SPLIT_SIZE_FACTOR,
+            speed: parent.speed * SPLIT_SPEED_FACTOR,
+            score_value: (parent.score_value / 2).max(1),
+            xp_value: (parent.xp_value / 2).max(1),
+            ..parent
+        };
+        let base_angle = rng.random_range(0.0..TAU);
+        for i in 0..count {
+            let angle = base_angle + TAU * i as f32 / count as f32;
+            let dir = Vec2::new(angle.cos(), angle.sin());
+            spawn_enemy(
+                &mut commands,
+                format!("{} Fragment", killed.name),
+                attributes,
+                EnemyBehavior::Chase,
+                killed.position + dir * parent.size.max_element() * 0.5,
+                dir * SPLIT_SCATTER_SPEED,
+                rng,
+            );
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::arena::{ArenaMap, CameraView};
+use super::behavior::{
+    ChargeState, EnemyBehavior, OrbitSpin, RangedAttack, insert_behavior_state,
+    spawn_enemy_projectile,
+};
+use super::components::{Health, LevelEntity};
+use super::constants::{PLAYER_SIZE, VIEW_HALF_SIZE};
+use super::enemy::{Enemy, EnemyAttributes, spawn_enemy};
+use super::events::BossDefeated;
+use super::player::Player;
+use super::powerup::PowerUpProgress;
+use super::rng::RunRng;
+use super::ui::{BossHealthBar, BossHealthBarFill, BossNameText};
+use super::waves::{WaveDirector, WaveTimeline};
+use crate::MainState;
+
+/// Seconds of run time between boss arrivals.
+pub const BOSS_INTERVAL: f32 = 180.0;
+pub const BOSS_CONTACT_COOLDOWN: f32 = 1.0;
+const BOSS_NAME: &str = "Behemoth";
+const BOSS_ATTRIBUTES: EnemyAttributes = EnemyAttributes {
+    health: 150,
+    size: Vec2::splat(72.0),
+    speed: 70.0,
+    damage: 2,
+    score_value: 50,
+    xp_value: 40,
+    color: Color::srgb(0.55, 0.15, 0.6),
+};
+const BOSS_BURST_PROJECTILE_SPEED: f32 = 200.0;
+const BOSS_REWARD_SIZE: f32 = 20.0;
+const BOSS_REWARD_COLOR: Color = Color::srgb(1.0, 0.85, 0.2);
+
+/// One stage of a boss fight, active while the boss' health fraction is at or below
+/// `health_threshold`.
+pub struct BossPhase {
+    pub health_threshold: f32,
+    pub behavior: EnemyBehavior,
+    /// Projectiles fired in a ring every `burst_interval` seconds. Zero disables bursts.
+    pub burst_count: u32,
+    pub burst_interval: f32,
+}
+
+pub const BOSS_PHASES: [BossPhase; 3] = [
+    BossPhase {
+        health_threshold: 1.0,
+        behavior: EnemyBehavior::Chase,
+        burst_count: 0,
+        burst_interval: 0.0,
+    },
+    BossPhase {
+        health_threshold: 0.66,
+        behavior: EnemyBehavior::Charger {
+            range: 320.0,
+            windup: 0.8,
+            dash_speed: 480.0,
+            dash_duration: 0.6,
+            cooldown: 1.5,
+        },
+        burst_count: 0,
+        burst_interval: 0.0,
+    },
+    BossPhase {
+        health_threshold: 0.33,
+        behavior: EnemyBehavior::Ranged {
+            preferred_distance: 240.0,
+            fire_interval: 1.2,
+            projectile_speed: 260.0,
+            projectile_damage: 1,
+        },
+        burst_count: 12,
+        burst_interval: 3.0,
+    },
+];
+
+#[derive(Component)]
+pub struct Boss {
+    pub phase: usize,
+    /// Bosses survive touching the player and instead hurt them at most once per cooldown.
+    pub contact_cooldown: Timer,
+    burst_timer: Option<Timer>,
+}
+
+impl Boss {
+    fn new() -> Self {
+        let mut contact_cooldown = Timer::from_seconds(BOSS_CONTACT_COOLDOWN, TimerMode::Once);
+        contact_cooldown.finish();
+        Self {
+            phase: 0,
+            contact_cooldown,
+            burst_timer: None,
+        }
+    }
+
+    /// A boss already in `phase`, as when a saved run is resumed. Its timers start over.
+    pub fn in_phase(phase: usize) -> Self {
+        let phase = phase.min(BOSS_PHASES.len() - 1);
+        let stage = &BOSS_PHASES[phase];
+        Self {
+            phase,
+            burst_timer: (stage.burst_count > 0)
+                .then(|| Timer::from_seconds(stage.burst_interval, TimerMode::Repeating)),
+            ..Self::new()
+        }
+    }
+}
+
+/// Dropped when a boss dies; picking it up grants a power-up and restores health.
+#[derive(Component)]
+pub struct BossReward;
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct BossSpawner {
+    next_spawn_at: f32,
+    spawned: u32,
+}
+
+impl Default for BossSpawner {
+    fn default() -> Self {
+        Self {
+            next_spawn_at: BOSS_INTERVAL,
+            spawned: 0,
+        }
+    }
+}
+
+pub fn spawn_boss_when_due(
+    mut commands: Commands,
+    mut spawner: ResMut<BossSpawner>,
+    director: Res<WaveDirector>,
+    timeline: Res<WaveTimeline>,
+    bosses: Query<(), With<Boss>>,
+    player: Single<&Transform, With<Player>>,
+    view: Res<CameraView>,
+    map: Res<ArenaMap>,
+    mut run_rng: ResMut<RunRng>,
+) {
+    if director.elapsed() < spawner.next_spawn_at {
+        return;
+    }
+    // Only one boss at a time; skip this slot if the previous one is still alive.
+    if !bosses.is_empty() {
+        spawner.next_spawn_at = director.elapsed() + BOSS_INTERVAL;
+        return;
+    }
+
+    let wave = director.wave(&timeline);
+    let mut attributes = wave.scale_attributes(BOSS_ATTRIBUTES);
+    attributes.health += attributes.health * spawner.spawned as i32 / 2;
+
+    // Enter from behind: the player runs ahead of the view, so from the side it trails.
+    let behind = (view.center - player.translation.xy()).normalize_or(Vec2::NEG_Y);
+    let position = view.center + behind * (VIEW_HALF_SIZE.length() + 80.0);
+    // Small maps may not reach that far; the boss then walks in from the nearest clear spot.
+    let radius = attributes.radius();
+    let position = map.push_out(map.bounds.clamp(position, radius), radius);
+    let phase = &BOSS_PHASES[0];
+    let entity = spawn_enemy(
+        &mut commands,
+        BOSS_NAME.to_string(),
+        attributes,
+        phase.behavior,
+        position,
+        Vec2::ZERO,
+        run_rng.gameplay(),
+    );
+    commands.entity(entity).insert(Boss::new());
+
+    spawner.spawned += 1;
+    spawner.next_spawn_at = director.elapsed() + BOSS_INTERVAL;
+}
+
+pub fn update_bosses(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut bosses: Query<(Entity, &mut Boss, &Health, &mut Transform), With<Enemy>>,
+    mut run_rng: ResMut<RunRng>,
+) {
+    for (entity, mut boss, health, mut transform) in &mut bosses {
+        boss.contact_cooldown.tick(time.delta());
+
+        let fraction = health.current as f32 / health.max.max(1) as f32;
+        let phase_index = BOSS_PHASES
+            .iter()
+            .rposition(|phase| fraction <= phase.health_threshold)
+            .unwrap_or(0);
+        if phase_index > boss.phase {
+            let phase = &BOSS_PHASES[phase_index];
+            boss.phase = phase_index;
+            boss.burst_timer = (phase.burst_count > 0)
+                .then(|| Timer::from_seconds(phase.burst_interval, TimerMode::Repeating));
+            // Drop the previous phase's state, including a charge windup's telegraph scale.
+            transform.scale = Vec3::ONE;
+            let mut entity_commands = commands.entity(entity);
+            entity_commands.remove::<(ChargeState, RangedAttack, OrbitSpin)>();
+            entity_commands.insert(phase.behavior);
+            insert_behavior_state(&mut entity_commands, phase.behavior, run_rng.gameplay());
+        }
+
+        let burst_count = BOSS_PHASES[boss.phase].burst_count;
+        if let Some(timer) = boss.burst_timer.as_mut()
+            && timer.tick(time.delta()).just_finished()
+        {
+            let origin = transform.translation.xy();
+            for i in 0..burst_count {
+                let angle = TAU * i as f32 / burst_count as f32;
+                let dir = Vec2::new(angle.cos(), angle.sin());
+                spawn_enemy_projectile(&mut commands, origin, dir * BOSS_BURST_PROJECTILE_SPEED, 1);
+            }
+        }
+    }
+}
+
+pub fn drop_boss_reward(mut commands: Commands, mut defeated: MessageReader<BossDefeated>) {
+    for boss in defeated.read() {
+        commands.spawn((
+            DespawnOnExit(MainState::Game),
+            LevelEntity,
+            BossReward,
+            Sprite::from_color(BOSS_REWARD_COLOR, Vec2::splat(BOSS_REWARD_SIZE)),
+            Transform::from_translation(boss.position.extend(0.7))
+                .with_rotation(Quat::from_rotation_z(TAU / 8.0)),
+        ));
+    }
+}
+
+pub fn collect_boss_reward(
+    mut commands: Commands,
+    rewards: Query<(Entity, &Transform), With<BossReward>>,
+    mut player: Single<(&Transform, &mut Health), With<Player>>,
+    mut progress: ResMut<PowerUpProgress>,
+) {
+    let (player_transform, player_health) = &mut *player;
+    let pickup_radius = (PLAYER_SIZE.x + BOSS_REWARD_SIZE) * 0.5;
+    for (entity, transform) in &rewards {
+        if player_transform
+            .translation
+            .xy()
+            .distance(transform.translation.xy())
+            > pickup_radius
+        {
+            continue;
+        }
+        commands.entity(entity).despawn();
+        player_health.current = player_health.max;
+        progress.grant_level();
+    }
+}
+
+pub fn update_boss_health_bar(
+    bosses: Query<(&Health, &Name), With<Boss>>,
+    mut bar: Single<&mut Visibility, With<BossHealthBar>>,
+    mut fill: Single<&mut Node, With<BossHealthBarFill>>,
+    mut name_text: Single<&mut Text, With<BossNameText>>,
+) {
+    let Some((health, name)) = bosses.iter().next() else {
+        **bar = Visibility::Hidden;
+        return;
+    };
+
+    **bar = Visibility::Inherited;
+    let ratio = (health.current.max(0) as f32 / health.max.max(1) as f32).clamp(0.0, 1.0);
+    fill.width = percent(ratio * 100.0);
+    if name_text.0 != name.as_str() {
+        name_text.0 = name.to_string();
+    }
+}
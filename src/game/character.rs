@@ -0,0 +1,189 @@
+//! Playable characters: how the player looks and starts out, their passive trait, and what
+//! unlocks them.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::components::Health;
+use super::constants::PLAYER_MAX_HEALTH;
+use super::player::Player;
+use super::weapon::{STARTING_WEAPONS, WeaponKind};
+
+/// Seconds between the Knight's regeneration ticks.
+const REGENERATION_INTERVAL: f32 = 30.0;
+
+#[derive(
+    Resource, Component, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum Character {
+    #[default]
+    Scout,
+    Knight,
+    Pyromancer,
+    Gambler,
+}
+
+pub const ALL_CHARACTERS: [Character; 4] = [
+    Character::Scout,
+    Character::Knight,
+    Character::Pyromancer,
+    Character::Gambler,
+];
+
+/// A character's passive trait, active for the whole run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Passive {
+    /// Gains 20% more experience.
+    Scholar,
+    /// Regains 1 health every `REGENERATION_INTERVAL` seconds.
+    Regeneration,
+    /// Starts with a level of the Incendiary infusion.
+    Kindling,
+    /// 10% more critical hit chance.
+    Lucky,
+}
+
+/// What a character needs before it can be picked. Checked against the career stats.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unlock {
+    Always,
+    /// Survive this many seconds in a single run.
+    Survive(f32),
+    /// Defeat this many enemies over all runs.
+    TotalKills(u64),
+    /// Defeat any boss.
+    DefeatBoss,
+}
+
+impl Character {
+    pub fn id(self) -> &'static str {
+        match self {
+            Character::Scout => "scout",
+            Character::Knight => "knight",
+            Character::Pyromancer => "pyromancer",
+            Character::Gambler => "gambler",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        ALL_CHARACTERS
+            .into_iter()
+            .find(|character| character.id() == id)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Character::Scout => "Scout",
+            Character::Knight => "Knight",
+            Character::Pyromancer => "Pyromancer",
+            Character::Gambler => "Gambler",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Character::Scout => Color::srgb(0.2, 0.8, 1.0),
+            Character::Knight => Color::srgb(0.75, 0.78, 0.85),
+            Character::Pyromancer => Color::srgb(1.0, 0.5, 0.15),
+            Character::Gambler => Color::srgb(0.7, 0.4, 1.0),
+        }
+    }
+
+    /// Health before difficulty and shop upgrades.
+    pub fn max_health(self) -> i32 {
+        match self {
+            Character::Scout => PLAYER_MAX_HEALTH,
+            Character::Knight => 8,
+            Character::Pyromancer => 4,
+            Character::Gambler => 5,
+        }
+    }
+
+    /// Multiplier on the base move speed.
+    pub fn speed(self) -> f32 {
+        match self {
+            Character::Scout => 1.0,
+            Character::Knight => 0.85,
+            Character::Pyromancer => 1.0,
+            Character::Gambler => 1.05,
+        }
+    }
+
+    pub fn starting_weapons(self) -> &'static [WeaponKind] {
+        match self {
+            Character::Scout => &STARTING_WEAPONS,
+            Character::Knight => &[WeaponKind::Blades],
+            Character::Pyromancer => &[WeaponKind::Beam],
+            Character::Gambler => &[WeaponKind::Gun, WeaponKind::Lightning],
+        }
+    }
+
+    pub fn passive(self) -> Passive {
+        match self {
+            Character::Scout => Passive::Scholar,
+            Character::Knight => Passive::Regeneration,
+            Character::Pyromancer => Passive::Kindling,
+            Character::Gambler => Passive::Lucky,
+        }
+    }
+
+    pub fn unlock(self) -> Unlock {
+        match self {
+            Character::Scout => Unlock::Always,
+            Character::Knight => Unlock::Survive(300.0),
+            Character::Pyromancer => Unlock::TotalKills(500),
+            Character::Gambler => Unlock::DefeatBoss,
+        }
+    }
+}
+
+impl Passive {
+    pub fn description(self) -> &'static str {
+        match self {
+            Passive::Scholar => "Gains 20% more experience",
+            Passive::Regeneration => "Regains 1 health every 30s",
+            Passive::Kindling => "Starts with Incendiary",
+            Passive::Lucky => "+10% critical hit chance",
+        }
+    }
+}
+
+impl Unlock {
+    pub fn description(self) -> String {
+        match self {
+            Unlock::Always => "Unlocked".to_string(),
+            Unlock::Survive(seconds) => {
+                let seconds = seconds as u32;
+                format!("Survive {}:{:02} in one run", seconds / 60, seconds % 60)
+            }
+            Unlock::TotalKills(kills) => format!("Defeat {kills} enemies in total"),
+            Unlock::DefeatBoss => "Defeat a boss".to_string(),
+        }
+    }
+}
+
+/// Heals the player over time. Added by `spawn_player` for characters with
+/// [`Passive::Regeneration`].
+#[derive(Component)]
+pub struct Regeneration {
+    pub timer: Timer,
+}
+
+impl Default for Regeneration {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(REGENERATION_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+pub fn regenerate(
+    time: Res<Time>,
+    mut player: Query<(&mut Health, &mut Regeneration), With<Player>>,
+) {
+    for (mut health, mut regeneration) in &mut player {
+        if regeneration.timer.tick(time.delta()).just_finished() {
+            health.current = (health.current + 1).min(health.max);
+        }
+    }
+}
@@ -4,36 +4,54 @@ use std::f32::consts::TAU;
 use bevy::prelude::*;
 use rand::Rng;
 
-use super::components::{BombExplosion, Lifetime, Particle, Projectile, Velocity};
+use super::arena::ArenaMap;
+use super::behavior::EnemyBehavior;
+use super::boss::Boss;
+use super::components::{BombExplosion, EnemyProjectile, Lifetime, Particle, Projectile, Velocity};
 use super::constants::{
     ENEMY_DEATH_PARTICLE_LIFETIME, ENEMY_DEATH_PARTICLE_SIZE, ENEMY_DEATH_PARTICLE_SPEED,
-    ENEMY_DEATH_PARTICLES,
+    ENEMY_DEATH_PARTICLES, PLAYER_SIZE,
 };
 use super::enemy::{
     ENEMY_HIT_FLASH_COLOR, ENEMY_HIT_FLASH_DURATION, Enemy, EnemyAttributes, EnemyHitFlash,
 };
-use super::events::{EnemyKilled, PlayerHit};
+use super::events::{BossDefeated, DamageEnemy, EnemyDamaged, EnemyKilled, PlayerHit};
+use super::powerup::PlayerUpgrades;
 use super::resources::{DefeatSound, HitSelfSound, HitSound};
-use crate::MainState;
+use super::rng::RunRng;
+use super::spatial::{SpatialIndex, compare_positions};
+use super::status::{ApplyStatus, on_hit_effects};
 use crate::audio::{SEVolume, spawn_se};
+use crate::game::GameState;
 use crate::game::components::{Health, LevelEntity};
-use crate::game::experience::{OrbMesh, spawn_experience_orb};
 use crate::game::player::Player;
 use crate::game::ui::Score;
+use crate::{DisplayQuality, MainState};
 
-#[derive(Resource)]
-pub struct EnemySpawnTimer(pub Timer);
+const ENEMY_HIT_RADIUS: f32 = 12.0;
+/// Slightly under the player's half-width, so enemy projectiles that only graze don't count.
+const ENEMY_PROJECTILE_HIT_RADIUS: f32 = PLAYER_SIZE.x * 0.5 - 2.0;
 
 pub fn handle_collisions(
     mut commands: Commands,
     mut score: ResMut<Score>,
     mut player_hit_messages: MessageWriter<PlayerHit>,
     mut enemy_killed_messages: MessageWriter<EnemyKilled>,
-    hit_sound: Res<HitSound>,
-    hit_self_sound: Res<HitSelfSound>,
-    se_volume: Res<SEVolume>,
-    defeat_sound: Res<DefeatSound>,
-    orb_mesh: Res<OrbMesh>,
+    mut boss_defeated_messages: MessageWriter<BossDefeated>,
+    mut damage_messages: MessageReader<DamageEnemy>,
+    (hit_sound, hit_self_sound, defeat_sound, se_volume): (
+        Res<HitSound>,
+        Res<HitSelfSound>,
+        Res<DefeatSound>,
+        Res<SEVolume>,
+    ),
+    (upgrades, mut status_messages, mut damaged_messages, quality): (
+        Res<PlayerUpgrades>,
+        MessageWriter<ApplyStatus>,
+        MessageWriter<EnemyDamaged>,
+        Res<DisplayQuality>,
+    ),
+    mut run_rng: ResMut<RunRng>,
     mut player: Single<(Entity, &mut Health, &Transform), (With<Player>, Without<Enemy>)>,
     mut enemies: Query<
         (
@@ -42,81 +60,211 @@ pub fn handle_collisions(
             &mut Sprite,
             &Transform,
             &EnemyAttributes,
+            &EnemyBehavior,
+            &Name,
+            Option<&mut Boss>,
         ),
         (With<Enemy>, Without<Player>),
     >,
     projectiles: Query<(Entity, &Projectile, &Transform)>,
-    bomb_explosions: Query<(Entity, &Transform, &BombExplosion)>,
+    bomb_explosions: Query<(&Transform, &BombExplosion)>,
+    index: Res<SpatialIndex>,
+    map: Res<ArenaMap>,
+    mut next_state: ResMut<NextState<GameState>>,
 ) {
     let (player_entity, player_health, player_transform) = &mut *player;
-
-    let bomb_explosions_data: Vec<(Entity, Vec2, f32)> = bomb_explosions
-        .iter()
-        .map(|(entity, transform, explosion)| {
-            (entity, transform.translation.xy(), explosion.radius)
-        })
-        .collect();
+    let grid = &index.enemies;
+    // Far enough to find every enemy whose hit radius could reach a point.
+    let hit_reach = grid.max_radius().max(ENEMY_HIT_RADIUS);
 
     let mut enemies_to_despawn: HashSet<Entity> = HashSet::new();
     let mut projectiles_to_despawn: HashSet<Entity> = HashSet::new();
+    // Enemies damaged this tick.
+    let mut hit_enemies: Vec<Entity> = Vec::new();
+    // Enemies hit by a weapon this tick, which the player's infusions apply to.
+    let mut weapon_hits: HashSet<Entity> = HashSet::new();
 
-    for (enemy_entity, mut health, mut sprite, transform, attributes) in &mut enemies {
-        if enemies_to_despawn.contains(&enemy_entity) {
+    for (projectile_entity, projectile, transform) in &projectiles {
+        let projectile_pos = transform.translation.xy();
+        if map.blocks_projectile(projectile_pos) {
+            projectiles_to_despawn.insert(projectile_entity);
             continue;
         }
-
-        let enemy_pos = transform.translation.xy();
-        let mut enemy_hit = false;
-
-        for (projectile_entity, projectile, transform) in &projectiles {
-            if projectiles_to_despawn.contains(&projectile_entity) {
+        for candidate in grid.candidates(projectile_pos, hit_reach) {
+            let Ok((enemy_entity, mut health, _, transform, attributes, _, _, boss)) =
+                enemies.get_mut(candidate.entity)
+            else {
+                continue;
+            };
+            let hit_radius = hit_radius(attributes, boss.is_some());
+            if !collide(transform.translation.xy(), projectile_pos, hit_radius) {
                 continue;
             }
-            let projectile_pos = transform.translation.xy();
-            if collide(enemy_pos, projectile_pos, 12.0) {
-                projectiles_to_despawn.insert(projectile_entity);
-                health.current -= projectile.damage;
-                enemy_hit = true;
+            projectiles_to_despawn.insert(projectile_entity);
+            health.current -= projectile.damage;
+            damaged_messages.write(EnemyDamaged {
+                position: transform.translation.xy(),
+                amount: projectile.damage,
+                crit: projectile.crit,
+            });
+            weapon_hits.insert(enemy_entity);
+            if !hit_enemies.contains(&enemy_entity) {
+                hit_enemies.push(enemy_entity);
             }
+            break;
         }
+    }
+
+    for damage in damage_messages.read() {
+        let Ok((enemy_entity, mut health, _, transform, ..)) = enemies.get_mut(damage.target)
+        else {
+            continue;
+        };
+        health.current -= damage.amount;
+        damaged_messages.write(EnemyDamaged {
+            position: transform.translation.xy(),
+            amount: damage.amount,
+            crit: false,
+        });
+        if damage.on_hit {
+            weapon_hits.insert(enemy_entity);
+        }
+        if !hit_enemies.contains(&enemy_entity) {
+            hit_enemies.push(enemy_entity);
+        }
+    }
 
-        for (_, explosion_pos, radius) in &bomb_explosions_data {
-            if collide(enemy_pos, *explosion_pos, *radius) {
-                health.current -= 1;
-                enemy_hit = true;
-                // Only apply damage from one explosion per frame
-                break;
+    // Only apply damage from one explosion per enemy per frame
+    let mut exploded: HashSet<Entity> = HashSet::new();
+    for (explosion_transform, explosion) in &bomb_explosions {
+        let explosion_pos = explosion_transform.translation.xy();
+        for candidate in grid.candidates(explosion_pos, explosion.radius) {
+            if exploded.contains(&candidate.entity) {
+                continue;
+            }
+            let Ok((enemy_entity, mut health, _, transform, ..)) =
+                enemies.get_mut(candidate.entity)
+            else {
+                continue;
+            };
+            if collide(transform.translation.xy(), explosion_pos, explosion.radius) {
+                exploded.insert(enemy_entity);
+                health.current -= explosion.damage;
+                damaged_messages.write(EnemyDamaged {
+                    position: transform.translation.xy(),
+                    amount: explosion.damage,
+                    crit: explosion.crit,
+                });
+                weapon_hits.insert(enemy_entity);
+                if !hit_enemies.contains(&enemy_entity) {
+                    hit_enemies.push(enemy_entity);
+                }
             }
         }
+    }
+
+    // Resolved in position order, so kills, and the random draws they lead to, happen in the
+    // same order when the run is replayed.
+    let position_of = |entity| {
+        enemies
+            .get(entity)
+            .map(|(_, _, _, transform, ..)| transform.translation.xy())
+            .unwrap_or_default()
+    };
+    hit_enemies.sort_by(|&a, &b| compare_positions(position_of(a), position_of(b)));
 
-        if enemy_hit {
-            if health.current <= 0 {
-                enemies_to_despawn.insert(enemy_entity);
-                score.0 += attributes.score_value;
-                enemy_killed_messages.write(EnemyKilled);
-                spawn_se(&mut commands, &*se_volume, &hit_sound.0);
-                spawn_enemy_death_particles(&mut commands, enemy_pos, attributes.color);
-                spawn_experience_orb(&mut commands, &orb_mesh, enemy_pos, attributes.xp_value);
-            } else {
+    let player_pos = player_transform.translation.xy();
+    for enemy_entity in hit_enemies {
+        let Ok((_, health, mut sprite, transform, attributes, behavior, name, boss)) =
+            enemies.get_mut(enemy_entity)
+        else {
+            continue;
+        };
+        let enemy_pos = transform.translation.xy();
+        if health.current <= 0 {
+            enemies_to_despawn.insert(enemy_entity);
+            score.0 += attributes.score_value;
+            enemy_killed_messages.write(EnemyKilled {
+                name: name.to_string(),
+                position: enemy_pos,
+                attributes: *attributes,
+                behavior: *behavior,
+            });
+            if boss.is_some() {
+                boss_defeated_messages.write(BossDefeated {
+                    position: enemy_pos,
+                });
+            }
+            spawn_se(&mut commands, &*se_volume, &hit_sound.0);
+            spawn_enemy_death_particles(
+                &mut commands,
+                run_rng.cosmetic(),
+                *quality,
+                enemy_pos,
+                attributes.color,
+            );
+        } else {
+            if quality.flashes() {
                 sprite.color = ENEMY_HIT_FLASH_COLOR;
                 commands.entity(enemy_entity).insert(EnemyHitFlash {
                     timer: Timer::from_seconds(ENEMY_HIT_FLASH_DURATION, TimerMode::Once),
                 });
             }
+            if weapon_hits.contains(&enemy_entity) {
+                for effect in on_hit_effects(&upgrades, player_pos, enemy_pos, run_rng.gameplay()) {
+                    status_messages.write(ApplyStatus {
+                        target: enemy_entity,
+                        effect,
+                    });
+                }
+            }
         }
+    }
 
-        if enemies_to_despawn.contains(&enemy_entity) {
+    for candidate in grid.candidates(player_pos, hit_reach) {
+        if enemies_to_despawn.contains(&candidate.entity) {
+            continue;
+        }
+        let Ok((enemy_entity, _, _, transform, attributes, _, _, mut boss)) =
+            enemies.get_mut(candidate.entity)
+        else {
+            continue;
+        };
+        let enemy_pos = transform.translation.xy();
+        if !collide(
+            enemy_pos,
+            player_pos,
+            hit_radius(attributes, boss.is_some()),
+        ) {
             continue;
         }
 
-        if collide(enemy_pos, player_transform.translation.xy(), 12.0) {
-            enemies_to_despawn.insert(enemy_entity);
-            spawn_enemy_death_particles(&mut commands, enemy_pos, attributes.color);
-            if player_health.current > 0 {
+        if let Some(boss) = boss.as_mut() {
+            if boss.contact_cooldown.is_finished() && player_health.current > 0 {
+                boss.contact_cooldown.reset();
                 player_health.current -= attributes.damage;
-                player_hit_messages.write(PlayerHit);
-                spawn_se(&mut commands, &*se_volume, &hit_self_sound.0);
+                player_hit_messages.write(PlayerHit {
+                    damage: attributes.damage,
+                });
+                spawn_se(&mut commands, &se_volume, &hit_self_sound.0);
             }
+            continue;
+        }
+
+        enemies_to_despawn.insert(enemy_entity);
+        spawn_enemy_death_particles(
+            &mut commands,
+            run_rng.cosmetic(),
+            *quality,
+            enemy_pos,
+            attributes.color,
+        );
+        if player_health.current > 0 {
+            player_health.current -= attributes.damage;
+            player_hit_messages.write(PlayerHit {
+                damage: attributes.damage,
+            });
+            spawn_se(&mut commands, &*se_volume, &hit_self_sound.0);
         }
     }
 
@@ -130,21 +278,66 @@ pub fn handle_collisions(
     if player_health.current <= 0 {
         spawn_se(&mut commands, &*se_volume, &defeat_sound.0);
         commands.entity(*player_entity).despawn();
+        next_state.set(GameState::GameOver);
+    }
+}
+
+/// Bosses are far larger than regular enemies, so use their actual extent.
+fn hit_radius(attributes: &EnemyAttributes, is_boss: bool) -> f32 {
+    if is_boss {
+        attributes.radius()
+    } else {
+        ENEMY_HIT_RADIUS
+    }
+}
+
+pub fn enemy_projectile_hits(
+    mut commands: Commands,
+    mut player_hit_messages: MessageWriter<PlayerHit>,
+    hit_self_sound: Res<HitSelfSound>,
+    se_volume: Res<SEVolume>,
+    mut player: Single<(&mut Health, &Transform), With<Player>>,
+    projectiles: Query<(Entity, &EnemyProjectile, &Transform)>,
+) {
+    let (player_health, player_transform) = &mut *player;
+    let player_pos = player_transform.translation.xy();
+
+    for (entity, projectile, transform) in &projectiles {
+        if !collide(
+            player_pos,
+            transform.translation.xy(),
+            ENEMY_PROJECTILE_HIT_RADIUS,
+        ) {
+            continue;
+        }
+        commands.entity(entity).despawn();
+        if player_health.current > 0 {
+            player_health.current -= projectile.damage;
+            player_hit_messages.write(PlayerHit {
+                damage: projectile.damage,
+            });
+            spawn_se(&mut commands, &se_volume, &hit_self_sound.0);
+        }
     }
 }
 
-fn spawn_enemy_death_particles(commands: &mut Commands, position: Vec2, color: Color) {
-    let mut rng = rand::rng();
+fn spawn_enemy_death_particles(
+    commands: &mut Commands,
+    rng: &mut impl Rng,
+    quality: DisplayQuality,
+    position: Vec2,
+    color: Color,
+) {
+    let count = (ENEMY_DEATH_PARTICLES as f32 * quality.particle_share()).round() as usize;
+    let max_lifetime = ENEMY_DEATH_PARTICLE_LIFETIME * quality.particle_lifetime_scale();
     commands.spawn_batch(
-        (0..ENEMY_DEATH_PARTICLES)
+        (0..count)
             .map(|_| {
                 let angle = rng.random_range(0.0f32..TAU);
                 let speed =
                     rng.random_range(ENEMY_DEATH_PARTICLE_SPEED * 0.6..ENEMY_DEATH_PARTICLE_SPEED);
                 let velocity = Vec2::new(angle.cos(), angle.sin()) * speed;
-                let lifetime = rng.random_range(
-                    ENEMY_DEATH_PARTICLE_LIFETIME * 0.7..ENEMY_DEATH_PARTICLE_LIFETIME,
-                );
+                let lifetime = rng.random_range(max_lifetime * 0.7..max_lifetime);
                 (
                     DespawnOnExit(MainState::Game),
                     LevelEntity,
@@ -1,10 +1,11 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 /// Entities with this component will be despawned on game reset.
 #[derive(Component)]
 pub struct LevelEntity;
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Health {
     pub current: i32,
     pub max: i32,
@@ -25,19 +26,31 @@ impl Default for Health {
 #[derive(Component)]
 pub struct Projectile {
     pub damage: i32,
+    pub crit: bool,
 }
 
+/// Fired by ranged enemies; hurts the player on contact.
 #[derive(Component)]
+pub struct EnemyProjectile {
+    pub damage: i32,
+}
+
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub struct Bomb {
     pub timer: Timer,
     pub blink_timer: Timer,
     pub radius: f32,
+    pub damage: i32,
+    pub crit: bool,
     pub visible: bool,
 }
 
 #[derive(Component)]
 pub struct BombExplosion {
     pub radius: f32,
+    /// Dealt to each enemy inside the blast on every tick the explosion lasts.
+    pub damage: i32,
+    pub crit: bool,
 }
 
 #[derive(Component, Deref, DerefMut)]
@@ -51,7 +64,7 @@ pub struct Lifetime {
 #[derive(Component)]
 pub struct Particle;
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct ExperienceOrb {
     pub value: u32,
     pub magnetized: bool,
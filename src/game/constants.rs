@@ -9,7 +9,8 @@ pub const BOMB_FUSE: f32 = 1.0;
 pub const BOMB_EXPLOSION_RADIUS: f32 = 100.0;
 pub const BOMB_EXPLOSION_DURATION: f32 = 0.2;
 pub const ENEMY_SPAWN_INTERVAL: f32 = 0.5;
-pub const ARENA_HALF_SIZE: f32 = 400.0;
+/// Half of the largest part of the world the camera shows.
+pub const VIEW_HALF_SIZE: Vec2 = Vec2::new(640.0, 360.0);
 pub const PLAYER_MAX_HEALTH: i32 = 5;
 pub const ENEMY_DEATH_PARTICLES: usize = 20;
 pub const ENEMY_DEATH_PARTICLE_LIFETIME: f32 = 0.35;
@@ -25,3 +26,4 @@ pub const EXPERIENCE_ORB_MAGNET_BASE_SPEED: f32 = 280.0;
 pub const EXPERIENCE_ORB_MAGNET_ACCEL: f32 = 3.5;
 pub const EXPERIENCE_ORB_MAGNET_MAX_SPEED: f32 = 520.0;
 pub const EXPERIENCE_ORB_IDLE_DAMPING: f32 = 3.0;
+pub const SPATIAL_CELL_SIZE: f32 = 64.0;
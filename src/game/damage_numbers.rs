@@ -0,0 +1,107 @@
+//! Numbers that float up from enemies showing how much damage each hit dealt. Critical hits
+//! show larger and in a different colour.
+
+use bevy::prelude::*;
+
+use super::components::LevelEntity;
+use super::events::EnemyDamaged;
+use crate::MainState;
+
+/// Numbers on screen at once. Past this, the oldest number is reused for the newest hit.
+const MAX_DAMAGE_NUMBERS: usize = 48;
+const DAMAGE_NUMBER_LIFETIME: f32 = 0.7;
+const DAMAGE_NUMBER_RISE_SPEED: f32 = 50.0;
+const DAMAGE_NUMBER_SIZE: f32 = 16.0;
+const CRIT_NUMBER_SIZE: f32 = 26.0;
+const DAMAGE_NUMBER_COLOR: Color = Color::WHITE;
+const CRIT_NUMBER_COLOR: Color = Color::srgb(1.0, 0.8, 0.1);
+
+/// A pooled number. Hidden numbers are free for the next hit.
+#[derive(Component)]
+pub struct DamageNumber {
+    timer: Timer,
+}
+
+fn damage_number_bundle(damaged: &EnemyDamaged, font: Handle<Font>) -> impl Bundle {
+    let (font_size, color) = if damaged.crit {
+        (CRIT_NUMBER_SIZE, CRIT_NUMBER_COLOR)
+    } else {
+        (DAMAGE_NUMBER_SIZE, DAMAGE_NUMBER_COLOR)
+    };
+    (
+        DamageNumber {
+            timer: Timer::from_seconds(DAMAGE_NUMBER_LIFETIME, TimerMode::Once),
+        },
+        Text2d::new(damaged.amount.to_string()),
+        TextFont {
+            font,
+            font_size,
+            ..default()
+        },
+        TextColor(color),
+        Transform::from_translation(damaged.position.extend(6.0)),
+        Visibility::Visible,
+    )
+}
+
+/// Shows a number for every hit, reusing hidden numbers before spawning new ones.
+pub fn spawn_damage_numbers(
+    mut commands: Commands,
+    mut messages: MessageReader<EnemyDamaged>,
+    numbers: Query<(Entity, &DamageNumber, &Visibility)>,
+    asset_server: Res<AssetServer>,
+) {
+    if messages.is_empty() {
+        return;
+    }
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+
+    let mut free: Vec<Entity> = numbers
+        .iter()
+        .filter(|(_, _, visibility)| **visibility == Visibility::Hidden)
+        .map(|(entity, ..)| entity)
+        .collect();
+    // Visible numbers, oldest last, for when the pool is full.
+    let mut oldest: Vec<(Entity, f32)> = numbers
+        .iter()
+        .filter(|(_, _, visibility)| **visibility != Visibility::Hidden)
+        .map(|(entity, number, _)| (entity, number.timer.elapsed_secs()))
+        .collect();
+    oldest.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let mut pooled = numbers.iter().count();
+
+    for damaged in messages.read() {
+        let bundle = damage_number_bundle(damaged, font.clone());
+        if let Some(entity) = free.pop() {
+            commands.entity(entity).insert(bundle);
+        } else if pooled < MAX_DAMAGE_NUMBERS {
+            commands.spawn((DespawnOnExit(MainState::Game), LevelEntity, bundle));
+            pooled += 1;
+        } else if let Some((entity, _)) = oldest.pop() {
+            commands.entity(entity).insert(bundle);
+        }
+    }
+}
+
+/// Floats numbers up while fading them out, then hides them for reuse.
+pub fn animate_damage_numbers(
+    time: Res<Time>,
+    mut numbers: Query<(
+        &mut DamageNumber,
+        &mut Transform,
+        &mut TextColor,
+        &mut Visibility,
+    )>,
+) {
+    for (mut number, mut transform, mut color, mut visibility) in &mut numbers {
+        if *visibility == Visibility::Hidden {
+            continue;
+        }
+        if number.timer.tick(time.delta()).is_finished() {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        transform.translation.y += DAMAGE_NUMBER_RISE_SPEED * time.delta_secs();
+        color.0.set_alpha(1.0 - number.timer.fraction());
+    }
+}
@@ -1,14 +1,17 @@
 use bevy::prelude::*;
 use rand::Rng;
-use rand::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::MainState;
-use crate::game::combat::EnemySpawnTimer;
+use crate::game::arena::{ArenaMap, CameraView};
+use crate::game::behavior::{EnemyBehavior, insert_behavior_state};
 use crate::game::components::Health;
 use crate::game::components::LevelEntity;
 use crate::game::components::Velocity;
-use crate::game::constants::ARENA_HALF_SIZE;
+use crate::game::pickup::DropTable;
 use crate::game::player::Player;
+use crate::game::rng::RunRng;
+use crate::game::waves::{WaveDirector, WaveTimeline};
 
 #[derive(Component)]
 pub struct Enemy;
@@ -21,7 +24,7 @@ pub struct EnemyHitFlash {
     pub timer: Timer,
 }
 
-#[derive(Component, Copy, Clone)]
+#[derive(Component, Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct EnemyAttributes {
     pub health: i32,
     pub size: Vec2,
@@ -32,127 +35,141 @@ pub struct EnemyAttributes {
     pub color: Color,
 }
 
-#[derive(Copy, Clone)]
+impl EnemyAttributes {
+    pub fn radius(&self) -> f32 {
+        self.size.max_element() * 0.5
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct EnemyPrototype {
+    pub name: String,
     pub attributes: EnemyAttributes,
+    pub behavior: EnemyBehavior,
     pub weight: f32,
+    pub drops: DropTable,
 }
 
-#[derive(Resource)]
+/// Weighted pool of prototypes that `spawn_enemies` draws from. Filled from the enemy catalog
+/// asset, see `enemy_data`.
+#[derive(Resource, Default)]
 pub struct EnemyCatalog {
     prototypes: Vec<EnemyPrototype>,
-    total_weight: f32,
 }
 
 impl EnemyCatalog {
-    pub fn new() -> Self {
-        let prototypes = vec![
-            EnemyPrototype {
-                attributes: EnemyAttributes {
-                    health: 5,
-                    size: Vec2::new(24.0, 24.0),
-                    speed: 120.0,
-                    damage: 1,
-                    score_value: 1,
-                    xp_value: 1,
-                    color: Color::srgb(0.9, 0.3, 0.3),
-                },
-                weight: 1.0,
-            },
-            EnemyPrototype {
-                attributes: EnemyAttributes {
-                    health: 3,
-                    size: Vec2::new(18.0, 18.0),
-                    speed: 210.0,
-                    damage: 1,
-                    score_value: 2,
-                    xp_value: 2,
-                    color: Color::srgb(0.95, 0.6, 0.2),
-                },
-                weight: 0.6,
-            },
-            EnemyPrototype {
-                attributes: EnemyAttributes {
-                    health: 10,
-                    size: Vec2::new(36.0, 36.0),
-                    speed: 80.0,
-                    damage: 2,
-                    score_value: 3,
-                    xp_value: 3,
-                    color: Color::srgb(0.6, 0.1, 0.1),
-                },
-                weight: 0.3,
-            },
-        ];
-
-        let total_weight = prototypes
+    pub fn from_prototypes(prototypes: Vec<EnemyPrototype>) -> Self {
+        Self { prototypes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prototypes.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&EnemyPrototype> {
+        self.prototypes
             .iter()
-            .map(|p| p.weight)
-            .sum::<f32>()
-            .max(f32::EPSILON);
+            .find(|prototype| prototype.name == name)
+    }
 
-        Self {
-            prototypes,
-            total_weight,
+    /// Picks a prototype by catalog weight, scaled per prototype by `weight_multiplier`.
+    pub fn random_prototype<'a>(
+        &'a self,
+        rng: &mut impl Rng,
+        weight_multiplier: impl Fn(&EnemyPrototype) -> f32,
+    ) -> Option<&'a EnemyPrototype> {
+        let weights: Vec<f32> = self
+            .prototypes
+            .iter()
+            .map(|prototype| (prototype.weight * weight_multiplier(prototype)).max(0.0))
+            .collect();
+        let total_weight = weights.iter().sum::<f32>();
+        if total_weight <= f32::EPSILON {
+            return self.prototypes.first();
         }
-    }
 
-    pub fn random_prototype<'a>(&'a self, rng: &mut ThreadRng) -> &'a EnemyPrototype {
-        let mut roll = rng.random_range(0.0..self.total_weight);
-        for prototype in &self.prototypes {
-            if roll <= prototype.weight {
-                return prototype;
+        let mut roll = rng.random_range(0.0..total_weight);
+        for (prototype, weight) in self.prototypes.iter().zip(weights) {
+            if roll < weight {
+                return Some(prototype);
             }
-            roll -= prototype.weight;
+            roll -= weight;
         }
-        &self.prototypes[0]
+        self.prototypes.last()
     }
 }
 
 pub fn spawn_enemies(
     mut commands: Commands,
     time: Res<Time>,
-    mut timer: ResMut<EnemySpawnTimer>,
+    mut director: ResMut<WaveDirector>,
+    timeline: Res<WaveTimeline>,
     enemy_catalog: Res<EnemyCatalog>,
     player_query: Query<&Transform, With<Player>>,
+    view: Res<CameraView>,
+    map: Res<ArenaMap>,
+    mut run_rng: ResMut<RunRng>,
 ) {
-    if timer.0.tick(time.delta()).just_finished() {
-        let Ok(player_transform) = player_query.single() else {
+    let batches = director.tick(time.delta(), &timeline);
+    if batches == 0 {
+        return;
+    }
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+
+    let wave = director.wave(&timeline);
+    let rng = run_rng.gameplay();
+    for _ in 0..batches * wave.batch_size {
+        let Some(prototype) = enemy_catalog.random_prototype(rng, |prototype| {
+            wave.weight_multiplier(&prototype.name) * map.enemy_weight(&prototype.name)
+        }) else {
             return;
         };
-
-        let mut rng = rand::rng();
-        let prototype = enemy_catalog.random_prototype(&mut rng);
-        let attributes = prototype.attributes;
-        let spawn_side = rng.random_range(0..4);
-        let offset = rng.random_range(-ARENA_HALF_SIZE..=ARENA_HALF_SIZE);
-        let (x, y) = match spawn_side {
-            0 => (-ARENA_HALF_SIZE - 40.0, offset),
-            1 => (ARENA_HALF_SIZE + 40.0, offset),
-            2 => (offset, -ARENA_HALF_SIZE - 40.0),
-            _ => (offset, ARENA_HALF_SIZE + 40.0),
-        };
-
-        let target = player_transform.translation.xy();
-        let dir = (target - Vec2::new(x, y)).normalize_or_zero();
-
-        commands.spawn((
-            DespawnOnExit(MainState::Game),
-            LevelEntity,
-            Sprite {
-                color: attributes.color,
-                custom_size: Some(attributes.size),
-                ..default()
-            },
-            Transform::from_translation(Vec3::new(x, y, 0.0)),
-            Enemy,
+        let attributes = wave.scale_attributes(prototype.attributes);
+        let position = map.random_spawn_point(&view, rng);
+        let dir = (player_transform.translation.xy() - position).normalize_or_zero();
+        spawn_enemy(
+            &mut commands,
+            prototype.name.clone(),
             attributes,
-            Health::new(attributes.health),
-            Velocity(dir * attributes.speed),
-        ));
+            prototype.behavior,
+            position,
+            dir * attributes.speed,
+            rng,
+        );
     }
 }
 
+pub fn spawn_enemy(
+    commands: &mut Commands,
+    name: String,
+    attributes: EnemyAttributes,
+    behavior: EnemyBehavior,
+    position: Vec2,
+    velocity: Vec2,
+    rng: &mut impl Rng,
+) -> Entity {
+    let mut entity = commands.spawn((
+        DespawnOnExit(MainState::Game),
+        LevelEntity,
+        Name::new(name),
+        Sprite {
+            color: attributes.color,
+            custom_size: Some(attributes.size),
+            ..default()
+        },
+        Transform::from_translation(position.extend(0.0)),
+        Enemy,
+        attributes,
+        behavior,
+        Health::new(attributes.health),
+        Velocity(velocity),
+    ));
+    insert_behavior_state(&mut entity, behavior, rng);
+    entity.id()
+}
+
 pub fn update_enemy_hit_flash(
     mut commands: Commands,
     time: Res<Time>,
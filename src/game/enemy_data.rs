@@ -0,0 +1,225 @@
+use std::collections::HashSet;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::behavior::EnemyBehavior;
+use super::enemy::{EnemyAttributes, EnemyCatalog, EnemyPrototype};
+use super::pickup::DropTable;
+use super::waves::{WaveTimeline, check_timeline_prototypes};
+
+pub const ENEMY_CATALOG_PATH: &str = "data/catalog.enemies.ron";
+
+/// Validated enemy prototypes loaded from a `*.enemies.ron` file.
+#[derive(Asset, TypePath, Debug)]
+pub struct EnemyCatalogAsset {
+    pub prototypes: Vec<EnemyPrototype>,
+}
+
+#[derive(Resource)]
+pub struct EnemyCatalogHandle(pub Handle<EnemyCatalogAsset>);
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EnemyCatalogFile {
+    enemies: Vec<EnemyDefinition>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EnemyDefinition {
+    name: String,
+    health: i32,
+    size: (f32, f32),
+    speed: f32,
+    damage: i32,
+    score_value: u32,
+    xp_value: u32,
+    color: (f32, f32, f32),
+    weight: f32,
+    #[serde(default)]
+    behavior: EnemyBehavior,
+    #[serde(default)]
+    drops: DropTable,
+}
+
+#[derive(Debug, Error)]
+pub enum EnemyCatalogError {
+    #[error("could not read enemy catalog: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse enemy catalog: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("enemy catalog must define at least one enemy")]
+    Empty,
+    #[error("enemy #{index} has an empty `name`")]
+    MissingName { index: usize },
+    #[error("enemy `{name}` is defined more than once")]
+    DuplicateName { name: String },
+    #[error("enemy `{name}`: `{field}` {reason}")]
+    InvalidField {
+        name: String,
+        field: &'static str,
+        reason: &'static str,
+    },
+    #[error("enemy `{name}`: behavior parameter `{parameter}` is out of range")]
+    InvalidBehavior {
+        name: String,
+        parameter: &'static str,
+    },
+    #[error("enemy `{name}`: drop table `{parameter}` is out of range")]
+    InvalidDrops {
+        name: String,
+        parameter: &'static str,
+    },
+}
+
+impl EnemyDefinition {
+    fn validate(&self) -> Result<(), EnemyCatalogError> {
+        let invalid = |field, reason| EnemyCatalogError::InvalidField {
+            name: self.name.clone(),
+            field,
+            reason,
+        };
+
+        if self.health <= 0 {
+            return Err(invalid("health", "must be greater than zero"));
+        }
+        let (width, height) = self.size;
+        if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+            return Err(invalid("size", "must have positive, finite dimensions"));
+        }
+        if !(self.speed.is_finite() && self.speed >= 0.0) {
+            return Err(invalid("speed", "must be a finite, non-negative number"));
+        }
+        if self.damage < 0 {
+            return Err(invalid("damage", "must not be negative"));
+        }
+        let (r, g, b) = self.color;
+        if ![r, g, b].iter().all(|c| (0.0..=1.0).contains(c)) {
+            return Err(invalid("color", "components must be within 0.0..=1.0"));
+        }
+        if !(self.weight.is_finite() && self.weight > 0.0) {
+            return Err(invalid(
+                "weight",
+                "must be a finite number greater than zero",
+            ));
+        }
+        if let Some(parameter) = self.behavior.invalid_parameter() {
+            return Err(EnemyCatalogError::InvalidBehavior {
+                name: self.name.clone(),
+                parameter,
+            });
+        }
+        if let Some(parameter) = self.drops.invalid_parameter() {
+            return Err(EnemyCatalogError::InvalidDrops {
+                name: self.name.clone(),
+                parameter,
+            });
+        }
+        Ok(())
+    }
+
+    fn into_prototype(self) -> EnemyPrototype {
+        let (r, g, b) = self.color;
+        EnemyPrototype {
+            name: self.name,
+            attributes: EnemyAttributes {
+                health: self.health,
+                size: Vec2::new(self.size.0, self.size.1),
+                speed: self.speed,
+                damage: self.damage,
+                score_value: self.score_value,
+                xp_value: self.xp_value,
+                color: Color::srgb(r, g, b),
+            },
+            behavior: self.behavior,
+            weight: self.weight,
+            drops: self.drops,
+        }
+    }
+}
+
+#[derive(Default, TypePath)]
+pub struct EnemyCatalogLoader;
+
+impl AssetLoader for EnemyCatalogLoader {
+    type Asset = EnemyCatalogAsset;
+    type Settings = ();
+    type Error = EnemyCatalogError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file: EnemyCatalogFile = ron::de::from_bytes(&bytes)?;
+
+        if file.enemies.is_empty() {
+            return Err(EnemyCatalogError::Empty);
+        }
+        let mut names = HashSet::new();
+        for (index, enemy) in file.enemies.iter().enumerate() {
+            if enemy.name.trim().is_empty() {
+                return Err(EnemyCatalogError::MissingName { index });
+            }
+            if !names.insert(enemy.name.as_str()) {
+                return Err(EnemyCatalogError::DuplicateName {
+                    name: enemy.name.clone(),
+                });
+            }
+            enemy.validate()?;
+        }
+
+        Ok(EnemyCatalogAsset {
+            prototypes: file
+                .enemies
+                .into_iter()
+                .map(EnemyDefinition::into_prototype)
+                .collect(),
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["enemies.ron"]
+    }
+}
+
+pub fn load_enemy_catalog(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(EnemyCatalogHandle(asset_server.load(ENEMY_CATALOG_PATH)));
+}
+
+/// Copies the catalog asset into [`EnemyCatalog`] whenever it is (re)loaded. A file that fails
+/// validation leaves the previous catalog in place.
+pub fn sync_enemy_catalog(
+    mut asset_events: MessageReader<AssetEvent<EnemyCatalogAsset>>,
+    assets: Res<Assets<EnemyCatalogAsset>>,
+    handle: Res<EnemyCatalogHandle>,
+    mut catalog: ResMut<EnemyCatalog>,
+    timeline: Res<WaveTimeline>,
+) {
+    for event in asset_events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = *event
+        else {
+            continue;
+        };
+        if id != handle.0.id() {
+            continue;
+        }
+        let Some(asset) = assets.get(id) else {
+            continue;
+        };
+        *catalog = EnemyCatalog::from_prototypes(asset.prototypes.clone());
+        info!(
+            "Loaded {} enemy prototypes from {}",
+            asset.prototypes.len(),
+            ENEMY_CATALOG_PATH
+        );
+        check_timeline_prototypes(&timeline, &catalog);
+    }
+}
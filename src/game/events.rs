@@ -1,7 +1,48 @@
 use bevy::prelude::*;
 
 #[derive(Message)]
-pub struct PlayerHit;
+pub struct PlayerHit {
+    pub damage: i32,
+}
 
+use super::behavior::EnemyBehavior;
+use super::enemy::EnemyAttributes;
+use super::powerup::PowerUpChoice;
+
+#[derive(Message)]
+pub struct EnemyKilled {
+    pub name: String,
+    pub position: Vec2,
+    pub attributes: EnemyAttributes,
+    pub behavior: EnemyBehavior,
+}
+
+#[derive(Message)]
+pub struct BossDefeated {
+    pub position: Vec2,
+}
+
+/// Damage dealt to an enemy by a weapon that doesn't use `Projectile` or `BombExplosion`.
+/// Applied, together with kills and hit flashes, by `handle_collisions`.
+#[derive(Message)]
+pub struct DamageEnemy {
+    pub target: Entity,
+    pub amount: i32,
+    /// Whether the hit applies the player's infusions. False for damage that isn't a weapon
+    /// hit, like burning.
+    pub on_hit: bool,
+}
+
+/// Damage that landed on an enemy, written by `handle_collisions` for every hit. Shown as a
+/// floating number.
+#[derive(Message)]
+pub struct EnemyDamaged {
+    pub position: Vec2,
+    pub amount: i32,
+    pub crit: bool,
+}
+
+/// A pick from the power-up offer, made through the menu or by the autopilot. Applied by
+/// `apply_powerup_selection`.
 #[derive(Message)]
-pub struct EnemyKilled;
+pub struct PowerUpSelected(pub PowerUpChoice);
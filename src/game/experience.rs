@@ -10,8 +10,11 @@ use super::constants::{
     EXPERIENCE_ORB_MAGNET_BASE_SPEED, EXPERIENCE_ORB_MAGNET_MAX_SPEED,
     EXPERIENCE_ORB_MAGNET_RADIUS, EXPERIENCE_ORB_SIZE, PLAYER_SIZE,
 };
+use super::events::EnemyKilled;
 use super::powerup::PowerUpProgress;
 use super::resources::ExperienceOrbSound;
+use super::rng::RunRng;
+use super::spatial::SpatialIndex;
 use crate::MainState;
 use crate::audio::{SEVolume, spawn_se};
 use crate::game::components::LevelEntity;
@@ -26,6 +29,7 @@ pub fn experience_orb_behavior(
     player_query: Query<&Transform, With<Player>>,
     exp_sound: Res<ExperienceOrbSound>,
     se_volume: Res<SEVolume>,
+    index: Res<SpatialIndex>,
 ) {
     let Ok(player_transform) = player_query.single() else {
         return;
@@ -33,15 +37,22 @@ pub fn experience_orb_behavior(
     let player_pos = player_transform.translation.xy();
     let pickup_radius = (PLAYER_SIZE.x + EXPERIENCE_ORB_SIZE) * 0.5;
 
-    for (entity, mut orb, mut velocity, transform) in &mut orbs {
+    for candidate in index
+        .orbs
+        .candidates(player_pos, EXPERIENCE_ORB_MAGNET_RADIUS)
+    {
+        if candidate.position.distance(player_pos) <= EXPERIENCE_ORB_MAGNET_RADIUS
+            && let Ok((_, mut orb, _, _)) = orbs.get_mut(candidate.entity)
+        {
+            orb.magnetized = true;
+        }
+    }
+
+    for (entity, orb, mut velocity, transform) in &mut orbs {
         let orb_pos = transform.translation.xy();
         let to_player = player_pos - orb_pos;
         let distance = to_player.length();
 
-        if !orb.magnetized && distance <= EXPERIENCE_ORB_MAGNET_RADIUS {
-            orb.magnetized = true;
-        }
-
         if distance <= pickup_radius {
             player_stats.experience = player_stats.experience.saturating_add(orb.value);
             powerup_progress.add_experience(orb.value);
@@ -82,26 +93,61 @@ pub fn setup(
     commands.insert_resource(orb_mesh);
 }
 
+/// Every defeated enemy, bosses included, leaves an orb worth its `xp_value`.
+pub fn drop_experience_orbs(
+    mut commands: Commands,
+    mut enemy_killed_messages: MessageReader<EnemyKilled>,
+    orb: Res<OrbMesh>,
+    mut run_rng: ResMut<RunRng>,
+) {
+    for killed in enemy_killed_messages.read() {
+        spawn_experience_orb(
+            &mut commands,
+            &orb,
+            run_rng.gameplay(),
+            killed.position,
+            killed.attributes.xp_value,
+        );
+    }
+}
+
 pub fn spawn_experience_orb(
     commands: &mut Commands,
     orb: &Res<OrbMesh>,
+    rng: &mut impl Rng,
     position: Vec2,
     value: u32,
 ) {
-    let mut rng = rand::rng();
     let angle = rng.random_range(0.0f32..TAU);
     let speed =
         rng.random_range(EXPERIENCE_ORB_INITIAL_SPEED_MIN..EXPERIENCE_ORB_INITIAL_SPEED_MAX);
     let velocity = Vec2::new(angle.cos(), angle.sin()) * speed;
-    commands.spawn((
-        DespawnOnExit(MainState::Game),
-        LevelEntity,
-        orb.0.clone(),
-        Transform::from_translation(Vec3::new(position.x, position.y, 0.8)),
+    spawn_orb(
+        commands,
+        orb,
+        position,
+        velocity,
         ExperienceOrb {
             value,
             magnetized: false,
         },
+    );
+}
+
+/// Spawns an orb that is already moving, as when resuming a saved run.
+pub fn spawn_orb(
+    commands: &mut Commands,
+    mesh: &OrbMesh,
+    position: Vec2,
+    velocity: Vec2,
+    orb: ExperienceOrb,
+) {
+    commands.spawn((
+        DespawnOnExit(MainState::Game),
+        LevelEntity,
+        mesh.0.clone(),
+        Transform::from_translation(position.extend(0.8)),
+        orb,
         Velocity(velocity),
     ));
 }
@@ -0,0 +1,515 @@
+use bevy::ecs::spawn::SpawnIter;
+use bevy::prelude::*;
+
+use super::arena::SelectedMap;
+use super::character::Character;
+use super::player::PlayerStats;
+use super::powerup::{PlayerUpgrades, PowerUpProgress};
+use super::replay::{ActiveReplay, ReplayMode};
+use super::rng::{RunRng, random_seed};
+use super::status::ALL_INFUSIONS;
+use super::ui::Score;
+use super::weapon::Weapon;
+use crate::controls::{ActionInput, Controls, InputAction};
+use crate::game::components::LevelEntity;
+use crate::game::{reset_game, start_replay};
+use crate::highscore::{HighScoreEntry, HighScores, format_date, format_duration, now_unix_secs};
+use crate::shop::MetaProgress;
+use crate::unlocks::CareerStats;
+use crate::{Difficulty, MainState};
+
+const OVERLAY_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.75);
+const PANEL_COLOR: Color = Color::srgba(0.08, 0.08, 0.12, 0.95);
+const BUTTON_COLOR: Color = Color::srgba(0.18, 0.18, 0.28, 0.95);
+const BUTTON_HOVER_COLOR: Color = Color::srgba(0.28, 0.28, 0.38, 0.95);
+const BUTTON_PRESSED_COLOR: Color = Color::srgba(0.35, 0.65, 0.35, 1.0);
+const LABEL_COLOR: Color = Color::srgba(0.8, 0.8, 0.85, 1.0);
+const NEW_RECORD_COLOR: Color = Color::srgb(1.0, 0.85, 0.3);
+/// How many leaderboard rows fit on the game-over panel.
+const HIGH_SCORE_ROWS: usize = 5;
+
+/// Rank of the run that just ended on its difficulty's leaderboard, if it made it.
+#[derive(Resource, Default)]
+pub struct NewHighScoreRank(pub Option<usize>);
+
+/// Gold the run that just ended earned for the shop.
+#[derive(Resource, Default)]
+pub struct GoldEarned(pub u32);
+
+/// Characters the run that just ended unlocked.
+#[derive(Resource, Default)]
+pub struct NewUnlocks(pub Vec<Character>);
+
+#[derive(Component)]
+pub struct GameOverOverlay;
+
+#[derive(Component)]
+pub struct GameOverButton {
+    action: GameOverAction,
+}
+
+#[derive(Clone, Copy)]
+enum GameOverAction {
+    Retry,
+    RetrySeed,
+    WatchReplay,
+    QuitToMenu,
+}
+
+pub fn record_high_score(
+    mut new_rank: ResMut<NewHighScoreRank>,
+    mut high_scores: ResMut<HighScores>,
+    difficulty: Res<Difficulty>,
+    score: Res<Score>,
+    stats: Res<PlayerStats>,
+    progress: Res<PowerUpProgress>,
+    replay: Res<ActiveReplay>,
+) {
+    // Watching a replay doesn't earn a place on the leaderboard again.
+    if replay.mode() == ReplayMode::Playback {
+        new_rank.0 = None;
+        return;
+    }
+    let rank = high_scores.record(
+        *difficulty,
+        HighScoreEntry {
+            score: score.0,
+            time_survived: stats.time_survived,
+            level: progress.level(),
+            recorded_at: now_unix_secs(),
+        },
+    );
+    new_rank.0 = rank;
+}
+
+pub fn award_gold(
+    mut earned: ResMut<GoldEarned>,
+    mut meta: ResMut<MetaProgress>,
+    score: Res<Score>,
+    stats: Res<PlayerStats>,
+    replay: Res<ActiveReplay>,
+) {
+    // Watching a replay doesn't pay out again.
+    if replay.mode() == ReplayMode::Playback {
+        earned.0 = 0;
+        return;
+    }
+    earned.0 = MetaProgress::gold_for_run(score.0, stats.enemies_killed);
+    meta.gold = meta.gold.saturating_add(earned.0);
+}
+
+pub fn record_career(
+    mut new_unlocks: ResMut<NewUnlocks>,
+    mut career: ResMut<CareerStats>,
+    stats: Res<PlayerStats>,
+    replay: Res<ActiveReplay>,
+) {
+    // Watching a replay doesn't count towards unlocks again.
+    if replay.mode() == ReplayMode::Playback {
+        new_unlocks.0.clear();
+        return;
+    }
+    new_unlocks.0 = career.record_run(
+        stats.enemies_killed,
+        stats.bosses_defeated,
+        stats.time_survived,
+    );
+}
+
+pub fn spawn_game_over(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    score: Res<Score>,
+    stats: Res<PlayerStats>,
+    progress: Res<PowerUpProgress>,
+    upgrades: Res<PlayerUpgrades>,
+    weapons: Query<&Weapon>,
+    run_rng: Res<RunRng>,
+    replay: Res<ActiveReplay>,
+    high_scores: Res<HighScores>,
+    (new_rank, gold_earned, new_unlocks): (Res<NewHighScoreRank>, Res<GoldEarned>, Res<NewUnlocks>),
+    difficulty: Res<Difficulty>,
+    controls: Res<Controls>,
+) {
+    let font_handle = asset_server.load("fonts/FiraSans-Bold.ttf");
+
+    let mut upgrades_taken: Vec<String> = weapons
+        .iter()
+        .map(|weapon| format!("{} Lv{}", weapon.kind.label(), weapon.level))
+        .collect();
+    if upgrades.move_speed_level > 0 {
+        upgrades_taken.push(format!("Move Speed x{}", upgrades.move_speed_level));
+    }
+    if upgrades.crit_chance_level > 0 {
+        upgrades_taken.push(format!("Critical Chance x{}", upgrades.crit_chance_level));
+    }
+    if upgrades.crit_damage_level > 0 {
+        upgrades_taken.push(format!("Critical Damage x{}", upgrades.crit_damage_level));
+    }
+    for infusion in ALL_INFUSIONS {
+        let level = upgrades.infusion_level(infusion);
+        if level > 0 {
+            upgrades_taken.push(format!("{} x{}", infusion.label(), level));
+        }
+    }
+    let upgrades_text = upgrades_taken.join("\n");
+    // A resumed run's replay starts at the save and can't be played back.
+    let can_watch_replay = !replay.is_resumed();
+
+    let mut summary = vec![
+        ("Character", upgrades.character.label().to_string()),
+        ("Score", score.0.to_string()),
+        ("Time Survived", format_duration(stats.time_survived)),
+        ("Level", progress.level().to_string()),
+        ("Enemies Killed", stats.enemies_killed.to_string()),
+        ("Loadout", upgrades_text),
+        ("Seed", run_rng.seed().to_string()),
+    ];
+    match replay.mode() {
+        ReplayMode::Recording => {
+            summary.push(("Gold Earned", format!("+{}", gold_earned.0)));
+            if !new_unlocks.0.is_empty() {
+                let names: Vec<&str> = new_unlocks.0.iter().map(|c| c.label()).collect();
+                summary.push(("Unlocked", names.join("\n")));
+            }
+        }
+        ReplayMode::Playback => summary.push((
+            "Replay",
+            match replay.desynced_at() {
+                Some(tick) => format!("Desynced at tick {tick}"),
+                None => "In sync".to_string(),
+            },
+        )),
+    }
+
+    commands
+        .spawn((
+            DespawnOnExit(MainState::Game),
+            GameOverOverlay,
+            Node {
+                width: percent(100),
+                height: percent(100),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            BackgroundColor(OVERLAY_COLOR),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    width: Val::Px(480.0),
+                    row_gap: Val::Px(20.0),
+                    padding: UiRect::axes(Val::Px(32.0), Val::Px(28.0)),
+                    align_items: AlignItems::Stretch,
+                    ..default()
+                },
+                BackgroundColor(PANEL_COLOR),
+            ))
+            .with_children(|panel| {
+                panel.spawn((
+                    Text::new("Game Over".to_string()),
+                    TextFont {
+                        font: font_handle.clone(),
+                        font_size: 44.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+                panel
+                    .spawn((Node {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(6.0),
+                        ..default()
+                    },))
+                    .with_children(|list| {
+                        for (label, value) in summary {
+                            list.spawn(summary_row_bundle(font_handle.clone(), label, value));
+                        }
+                    });
+                panel.spawn((
+                    Text::new(match new_rank.0 {
+                        Some(rank) => format!("New High Score! #{} ({:?})", rank + 1, *difficulty),
+                        None => format!("High Scores ({:?})", *difficulty),
+                    }),
+                    TextFont {
+                        font: font_handle.clone(),
+                        font_size: 26.0,
+                        ..default()
+                    },
+                    TextColor(if new_rank.0.is_some() {
+                        NEW_RECORD_COLOR
+                    } else {
+                        Color::WHITE
+                    }),
+                ));
+                panel
+                    .spawn((Node {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(4.0),
+                        ..default()
+                    },))
+                    .with_children(|list| {
+                        for (rank, entry) in high_scores
+                            .entries(*difficulty)
+                            .iter()
+                            .take(HIGH_SCORE_ROWS)
+                            .enumerate()
+                        {
+                            list.spawn(high_score_row_bundle(
+                                font_handle.clone(),
+                                rank,
+                                entry,
+                                new_rank.0 == Some(rank),
+                            ));
+                        }
+                    });
+                panel
+                    .spawn((Node {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(12.0),
+                        ..default()
+                    },))
+                    .with_children(|list| {
+                        list.spawn(game_over_button_bundle(
+                            font_handle.clone(),
+                            "Retry",
+                            &controls.key_hint(InputAction::Confirm),
+                            GameOverAction::Retry,
+                        ));
+                        list.spawn(game_over_button_bundle(
+                            font_handle.clone(),
+                            "Retry Same Seed",
+                            &controls.key_hint(InputAction::RetrySameSeed),
+                            GameOverAction::RetrySeed,
+                        ));
+                        if can_watch_replay {
+                            list.spawn(game_over_button_bundle(
+                                font_handle.clone(),
+                                "Watch Replay",
+                                &controls.key_hint(InputAction::WatchReplay),
+                                GameOverAction::WatchReplay,
+                            ));
+                        }
+                        list.spawn(game_over_button_bundle(
+                            font_handle.clone(),
+                            "Back to Menu",
+                            &controls.key_hint(InputAction::QuitToMenu),
+                            GameOverAction::QuitToMenu,
+                        ));
+                    });
+            });
+        });
+}
+
+pub fn game_over_button_visuals(
+    mut query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>, With<GameOverButton>),
+    >,
+) {
+    for (interaction, mut color) in &mut query {
+        *color = match *interaction {
+            Interaction::Pressed => BUTTON_PRESSED_COLOR.into(),
+            Interaction::Hovered => BUTTON_HOVER_COLOR.into(),
+            Interaction::None => BUTTON_COLOR.into(),
+        };
+    }
+}
+
+pub fn game_over_menu_actions(
+    input: ActionInput,
+    mut button_interactions: Query<
+        (&Interaction, &GameOverButton),
+        (Changed<Interaction>, With<Button>),
+    >,
+    overlay: Query<Entity, With<GameOverOverlay>>,
+    mut commands: Commands,
+    level_entity_query: Query<Entity, With<LevelEntity>>,
+    difficulty: Res<Difficulty>,
+    selected_map: Res<SelectedMap>,
+    character: Res<Character>,
+    meta: Res<MetaProgress>,
+    run_rng: Res<RunRng>,
+    replay: Res<ActiveReplay>,
+    mut main_state: ResMut<NextState<MainState>>,
+) {
+    // The gamepad's confirm presses whichever button it is on instead.
+    let mut retry_requested = input.key_just_pressed(InputAction::Confirm);
+    let mut retry_seed_requested = input.just_pressed(InputAction::RetrySameSeed);
+    let mut replay_requested = input.just_pressed(InputAction::WatchReplay);
+    let mut menu_requested = input.just_pressed(InputAction::QuitToMenu);
+
+    for (interaction, button) in &mut button_interactions {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button.action {
+            GameOverAction::Retry => retry_requested = true,
+            GameOverAction::RetrySeed => retry_seed_requested = true,
+            GameOverAction::WatchReplay => replay_requested = true,
+            GameOverAction::QuitToMenu => menu_requested = true,
+        }
+    }
+
+    if retry_requested || retry_seed_requested {
+        let seed = if retry_seed_requested {
+            run_rng.seed()
+        } else {
+            random_seed()
+        };
+        reset_game(
+            &mut commands,
+            Some(&level_entity_query),
+            *difficulty,
+            *selected_map,
+            *character,
+            meta.bonuses,
+            seed,
+        );
+        close_game_over_overlay(&mut commands, &overlay);
+        return;
+    }
+
+    if replay_requested && !replay.is_resumed() {
+        start_replay(
+            &mut commands,
+            Some(&level_entity_query),
+            replay.replay().clone(),
+        );
+        close_game_over_overlay(&mut commands, &overlay);
+        return;
+    }
+
+    if menu_requested {
+        main_state.set(MainState::Menu);
+        close_game_over_overlay(&mut commands, &overlay);
+    }
+}
+
+fn summary_row_bundle(font: Handle<Font>, label: &str, value: String) -> impl Bundle {
+    (
+        Node {
+            flex_direction: FlexDirection::Row,
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::SpaceBetween,
+            column_gap: Val::Px(24.0),
+            ..default()
+        },
+        children![
+            (
+                Text::new(label.to_string()),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 22.0,
+                    ..default()
+                },
+                TextColor(LABEL_COLOR),
+            ),
+            (
+                Text::new(value),
+                TextFont {
+                    font,
+                    font_size: 22.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                TextLayout::new_with_justify(Justify::Right),
+            ),
+        ],
+    )
+}
+
+fn high_score_row_bundle(
+    font: Handle<Font>,
+    rank: usize,
+    entry: &HighScoreEntry,
+    highlighted: bool,
+) -> impl Bundle {
+    let color = if highlighted {
+        NEW_RECORD_COLOR
+    } else {
+        LABEL_COLOR
+    };
+    (
+        Node {
+            flex_direction: FlexDirection::Row,
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::SpaceBetween,
+            ..default()
+        },
+        Children::spawn(SpawnIter(
+            [
+                format!("#{}", rank + 1),
+                entry.score.to_string(),
+                format_duration(entry.time_survived),
+                format!("Lv {}", entry.level),
+                format_date(entry.recorded_at),
+            ]
+            .into_iter()
+            .map(move |value| {
+                (
+                    Text::new(value),
+                    TextFont {
+                        font: font.clone(),
+                        font_size: 18.0,
+                        ..default()
+                    },
+                    TextColor(color),
+                )
+            }),
+        )),
+    )
+}
+
+fn game_over_button_bundle(
+    font: Handle<Font>,
+    label: &str,
+    hint: &str,
+    action: GameOverAction,
+) -> impl Bundle {
+    (
+        Button,
+        GameOverButton { action },
+        Node {
+            flex_direction: FlexDirection::Row,
+            width: Val::Percent(100.0),
+            padding: UiRect::axes(Val::Px(18.0), Val::Px(14.0)),
+            row_gap: Val::Px(4.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::SpaceBetween,
+            ..default()
+        },
+        BackgroundColor(BUTTON_COLOR),
+        children![
+            (
+                Text::new(label.to_string()),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 32.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ),
+            (
+                Text::new(hint.to_string()),
+                TextFont {
+                    font,
+                    font_size: 18.0,
+                    ..default()
+                },
+                TextColor(LABEL_COLOR),
+            ),
+        ],
+    )
+}
+
+fn close_game_over_overlay(
+    commands: &mut Commands,
+    overlay: &Query<Entity, With<GameOverOverlay>>,
+) {
+    for entity in overlay.iter() {
+        commands.entity(entity).despawn();
+    }
+}
@@ -0,0 +1,310 @@
+use std::collections::BTreeMap;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::arena::{ArenaBounds, ArenaMap, MAPS, MapCatalog, SpawnZone};
+use super::obstacles::{Collider, Obstacle, ObstacleKind, PLAYER_RADIUS};
+
+/// Validated map loaded from a `*.map.ron` file.
+#[derive(Asset, TypePath, Debug)]
+pub struct ArenaMapAsset {
+    pub map: ArenaMap,
+}
+
+/// One handle per entry of [`MAPS`], in the same order.
+#[derive(Resource)]
+pub struct ArenaMapHandles(pub Vec<(&'static str, Handle<ArenaMapAsset>)>);
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ArenaMapFile {
+    name: String,
+    bounds: BoundsDefinition,
+    background: (f32, f32, f32),
+    #[serde(default)]
+    spawn_zones: Vec<SpawnZoneDefinition>,
+    #[serde(default)]
+    enemy_weights: BTreeMap<String, f32>,
+    #[serde(default)]
+    obstacles: Vec<ObstacleDefinition>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+enum BoundsDefinition {
+    Rect { width: f32, height: f32 },
+    Circle { radius: f32 },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpawnZoneDefinition {
+    position: (f32, f32),
+    radius: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObstacleDefinition {
+    kind: ObstacleKind,
+    position: (f32, f32),
+    shape: ShapeDefinition,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+enum ShapeDefinition {
+    Circle { radius: f32 },
+    Box { width: f32, height: f32 },
+}
+
+#[derive(Debug, Error)]
+pub enum ArenaMapError {
+    #[error("could not read arena map: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse arena map: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("arena map has an empty `name`")]
+    MissingName,
+    #[error("`{field}` {reason}")]
+    InvalidMapField {
+        field: &'static str,
+        reason: &'static str,
+    },
+    #[error("spawn zone #{index}: `{field}` {reason}")]
+    InvalidSpawnZone {
+        index: usize,
+        field: &'static str,
+        reason: &'static str,
+    },
+    #[error("enemy weight for `{name}` must be finite and not negative")]
+    InvalidEnemyWeight { name: String },
+    #[error("obstacle #{index}: `{field}` {reason}")]
+    InvalidField {
+        index: usize,
+        field: &'static str,
+        reason: &'static str,
+    },
+    #[error("obstacle #{index} covers the player's starting point")]
+    CoversStart { index: usize },
+}
+
+fn positive(value: f32) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+fn finite_point((x, y): (f32, f32)) -> Option<Vec2> {
+    (x.is_finite() && y.is_finite()).then_some(Vec2::new(x, y))
+}
+
+impl BoundsDefinition {
+    fn into_bounds(self) -> Result<ArenaBounds, ArenaMapError> {
+        let bounds = match self {
+            BoundsDefinition::Rect { width, height } if positive(width) && positive(height) => {
+                ArenaBounds::Rect {
+                    half_size: Vec2::new(width, height) * 0.5,
+                }
+            }
+            BoundsDefinition::Circle { radius } if positive(radius) => {
+                ArenaBounds::Circle { radius }
+            }
+            _ => {
+                return Err(ArenaMapError::InvalidMapField {
+                    field: "bounds",
+                    reason: "must have positive, finite dimensions",
+                });
+            }
+        };
+        // The player starts at the origin and needs room to stand there.
+        if bounds.edge(Vec2::ZERO).0 < PLAYER_RADIUS {
+            return Err(ArenaMapError::InvalidMapField {
+                field: "bounds",
+                reason: "must leave room for the player at (0, 0)",
+            });
+        }
+        Ok(bounds)
+    }
+}
+
+impl SpawnZoneDefinition {
+    fn into_zone(self, index: usize, bounds: ArenaBounds) -> Result<SpawnZone, ArenaMapError> {
+        let invalid = |field, reason| ArenaMapError::InvalidSpawnZone {
+            index,
+            field,
+            reason,
+        };
+        let Some(center) = finite_point(self.position) else {
+            return Err(invalid("position", "must be finite"));
+        };
+        if !bounds.contains(center) {
+            return Err(invalid("position", "must be inside the bounds"));
+        }
+        if !positive(self.radius) {
+            return Err(invalid(
+                "radius",
+                "must be a finite number greater than zero",
+            ));
+        }
+        Ok(SpawnZone {
+            center,
+            radius: self.radius,
+        })
+    }
+}
+
+impl ObstacleDefinition {
+    fn into_obstacle(self, index: usize) -> Result<Obstacle, ArenaMapError> {
+        let invalid = |field, reason| ArenaMapError::InvalidField {
+            index,
+            field,
+            reason,
+        };
+
+        let Some(position) = finite_point(self.position) else {
+            return Err(invalid("position", "must be finite"));
+        };
+        let collider = match self.shape {
+            ShapeDefinition::Circle { radius } => {
+                if !positive(radius) {
+                    return Err(invalid(
+                        "radius",
+                        "must be a finite number greater than zero",
+                    ));
+                }
+                Collider::Circle { radius }
+            }
+            ShapeDefinition::Box { width, height } => {
+                if !(positive(width) && positive(height)) {
+                    return Err(invalid("shape", "must have positive, finite dimensions"));
+                }
+                Collider::Box {
+                    half_size: Vec2::new(width, height) * 0.5,
+                }
+            }
+        };
+
+        let obstacle = Obstacle {
+            kind: self.kind,
+            position,
+            collider,
+        };
+        if obstacle.separation(Vec2::ZERO).0 < PLAYER_RADIUS {
+            return Err(ArenaMapError::CoversStart { index });
+        }
+        Ok(obstacle)
+    }
+}
+
+impl ArenaMapFile {
+    fn into_map(self) -> Result<ArenaMap, ArenaMapError> {
+        if self.name.trim().is_empty() {
+            return Err(ArenaMapError::MissingName);
+        }
+        let bounds = self.bounds.into_bounds()?;
+        let (r, g, b) = self.background;
+        if ![r, g, b]
+            .iter()
+            .all(|channel| (0.0..=1.0).contains(channel))
+        {
+            return Err(ArenaMapError::InvalidMapField {
+                field: "background",
+                reason: "channels must be between 0 and 1",
+            });
+        }
+        if let Some((name, _)) = self
+            .enemy_weights
+            .iter()
+            .find(|(_, weight)| !(weight.is_finite() && **weight >= 0.0))
+        {
+            return Err(ArenaMapError::InvalidEnemyWeight { name: name.clone() });
+        }
+
+        let spawn_zones = self
+            .spawn_zones
+            .into_iter()
+            .enumerate()
+            .map(|(index, zone)| zone.into_zone(index, bounds))
+            .collect::<Result<_, _>>()?;
+        let obstacles = self
+            .obstacles
+            .into_iter()
+            .enumerate()
+            .map(|(index, obstacle)| obstacle.into_obstacle(index))
+            .collect::<Result<_, _>>()?;
+        Ok(ArenaMap {
+            name: self.name,
+            bounds,
+            background: Color::srgb(r, g, b),
+            spawn_zones,
+            enemy_weights: self.enemy_weights,
+            obstacles,
+        })
+    }
+}
+
+#[derive(Default, TypePath)]
+pub struct ArenaMapLoader;
+
+impl AssetLoader for ArenaMapLoader {
+    type Asset = ArenaMapAsset;
+    type Settings = ();
+    type Error = ArenaMapError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file: ArenaMapFile = ron::de::from_bytes(&bytes)?;
+        Ok(ArenaMapAsset {
+            map: file.into_map()?,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["map.ron"]
+    }
+}
+
+pub fn load_arena_maps(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handles = MAPS
+        .iter()
+        .map(|&(id, path)| (id, asset_server.load(path)))
+        .collect();
+    commands.insert_resource(ArenaMapHandles(handles));
+}
+
+/// Copies map assets into [`MapCatalog`] whenever they are (re)loaded. A file that fails
+/// validation leaves the previous version of that map in place.
+pub fn sync_arena_maps(
+    mut asset_events: MessageReader<AssetEvent<ArenaMapAsset>>,
+    assets: Res<Assets<ArenaMapAsset>>,
+    handles: Res<ArenaMapHandles>,
+    mut catalog: ResMut<MapCatalog>,
+) {
+    for event in asset_events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = *event
+        else {
+            continue;
+        };
+        let Some(&(map_id, _)) = handles.0.iter().find(|(_, handle)| handle.id() == id) else {
+            continue;
+        };
+        let Some(asset) = assets.get(id) else {
+            continue;
+        };
+        catalog.insert(map_id, asset.map.clone());
+        info!(
+            "Loaded map `{}` with {} obstacles",
+            map_id,
+            asset.map.obstacles.len()
+        );
+    }
+}
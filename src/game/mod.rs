@@ -1,37 +1,75 @@
-mod combat;
-mod components;
+pub mod arena;
+pub mod autopilot;
+pub mod behavior;
+mod boss;
+pub mod character;
+pub mod combat;
+pub mod components;
 mod constants;
-mod enemy;
-mod events;
-mod experience;
-mod movement;
+mod damage_numbers;
+pub mod enemy;
+mod enemy_data;
+pub mod events;
+pub mod experience;
+mod game_over;
+mod map_data;
+pub mod movement;
+pub mod obstacles;
 mod pause;
-mod player;
-mod powerup;
-mod resources;
-mod ui;
+pub mod pickup;
+pub mod player;
+pub mod powerup;
+pub mod replay;
+pub mod resources;
+pub mod rng;
+pub mod save;
+pub mod spatial;
+pub mod status;
+pub mod ui;
+mod wave_data;
+pub mod waves;
+pub mod weapon;
 
 use bevy::prelude::*;
-use combat::handle_collisions;
-use constants::ENEMY_SPAWN_INTERVAL;
-use events::{EnemyKilled, PlayerHit};
+use bevy_pkv::PersistentResourceAppExtensions;
+use combat::{enemy_projectile_hits, handle_collisions};
+use enemy_data::{EnemyCatalogAsset, EnemyCatalogLoader, load_enemy_catalog, sync_enemy_catalog};
+use events::{BossDefeated, DamageEnemy, EnemyDamaged, EnemyKilled, PlayerHit, PowerUpSelected};
 use experience::experience_orb_behavior;
-use movement::{decay_lifetimes, enemy_seek_player, update_projectiles, update_velocity};
+use game_over::{game_over_button_visuals, game_over_menu_actions};
+use map_data::{ArenaMapAsset, ArenaMapLoader, load_arena_maps, sync_arena_maps};
+use movement::{decay_lifetimes, enemy_repulsion, update_projectiles, update_velocity};
 use pause::{pause_button_visuals, pause_menu_actions};
-use player::player_input;
-use powerup::{PlayerUpgrades, PowerUpProgress, handle_powerup_selection, powerup_button_visuals};
+use player::{player_aim, player_input};
+use powerup::{
+    PlayerUpgrades, PowerUpOffer, PowerUpProgress, StartingBonuses, powerup_button_visuals,
+};
 use resources::{BombSound, DefeatSound, ExperienceOrbSound, HitSelfSound, HitSound, ShootSound};
+use wave_data::{WaveTimelineAsset, WaveTimelineLoader, load_wave_timeline, sync_wave_timeline};
 
 use crate::{
     Difficulty, MainState,
     audio::BGM,
     game::{
-        combat::EnemySpawnTimer,
+        arena::{ArenaMap, CameraFollow, CameraView, MapCatalog, SelectedMap, view_projection},
+        boss::BossSpawner,
+        character::Character,
         components::LevelEntity,
         enemy::EnemyCatalog,
-        player::{BombTimer, PlayerStats, ShootTimer, spawn_player},
+        pickup::EnemyFreeze,
+        player::{AimIntent, MoveIntent, Player, PlayerStats, spawn_player},
+        replay::{
+            ActiveReplay, AimInput, LastReplay, MoveInput, Replay, ReplayExportPath,
+            RequestedReplay,
+        },
+        rng::{NextRunSeed, RunRng},
+        save::{RequestedResume, SavedRun},
+        spatial::SpatialIndex,
         ui::Score,
+        waves::{WaveDirector, WaveTimeline},
+        weapon::spawn_weapon,
     },
+    shop::MetaProgress,
 };
 
 #[derive(States, Default, Clone, Copy, Eq, PartialEq, Debug, Hash)]
@@ -40,24 +78,46 @@ pub enum GameState {
     Playing,
     Paused,
     SelectingPowerUp,
+    GameOver,
 }
 
 pub fn plugin(app: &mut App) {
-    app.init_state::<GameState>()
+    app.add_plugins(simulation_plugin)
         .add_systems(
             OnEnter(MainState::Game),
-            (setup, ui::setup, experience::setup),
+            // A resumed run spawns its orbs with the orb mesh.
+            (setup.after(experience::setup), ui::setup, experience::setup),
         )
-        .insert_resource(EnemyCatalog::new())
-        .add_message::<PlayerHit>()
-        .add_message::<EnemyKilled>()
+        .insert_resource(NextRunSeed::from_args())
+        .init_persistent_resource::<LastReplay>()
+        .init_resource::<RequestedReplay>()
+        .insert_resource(ReplayExportPath::from_args())
+        .add_systems(Startup, replay::load_requested_replay)
+        .init_persistent_resource::<SavedRun>()
+        .init_resource::<RequestedResume>()
+        .add_systems(Startup, save::discard_incompatible_save)
+        .init_resource::<game_over::NewHighScoreRank>()
+        .init_resource::<game_over::GoldEarned>()
+        .init_resource::<game_over::NewUnlocks>()
         .add_systems(
             Update,
             (
                 pause::pause_input,
                 player_input,
+                player_aim,
                 ui::update_score_text,
+                ui::update_replay_status,
                 player::update_health_bar,
+                boss::update_boss_health_bar,
+            )
+                .chain()
+                .run_if(in_state(MainState::Game).and(in_state(GameState::Playing))),
+        )
+        .add_systems(
+            Update,
+            (
+                damage_numbers::spawn_damage_numbers,
+                damage_numbers::animate_damage_numbers,
             )
                 .chain()
                 .run_if(in_state(MainState::Game).and(in_state(GameState::Playing))),
@@ -70,39 +130,191 @@ pub fn plugin(app: &mut App) {
         )
         .add_systems(
             Update,
-            (powerup_button_visuals, handle_powerup_selection)
+            (powerup_button_visuals, powerup::handle_powerup_buttons)
+                .before(powerup::apply_powerup_selection)
+                .run_if(in_state(MainState::Game).and(in_state(GameState::SelectingPowerUp))),
+        )
+        .add_systems(
+            Update,
+            powerup::show_powerup_menu
+                .after(powerup::apply_powerup_selection)
+                .run_if(in_state(MainState::Game).and(resource_changed::<PowerUpOffer>)),
+        )
+        .add_systems(
+            OnEnter(GameState::GameOver),
+            (
+                replay::finish_replay,
+                game_over::record_high_score,
+                game_over::award_gold,
+                game_over::record_career,
+                game_over::spawn_game_over,
+            )
+                .chain(),
+        )
+        .add_systems(
+            Update,
+            (
+                arena::move_camera_to_view,
+                arena::draw_floor,
+                obstacles::draw_obstacles,
+            )
+                .run_if(in_state(MainState::Game)),
+        )
+        .add_systems(OnExit(MainState::Game), arena::reset_camera)
+        .add_systems(
+            Update,
+            (game_over_button_visuals, game_over_menu_actions)
+                .chain()
+                .run_if(in_state(MainState::Game).and(in_state(GameState::GameOver))),
+        );
+}
+
+/// The rules of a run and its fixed-timestep simulation, without rendering, audio or UI.
+/// The headless simulator runs this on its own.
+pub fn simulation_plugin(app: &mut App) {
+    app.init_state::<GameState>()
+        .init_resource::<EnemyCatalog>()
+        .init_resource::<WaveTimeline>()
+        .init_resource::<SelectedMap>()
+        .init_resource::<Character>()
+        .init_resource::<MapCatalog>()
+        .init_resource::<ArenaMap>()
+        .init_resource::<SpatialIndex>()
+        .init_resource::<EnemyFreeze>()
+        .init_resource::<CameraFollow>()
+        .init_resource::<CameraView>()
+        .init_resource::<MoveIntent>()
+        .init_resource::<MoveInput>()
+        .init_resource::<AimIntent>()
+        .init_resource::<AimInput>()
+        .init_resource::<PowerUpOffer>()
+        .init_asset::<EnemyCatalogAsset>()
+        .init_asset_loader::<EnemyCatalogLoader>()
+        .init_asset::<ArenaMapAsset>()
+        .init_asset_loader::<ArenaMapLoader>()
+        .init_asset::<WaveTimelineAsset>()
+        .init_asset_loader::<WaveTimelineLoader>()
+        .add_systems(
+            Startup,
+            (load_enemy_catalog, load_arena_maps, load_wave_timeline),
+        )
+        .add_systems(
+            Update,
+            (sync_enemy_catalog, sync_arena_maps, sync_wave_timeline),
+        )
+        .add_systems(
+            RunFixedMainLoop,
+            arena::apply_selected_map
+                .in_set(RunFixedMainLoopSystems::BeforeFixedMainLoop)
+                .run_if(resource_changed::<SelectedMap>.or(resource_changed::<MapCatalog>)),
+        )
+        .add_message::<PlayerHit>()
+        .add_message::<EnemyKilled>()
+        .add_message::<BossDefeated>()
+        .add_message::<DamageEnemy>()
+        .add_message::<EnemyDamaged>()
+        .add_message::<PowerUpSelected>()
+        .add_message::<status::ApplyStatus>()
+        .add_systems(
+            Update,
+            powerup::apply_powerup_selection
                 .run_if(in_state(MainState::Game).and(in_state(GameState::SelectingPowerUp))),
         )
-        // .add_systems(
-        //     Update,
-        //     center_camera_on_player.run_if(in_state(MainState::Game)),
-        // )
         .add_systems(
             FixedUpdate,
             (
-                experience_orb_behavior,
-                update_velocity,
-                enemy_seek_player,
-                update_projectiles,
-                player::constrain_to_arena,
-                player::player_place_bomb,
-                player::update_bombs,
-                handle_collisions,
-                enemy::update_enemy_hit_flash,
-                enemy::spawn_enemies,
-                player::player_auto_fire,
-                decay_lifetimes,
-                powerup::spawn_menu_when_ready,
+                // Movement
+                (
+                    replay::feed_move_input,
+                    replay::feed_aim_input,
+                    player::apply_move_input,
+                    update_velocity,
+                    spatial::rebuild_spatial_index,
+                    experience_orb_behavior,
+                    behavior::steer_enemies,
+                    enemy_repulsion,
+                    status::apply_status_movement,
+                    obstacles::steer_enemies_around_obstacles,
+                    update_projectiles,
+                    player::constrain_to_arena,
+                    arena::follow_player,
+                )
+                    .chain(),
+                // Weapons
+                (
+                    weapon::tick_weapon_cooldowns,
+                    weapon::place_bombs,
+                    weapon::update_bombs,
+                    weapon::update_blades,
+                    weapon::fire_beams,
+                    weapon::fire_lightning,
+                )
+                    .chain(),
+                // Attacks and damage
+                (
+                    behavior::enemy_ranged_attack,
+                    obstacles::block_enemy_projectiles,
+                    enemy_projectile_hits,
+                    pickup::collect_pickups,
+                    status::tick_status_effects,
+                    handle_collisions,
+                    status::apply_status_effects,
+                    experience::drop_experience_orbs,
+                    pickup::drop_pickups,
+                    behavior::split_on_death,
+                    boss::drop_boss_reward,
+                    boss::collect_boss_reward,
+                    enemy::update_enemy_hit_flash,
+                    status::tint_status_effects,
+                )
+                    .chain(),
+                // Spawning and bookkeeping
+                (
+                    enemy::spawn_enemies,
+                    boss::spawn_boss_when_due,
+                    boss::update_bosses,
+                    weapon::fire_guns,
+                    decay_lifetimes,
+                    pickup::update_pickups,
+                    pickup::tick_enemy_freeze,
+                    character::regenerate,
+                    player::track_run_stats,
+                    powerup::offer_powerups_when_ready.run_if(any_with_component::<Player>),
+                    replay::checksum_tick,
+                )
+                    .chain(),
             )
                 .chain()
-                .run_if(in_state(MainState::Game).and(in_state(GameState::Playing))),
+                .run_if(
+                    in_state(MainState::Game)
+                        .and(in_state(GameState::Playing))
+                        .and(no_pending_state_change),
+                ),
         );
 }
 
 #[derive(Component)]
-struct OnGameScreen;
+pub struct OnGameScreen;
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>, difficulty: Res<Difficulty>) {
+/// Once a tick asks to leave `Playing` (power-up menu, game over), further ticks wait for the
+/// transition. Otherwise how many more run first would depend on how many fit into the frame,
+/// and replays would drift.
+fn no_pending_state_change(next_state: Res<NextState<GameState>>) -> bool {
+    matches!(*next_state, NextState::Unchanged)
+}
+
+fn setup(
+    mut commands: Commands,
+    camera: Single<Entity, With<Camera2d>>,
+    asset_server: Res<AssetServer>,
+    difficulty: Res<Difficulty>,
+    selected_map: Res<SelectedMap>,
+    character: Res<Character>,
+    meta: Res<MetaProgress>,
+    mut next_seed: ResMut<NextRunSeed>,
+    mut requested_replay: ResMut<RequestedReplay>,
+    mut requested_resume: ResMut<RequestedResume>,
+) {
     let hit_sound_handle = asset_server.load("sounds/hit.wav");
     let hit_self_sound_handle = asset_server.load("sounds/hit_self.wav");
     let shoot_sound_handle = asset_server.load("sounds/shoot.wav");
@@ -130,6 +342,8 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>, difficulty: Res
         OnGameScreen,
     ));
 
+    commands.entity(*camera).insert(view_projection());
+
     let bgm_handle: Handle<AudioSource> = asset_server.load("sounds/vamita-0.mp3");
     commands.spawn((
         DespawnOnExit(MainState::Game),
@@ -138,33 +352,38 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>, difficulty: Res
         PlaybackSettings::LOOP,
     ));
 
-    reset_game(&mut commands, None, *difficulty);
+    if let Some(replay) = requested_replay.0.take() {
+        start_replay(&mut commands, None, replay);
+    } else if let Some(save) = requested_resume.0.take() {
+        commands.run_system_cached_with(save::resume_run, save);
+    } else {
+        reset_game(
+            &mut commands,
+            None,
+            *difficulty,
+            *selected_map,
+            *character,
+            meta.bonuses,
+            next_seed.take_or_random(),
+        );
+    }
 }
 
 pub fn reset_game(
     commands: &mut Commands,
     level_entity_query: Option<&Query<Entity, With<LevelEntity>>>,
     difficulty: Difficulty,
+    map: SelectedMap,
+    character: Character,
+    bonuses: StartingBonuses,
+    seed: u64,
 ) {
-    commands.insert_resource(EnemySpawnTimer(Timer::from_seconds(
-        difficulty.enemy_spawn_interval(ENEMY_SPAWN_INTERVAL),
-        TimerMode::Repeating,
-    )));
-    let upgrades = PlayerUpgrades::default();
-    let fire_rate = upgrades.fire_rate_duration();
-    let bomb_interval = upgrades.bomb_interval_duration();
-    commands.insert_resource(upgrades);
-    commands.insert_resource(ShootTimer(Timer::from_seconds(
-        fire_rate,
-        TimerMode::Repeating,
-    )));
-    commands.insert_resource(BombTimer(Timer::from_seconds(
-        bomb_interval,
-        TimerMode::Repeating,
-    )));
-    commands.insert_resource(PowerUpProgress::default());
-    commands.insert_resource(PlayerStats { experience: 0 });
-    commands.insert_resource(Score::default());
+    info!(
+        "Starting run on map `{}` as {} with seed {seed}",
+        map.0,
+        character.label()
+    );
+    insert_run_resources(commands, difficulty, map, character, bonuses, seed);
 
     if let Some(level_entity_query) = level_entity_query {
         for entity in level_entity_query.iter() {
@@ -172,7 +391,58 @@ pub fn reset_game(
         }
     }
 
-    spawn_player(commands, &difficulty);
+    spawn_player(commands, &difficulty, &bonuses, character);
+    for &kind in character.starting_weapons() {
+        spawn_weapon(commands, kind);
+    }
 
     commands.set_state(GameState::Playing);
 }
+
+/// Every resource a run keeps its state in, as at the start of a run.
+fn insert_run_resources(
+    commands: &mut Commands,
+    difficulty: Difficulty,
+    map: SelectedMap,
+    character: Character,
+    bonuses: StartingBonuses,
+    seed: u64,
+) {
+    commands.insert_resource(RunRng::new(seed));
+    commands.insert_resource(map);
+    commands.insert_resource(character);
+    commands.insert_resource(ActiveReplay::record(
+        seed, difficulty, map, character, bonuses,
+    ));
+    commands.insert_resource(MoveIntent::default());
+    commands.insert_resource(AimIntent::default());
+    commands.insert_resource(SpatialIndex::default());
+    commands.insert_resource(EnemyFreeze::default());
+    commands.insert_resource(CameraView::default());
+    commands.insert_resource(WaveDirector::new(difficulty));
+    commands.insert_resource(BossSpawner::default());
+    commands.insert_resource(PlayerUpgrades::new(&bonuses, character));
+    commands.insert_resource(PowerUpProgress::new(&bonuses, character));
+    commands.insert_resource(PowerUpOffer::default());
+    commands.insert_resource(PlayerStats::default());
+    commands.insert_resource(Score::default());
+}
+
+/// Starts a run that plays `replay` back instead of taking input.
+pub fn start_replay(
+    commands: &mut Commands,
+    level_entity_query: Option<&Query<Entity, With<LevelEntity>>>,
+    replay: Replay,
+) {
+    commands.insert_resource(replay.difficulty);
+    reset_game(
+        commands,
+        level_entity_query,
+        replay.difficulty,
+        replay.map(),
+        replay.character(),
+        replay.bonuses(),
+        replay.seed,
+    );
+    commands.insert_resource(ActiveReplay::play(replay));
+}
@@ -1,57 +1,81 @@
 use bevy::prelude::*;
 
-use crate::game::player::Player;
-
+use super::arena::ArenaMap;
 use super::components::{Lifetime, Projectile, Velocity};
 use super::constants::{ENEMY_REPULSION_RADIUS_FACTOR, ENEMY_REPULSION_STRENGTH};
 use super::enemy::{Enemy, EnemyAttributes};
+use super::obstacles::PLAYER_RADIUS;
+use super::pickup::EnemyFreeze;
+use super::player::Player;
+use super::spatial::SpatialIndex;
 
-pub fn update_velocity(time: Res<Time>, mut query: Query<(&mut Transform, &Velocity)>) {
+/// Moves everything with a velocity. The player and enemies can't leave the map or move into
+/// obstacles, and enemies stay put while frozen.
+pub fn update_velocity(
+    time: Res<Time>,
+    map: Res<ArenaMap>,
+    freeze: Res<EnemyFreeze>,
+    mut query: Query<(
+        &mut Transform,
+        &Velocity,
+        Option<&EnemyAttributes>,
+        Has<Player>,
+    )>,
+) {
     let delta = time.delta_secs();
-    for (mut transform, velocity) in &mut query {
+    for (mut transform, velocity, attributes, is_player) in &mut query {
+        if attributes.is_some() && freeze.is_active() {
+            continue;
+        }
         transform.translation += velocity.extend(0.0) * delta;
+        let radius = match attributes {
+            Some(attributes) => attributes.radius(),
+            None if is_player => PLAYER_RADIUS,
+            None => continue,
+        };
+        let position = map.bounds.clamp(transform.translation.xy(), radius);
+        let position = map.push_out(position, radius);
+        transform.translation = position.extend(transform.translation.z);
     }
 }
 
-pub fn enemy_seek_player(
-    mut enemies: Query<(&Transform, &mut Velocity, &EnemyAttributes), With<Enemy>>,
-    player: Query<&Transform, (With<Player>, Without<Enemy>)>,
+/// Pushes overlapping enemies apart on top of the velocity set by their behavior.
+pub fn enemy_repulsion(
+    index: Res<SpatialIndex>,
+    mut enemies: Query<(Entity, &Transform, &mut Velocity, &EnemyAttributes), With<Enemy>>,
 ) {
-    let Ok(player_transform) = player.single() else {
-        return;
-    };
+    let grid = &index.enemies;
+    let mut impulses: Vec<(Entity, Vec2)> = Vec::new();
 
-    let player_pos = player_transform.translation.xy();
-    for (transform, mut velocity, attributes) in &mut enemies {
-        let dir = (player_pos - transform.translation.xy()).normalize_or_zero();
-        velocity.0 = dir * attributes.speed;
-    }
+    // Each enemy sums the pushes from its neighbours in grid order, which unlike query order is
+    // the same when a run is replayed.
+    for (entity_a, transform_a, _, attributes_a) in &enemies {
+        let position_a = transform_a.translation.xy();
+        let radius_a = attributes_a.radius();
+        let reach = (radius_a + grid.max_radius()) * ENEMY_REPULSION_RADIUS_FACTOR;
+        let mut impulse_a = Vec2::ZERO;
 
-    // Enemy repulsion
-    {
-        let mut combinations = enemies.iter_combinations_mut();
-        while let Some(
-            [
-                (transform_a, mut velocity_a, attributes_a),
-                (transform_b, mut velocity_b, attributes_b),
-            ],
-        ) = combinations.fetch_next()
-        {
-            let offset = transform_b.translation.xy() - transform_a.translation.xy();
-            let distance_sq = offset.length_squared();
-            let radius_a = enemy_radius(attributes_a);
-            let radius_b = enemy_radius(attributes_b);
-            let min_distance = (radius_a + radius_b) * ENEMY_REPULSION_RADIUS_FACTOR;
+        for b in grid.candidates(position_a, reach) {
+            if b.entity == entity_a {
+                continue;
+            }
 
-            let distance = distance_sq.sqrt();
+            let offset = b.position - position_a;
+            let min_distance = (radius_a + b.radius) * ENEMY_REPULSION_RADIUS_FACTOR;
+            let distance = offset.length();
             if distance >= min_distance {
                 continue;
             }
+            let Ok((_, _, _, attributes_b)) = enemies.get(b.entity) else {
+                continue;
+            };
 
             let normal = if distance > f32::EPSILON {
                 offset / distance
-            } else {
+            } else if b.entity > entity_a {
                 Vec2::X
+            } else {
+                -Vec2::X
             };
             let effective_distance = distance.max(0.001);
             let overlap_ratio = (min_distance - effective_distance) / min_distance;
@@ -62,15 +86,18 @@ pub fn enemy_seek_player(
                 continue;
             }
 
-            let impulse = normal * repulsion_speed;
-            velocity_a.0 -= impulse;
-            velocity_b.0 += impulse;
+            impulse_a -= normal * repulsion_speed;
+        }
+        if impulse_a != Vec2::ZERO {
+            impulses.push((entity_a, impulse_a));
         }
     }
-}
 
-fn enemy_radius(attributes: &EnemyAttributes) -> f32 {
-    attributes.size.x.max(attributes.size.y) * 0.5
+    for (entity, impulse) in impulses {
+        if let Ok((_, _, mut velocity, _)) = enemies.get_mut(entity) {
+            velocity.0 += impulse;
+        }
+    }
 }
 
 pub fn update_projectiles(
@@ -96,13 +123,3 @@ pub fn decay_lifetimes(
         }
     }
 }
-
-#[allow(dead_code)]
-pub fn center_camera_on_player(
-    player_transform: Single<&Transform, With<Player>>,
-    mut camera_transform: Single<&mut Transform, (With<Camera2d>, Without<Player>)>,
-) {
-    let player_translation = player_transform.translation;
-    camera_transform.translation.x = player_translation.x;
-    camera_transform.translation.y = player_translation.y;
-}
@@ -0,0 +1,185 @@
+//! Static obstacles placed by the map. They block the player, enemies and projectiles.
+//!
+//! Every query walks the obstacles in map order, so overlapping obstacles resolve the same
+//! way when a run is replayed.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::arena::ArenaMap;
+use super::components::{EnemyProjectile, Velocity};
+use super::constants::PLAYER_SIZE;
+use super::enemy::{Enemy, EnemyAttributes};
+use crate::MainState;
+
+/// How close an enemy gets to an obstacle before it starts steering around it.
+const STEER_LOOKAHEAD: f32 = 24.0;
+/// Radius used when checking projectiles against obstacles.
+pub(super) const PROJECTILE_RADIUS: f32 = 3.0;
+
+pub const PLAYER_RADIUS: f32 = PLAYER_SIZE.x * 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum ObstacleKind {
+    Wall,
+    Pillar,
+    Rock,
+}
+
+impl ObstacleKind {
+    fn color(self) -> Color {
+        match self {
+            ObstacleKind::Wall => Color::srgb(0.35, 0.35, 0.42),
+            ObstacleKind::Pillar => Color::srgb(0.55, 0.52, 0.45),
+            ObstacleKind::Rock => Color::srgb(0.4, 0.33, 0.27),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Collider {
+    Circle { radius: f32 },
+    Box { half_size: Vec2 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Obstacle {
+    pub kind: ObstacleKind,
+    pub position: Vec2,
+    pub collider: Collider,
+}
+
+impl Obstacle {
+    /// Signed distance from `point` to the obstacle's edge, negative inside, and the
+    /// direction that leads out of it.
+    pub fn separation(&self, point: Vec2) -> (f32, Vec2) {
+        let offset = point - self.position;
+        match self.collider {
+            Collider::Circle { radius } => (offset.length() - radius, offset.normalize_or(Vec2::Y)),
+            Collider::Box { half_size } => {
+                let outside = offset.abs() - half_size;
+                if outside.x > 0.0 || outside.y > 0.0 {
+                    let to_point = offset - offset.clamp(-half_size, half_size);
+                    (to_point.length(), to_point.normalize_or(Vec2::Y))
+                } else if outside.x > outside.y {
+                    // Inside: leave through the nearest side.
+                    (outside.x, Vec2::new(offset.x.signum(), 0.0))
+                } else {
+                    (outside.y, Vec2::new(0.0, offset.y.signum()))
+                }
+            }
+        }
+    }
+
+    /// Distance along `direction` (a unit vector) from `origin` to where a ray enters the
+    /// obstacle, or zero if `origin` is already inside.
+    pub(super) fn ray_distance(&self, origin: Vec2, direction: Vec2) -> Option<f32> {
+        let offset = origin - self.position;
+        match self.collider {
+            Collider::Circle { radius } => {
+                let along = offset.dot(direction);
+                let outside = offset.length_squared() - radius * radius;
+                if outside > 0.0 && along > 0.0 {
+                    return None;
+                }
+                let discriminant = along * along - outside;
+                (discriminant >= 0.0).then(|| (-along - discriminant.sqrt()).max(0.0))
+            }
+            Collider::Box { half_size } => {
+                let inverse = direction.recip();
+                let near = (-half_size - offset) * inverse;
+                let far = (half_size - offset) * inverse;
+                let enter = near.min(far).max_element();
+                let exit = near.max(far).min_element();
+                (exit >= enter.max(0.0)).then_some(enter.max(0.0))
+            }
+        }
+    }
+}
+
+/// Turns `velocity` along the edge of any of `obstacles` it is about to run into. See
+/// [`ArenaMap::steer_around`].
+pub(super) fn steer_around(
+    obstacles: &[Obstacle],
+    position: Vec2,
+    radius: f32,
+    velocity: Vec2,
+) -> Vec2 {
+    let speed = velocity.length();
+    let mut steered = velocity;
+    for obstacle in obstacles {
+        let (distance, normal) = obstacle.separation(position);
+        let into = steered.dot(normal);
+        if distance > radius + STEER_LOOKAHEAD || into >= 0.0 {
+            continue;
+        }
+        // Head-on there is nothing left to slide along, so pick a side.
+        let slide = steered - normal * into;
+        steered = slide.normalize_or(normal.perp()) * speed;
+    }
+    steered
+}
+
+/// Steers enemies around obstacles, after their behavior and repulsion set their velocity.
+pub fn steer_enemies_around_obstacles(
+    map: Res<ArenaMap>,
+    mut enemies: Query<(&Transform, &mut Velocity, &EnemyAttributes), With<Enemy>>,
+) {
+    if map.obstacles.is_empty() {
+        return;
+    }
+    for (transform, mut velocity, attributes) in &mut enemies {
+        velocity.0 = map.steer_around(transform.translation.xy(), attributes.radius(), velocity.0);
+    }
+}
+
+/// Enemy shots stop at obstacles. The player's are stopped in `handle_collisions`.
+pub fn block_enemy_projectiles(
+    mut commands: Commands,
+    map: Res<ArenaMap>,
+    projectiles: Query<(Entity, &Transform), With<EnemyProjectile>>,
+) {
+    for (entity, transform) in &projectiles {
+        if map.blocks_projectile(transform.translation.xy()) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Sprites for the obstacles, redrawn when the map changes.
+#[derive(Component)]
+pub struct ObstacleSprite;
+
+pub fn draw_obstacles(
+    mut commands: Commands,
+    map: Res<ArenaMap>,
+    sprites: Query<Entity, With<ObstacleSprite>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !map.is_changed() && !sprites.is_empty() {
+        return;
+    }
+    for entity in &sprites {
+        commands.entity(entity).despawn();
+    }
+    for obstacle in &map.obstacles {
+        let color = obstacle.kind.color();
+        let transform = Transform::from_translation(obstacle.position.extend(-0.5));
+        match obstacle.collider {
+            Collider::Circle { radius } => commands.spawn((
+                DespawnOnExit(MainState::Game),
+                ObstacleSprite,
+                Mesh2d(meshes.add(Circle { radius })),
+                MeshMaterial2d(materials.add(color)),
+                transform,
+            )),
+            Collider::Box { half_size } => commands.spawn((
+                DespawnOnExit(MainState::Game),
+                ObstacleSprite,
+                Sprite::from_color(color, half_size * 2.0),
+                transform,
+            )),
+        };
+    }
+}
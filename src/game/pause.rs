@@ -1,9 +1,17 @@
-use bevy::input::ButtonInput;
+use bevy::input::gamepad::GamepadConnectionEvent;
 use bevy::prelude::*;
 
+use crate::controls::{ActionInput, Controls, InputAction};
+use crate::game::arena::SelectedMap;
+use crate::game::character::Character;
 use crate::game::components::LevelEntity;
+use crate::game::replay::{ActiveReplay, ReplayMode};
+use crate::game::rng::{RunRng, random_seed};
+use crate::game::save::save_run;
 use crate::game::ui::PauseOverlay;
 use crate::game::{GameState, reset_game};
+use crate::gamepad::CancelButton;
+use crate::shop::MetaProgress;
 use crate::{Difficulty, MainState};
 
 const OVERLAY_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.75);
@@ -21,34 +29,54 @@ pub struct PauseButton {
 enum PauseAction {
     Resume,
     Restart,
+    SaveAndQuit,
     QuitToMenu,
 }
 
 pub fn pause_input(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    kb: Res<ButtonInput<KeyCode>>,
+    input: ActionInput,
+    mut connections: MessageReader<GamepadConnectionEvent>,
     game_state: Res<State<GameState>>,
     mut set_game_state: ResMut<NextState<GameState>>,
     overlay: Query<Entity, With<PauseOverlay>>,
+    run_rng: Res<RunRng>,
+    replay: Res<ActiveReplay>,
 ) {
-    if kb.just_pressed(KeyCode::Escape) {
-        let next_state = if *game_state == GameState::Playing {
-            GameState::Paused
-        } else {
-            GameState::Playing
-        };
-        set_game_state.set(next_state);
+    let toggle = input.just_pressed(InputAction::Pause);
+    // A controller plugged in or lost mid-run stops the game until the player is ready.
+    let controller_changed = connections.read().count() > 0;
 
-        if next_state == GameState::Paused {
-            spawn_pause(commands, asset_server);
-        } else {
-            close_pause_overlay(&mut commands, &overlay);
-        }
+    let next_state = match **game_state {
+        GameState::Playing if toggle || controller_changed => GameState::Paused,
+        GameState::Paused if toggle => GameState::Playing,
+        _ => return,
+    };
+    set_game_state.set(next_state);
+
+    if next_state == GameState::Paused {
+        // A replay being watched isn't a run that could be continued.
+        let can_save = replay.mode() == ReplayMode::Recording;
+        spawn_pause(
+            commands,
+            asset_server,
+            input.controls(),
+            run_rng.seed(),
+            can_save,
+        );
+    } else {
+        close_pause_overlay(&mut commands, &overlay);
     }
 }
 
-pub fn spawn_pause(mut commands: Commands, asset_server: Res<AssetServer>) {
+pub fn spawn_pause(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    controls: &Controls,
+    seed: u64,
+    can_save: bool,
+) {
     let font_handle = asset_server.load("fonts/FiraSans-Bold.ttf");
 
     commands
@@ -86,6 +114,15 @@ pub fn spawn_pause(mut commands: Commands, asset_server: Res<AssetServer>) {
                     },
                     TextColor(Color::WHITE),
                 ));
+                panel.spawn((
+                    Text::new(format!("Seed {seed}")),
+                    TextFont {
+                        font: font_handle.clone(),
+                        font_size: 20.0,
+                        ..default()
+                    },
+                    TextColor(Color::srgba(0.8, 0.8, 0.85, 1.0)),
+                ));
                 panel
                     .spawn((Node {
                         flex_direction: FlexDirection::Column,
@@ -93,22 +130,37 @@ pub fn spawn_pause(mut commands: Commands, asset_server: Res<AssetServer>) {
                         ..default()
                     },))
                     .with_children(|list| {
-                        list.spawn(pause_button_bundle(
-                            font_handle.clone(),
-                            "Resume",
-                            "Enter / Esc",
-                            PauseAction::Resume,
+                        list.spawn((
+                            pause_button_bundle(
+                                font_handle.clone(),
+                                "Resume",
+                                &format!(
+                                    "{} / {}",
+                                    controls.key_hint(InputAction::Confirm),
+                                    controls.key_hint(InputAction::Pause)
+                                ),
+                                PauseAction::Resume,
+                            ),
+                            CancelButton,
                         ));
                         list.spawn(pause_button_bundle(
                             font_handle.clone(),
                             "New Game",
-                            "N",
+                            &controls.key_hint(InputAction::NewGame),
                             PauseAction::Restart,
                         ));
+                        if can_save {
+                            list.spawn(pause_button_bundle(
+                                font_handle.clone(),
+                                "Save & Quit",
+                                "",
+                                PauseAction::SaveAndQuit,
+                            ));
+                        }
                         list.spawn(pause_button_bundle(
                             font_handle.clone(),
                             "Back to Menu",
-                            "Q",
+                            &controls.key_hint(InputAction::QuitToMenu),
                             PauseAction::QuitToMenu,
                         ));
                     });
@@ -132,7 +184,7 @@ pub fn pause_button_visuals(
 }
 
 pub fn pause_menu_actions(
-    kb: Res<ButtonInput<KeyCode>>,
+    input: ActionInput,
     mut button_interactions: Query<
         (&Interaction, &PauseButton),
         (Changed<Interaction>, With<Button>),
@@ -141,12 +193,17 @@ pub fn pause_menu_actions(
     mut commands: Commands,
     level_entity_query: Query<Entity, With<LevelEntity>>,
     difficulty: Res<Difficulty>,
+    selected_map: Res<SelectedMap>,
+    character: Res<Character>,
+    meta: Res<MetaProgress>,
     mut main_state: ResMut<NextState<MainState>>,
     mut game_state: ResMut<NextState<GameState>>,
 ) {
-    let mut resume_requested = kb.just_pressed(KeyCode::Enter);
-    let mut restart_requested = kb.just_pressed(KeyCode::KeyN);
-    let mut menu_requested = kb.just_pressed(KeyCode::KeyQ);
+    // The gamepad's confirm presses whichever button it is on instead.
+    let mut resume_requested = input.key_just_pressed(InputAction::Confirm);
+    let mut restart_requested = input.just_pressed(InputAction::NewGame);
+    let mut save_requested = false;
+    let mut menu_requested = input.just_pressed(InputAction::QuitToMenu);
 
     for (interaction, button) in &mut button_interactions {
         if *interaction != Interaction::Pressed {
@@ -155,6 +212,7 @@ pub fn pause_menu_actions(
         match button.action {
             PauseAction::Resume => resume_requested = true,
             PauseAction::Restart => restart_requested = true,
+            PauseAction::SaveAndQuit => save_requested = true,
             PauseAction::QuitToMenu => menu_requested = true,
         }
     }
@@ -166,13 +224,26 @@ pub fn pause_menu_actions(
     }
 
     if restart_requested {
-        reset_game(&mut commands, Some(&level_entity_query), *difficulty);
+        reset_game(
+            &mut commands,
+            Some(&level_entity_query),
+            *difficulty,
+            *selected_map,
+            *character,
+            meta.bonuses,
+            random_seed(),
+        );
         game_state.set(GameState::Playing);
         close_pause_overlay(&mut commands, &overlay);
         return;
     }
 
-    if menu_requested {
+    if save_requested {
+        // Runs before the state change despawns the run.
+        commands.run_system_cached(save_run);
+    }
+
+    if save_requested || menu_requested {
         main_state.set(MainState::Menu);
         close_pause_overlay(&mut commands, &overlay);
     }
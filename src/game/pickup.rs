@@ -0,0 +1,265 @@
+//! Items enemies drop on death, picked up by walking over them.
+
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::arena::CameraView;
+use super::boss::Boss;
+use super::components::{ExperienceOrb, Health, LevelEntity};
+use super::constants::PLAYER_SIZE;
+use super::enemy::{Enemy, EnemyAttributes, EnemyCatalog};
+use super::events::{DamageEnemy, EnemyKilled};
+use super::player::Player;
+use super::resources::ExperienceOrbSound;
+use super::rng::RunRng;
+use crate::MainState;
+use crate::audio::{SEVolume, spawn_se};
+
+const PICKUP_SIZE: f32 = 14.0;
+/// Seconds a pickup stays on the floor.
+const PICKUP_LIFETIME: f32 = 12.0;
+/// Pickups blink for the last this many seconds before vanishing.
+const PICKUP_BLINK_TIME: f32 = 3.0;
+const PICKUP_BLINK_INTERVAL: f32 = 0.2;
+const HEART_HEAL: i32 = 2;
+const FREEZE_DURATION: f32 = 4.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum PickupKind {
+    /// Restores `HEART_HEAL` health.
+    Heart,
+    /// Pulls every experience orb on the floor to the player.
+    Magnet,
+    /// Kills every enemy on screen except bosses.
+    Nuke,
+    /// Stops every enemy in place for `FREEZE_DURATION` seconds.
+    Freeze,
+}
+
+impl PickupKind {
+    fn color(self) -> Color {
+        match self {
+            PickupKind::Heart => Color::srgb(1.0, 0.35, 0.45),
+            PickupKind::Magnet => Color::srgb(0.35, 0.6, 1.0),
+            PickupKind::Nuke => Color::srgb(1.0, 0.6, 0.1),
+            PickupKind::Freeze => Color::srgb(0.7, 0.95, 1.0),
+        }
+    }
+}
+
+/// What an enemy prototype may drop on death: with probability `chance`, one item picked by
+/// weight from `items`.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DropTable {
+    pub chance: f32,
+    pub items: Vec<(PickupKind, f32)>,
+}
+
+impl DropTable {
+    /// Returns the name of the first parameter that is out of range, if any.
+    pub fn invalid_parameter(&self) -> Option<&'static str> {
+        if !(0.0..=1.0).contains(&self.chance) {
+            return Some("chance");
+        }
+        let weights_valid = self
+            .items
+            .iter()
+            .all(|(_, weight)| weight.is_finite() && *weight >= 0.0);
+        let total_weight: f32 = self.items.iter().map(|(_, weight)| weight).sum();
+        if !weights_valid || (self.chance > 0.0 && total_weight <= 0.0) {
+            return Some("items");
+        }
+        None
+    }
+
+    /// Rolls the table. Always draws the chance roll, so the number of draws doesn't depend on
+    /// the table's contents.
+    pub fn roll(&self, rng: &mut impl Rng) -> Option<PickupKind> {
+        if rng.random_range(0.0f32..1.0) >= self.chance {
+            return None;
+        }
+        let total_weight: f32 = self.items.iter().map(|(_, weight)| weight).sum();
+        let mut roll = rng.random_range(0.0..total_weight);
+        for &(kind, weight) in &self.items {
+            if roll < weight {
+                return Some(kind);
+            }
+            roll -= weight;
+        }
+        self.items.last().map(|(kind, _)| *kind)
+    }
+}
+
+#[derive(Component)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    pub timer: Timer,
+    pub blink_timer: Timer,
+    pub visible: bool,
+}
+
+/// Counts down while the freeze clock holds enemies in place.
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+pub struct EnemyFreeze {
+    remaining: f32,
+}
+
+impl EnemyFreeze {
+    pub fn is_active(&self) -> bool {
+        self.remaining > 0.0
+    }
+}
+
+/// Rolls the drop table of every defeated enemy's prototype. Enemies that aren't in the
+/// catalog, like bosses and splitter fragments, drop nothing.
+pub fn drop_pickups(
+    mut commands: Commands,
+    mut enemy_killed_messages: MessageReader<EnemyKilled>,
+    catalog: Res<EnemyCatalog>,
+    mut run_rng: ResMut<RunRng>,
+) {
+    let rng = run_rng.gameplay();
+    for killed in enemy_killed_messages.read() {
+        let Some(prototype) = catalog.get(&killed.name) else {
+            continue;
+        };
+        if let Some(kind) = prototype.drops.roll(rng) {
+            spawn_pickup(&mut commands, kind, killed.position);
+        }
+    }
+}
+
+pub fn spawn_pickup(commands: &mut Commands, kind: PickupKind, position: Vec2) {
+    commands.spawn((
+        DespawnOnExit(MainState::Game),
+        LevelEntity,
+        Sprite::from_color(kind.color(), Vec2::splat(PICKUP_SIZE)),
+        Transform::from_translation(position.extend(0.7))
+            .with_rotation(Quat::from_rotation_z(TAU / 8.0)),
+        Pickup {
+            kind,
+            timer: Timer::from_seconds(PICKUP_LIFETIME, TimerMode::Once),
+            blink_timer: Timer::from_seconds(PICKUP_BLINK_INTERVAL, TimerMode::Repeating),
+            visible: true,
+        },
+    ));
+}
+
+/// Despawns pickups that have lain around too long, blinking them first.
+pub fn update_pickups(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pickups: Query<(Entity, &mut Sprite, &mut Pickup)>,
+) {
+    for (entity, mut sprite, mut pickup) in &mut pickups {
+        if pickup.timer.tick(time.delta()).is_finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        if pickup.timer.remaining_secs() > PICKUP_BLINK_TIME {
+            continue;
+        }
+        if pickup.blink_timer.tick(time.delta()).just_finished() {
+            pickup.visible = !pickup.visible;
+            sprite
+                .color
+                .set_alpha(if pickup.visible { 1.0 } else { 0.3 });
+        }
+    }
+}
+
+pub fn collect_pickups(
+    mut commands: Commands,
+    pickups: Query<(Entity, &Transform, &Pickup)>,
+    mut player: Single<(&Transform, &mut Health), (With<Player>, Without<Enemy>)>,
+    mut orbs: Query<&mut ExperienceOrb>,
+    enemies: Query<(Entity, &Transform, &Health, &EnemyAttributes), (With<Enemy>, Without<Boss>)>,
+    view: Res<CameraView>,
+    mut freeze: ResMut<EnemyFreeze>,
+    mut damage_messages: MessageWriter<DamageEnemy>,
+    exp_sound: Res<ExperienceOrbSound>,
+    se_volume: Res<SEVolume>,
+) {
+    let (player_transform, player_health) = &mut *player;
+    let player_pos = player_transform.translation.xy();
+    let pickup_radius = (PLAYER_SIZE.x + PICKUP_SIZE) * 0.5;
+
+    for (entity, transform, pickup) in &pickups {
+        if player_pos.distance(transform.translation.xy()) > pickup_radius {
+            continue;
+        }
+        commands.entity(entity).despawn();
+        spawn_se(&mut commands, &se_volume, &exp_sound.0);
+        match pickup.kind {
+            PickupKind::Heart => {
+                player_health.current = (player_health.current + HEART_HEAL).min(player_health.max);
+            }
+            PickupKind::Magnet => {
+                for mut orb in &mut orbs {
+                    orb.magnetized = true;
+                }
+            }
+            PickupKind::Nuke => {
+                // Killed through `handle_collisions`, which awards score and drops orbs as
+                // for any other kill.
+                for (enemy, transform, health, attributes) in &enemies {
+                    if view.shows(transform.translation.xy(), attributes.radius()) {
+                        damage_messages.write(DamageEnemy {
+                            target: enemy,
+                            amount: health.current.max(1),
+                            on_hit: false,
+                        });
+                    }
+                }
+            }
+            PickupKind::Freeze => freeze.remaining = FREEZE_DURATION,
+        }
+    }
+}
+
+pub fn tick_enemy_freeze(time: Res<Time>, mut freeze: ResMut<EnemyFreeze>) {
+    if freeze.is_active() {
+        freeze.remaining = (freeze.remaining - time.delta_secs()).max(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+
+    #[test]
+    fn roll_respects_chance_and_skips_zero_weights() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let never = DropTable {
+            chance: 0.0,
+            items: vec![(PickupKind::Heart, 1.0)],
+        };
+        assert!((0..100).all(|_| never.roll(&mut rng).is_none()));
+
+        let always = DropTable {
+            chance: 1.0,
+            items: vec![(PickupKind::Heart, 0.0), (PickupKind::Magnet, 1.0)],
+        };
+        assert!((0..100).all(|_| always.roll(&mut rng) == Some(PickupKind::Magnet)));
+    }
+
+    #[test]
+    fn invalid_parameter_flags_bad_tables() {
+        let table = |chance, weight| DropTable {
+            chance,
+            items: vec![(PickupKind::Nuke, weight)],
+        };
+        assert_eq!(table(0.5, 1.0).invalid_parameter(), None);
+        assert_eq!(table(1.5, 1.0).invalid_parameter(), Some("chance"));
+        assert_eq!(table(0.5, -1.0).invalid_parameter(), Some("items"));
+        assert_eq!(table(0.5, 0.0).invalid_parameter(), Some("items"));
+        assert_eq!(DropTable::default().invalid_parameter(), None);
+    }
+}
@@ -1,202 +1,164 @@
-use bevy::input::ButtonInput;
 use bevy::prelude::*;
-
-use super::components::{Bomb, BombExplosion, Lifetime, Velocity};
-use super::constants::{BOMB_EXPLOSION_DURATION, BOMB_FUSE};
-use super::enemy::Enemy;
-use super::powerup::PlayerUpgrades;
-use super::resources::{BombSound, ShootSound};
-use crate::audio::{SEVolume, spawn_se};
-use crate::game::components::{Health, LevelEntity, Projectile};
-use crate::game::constants::{ARENA_HALF_SIZE, PLAYER_MAX_HEALTH, PLAYER_SIZE};
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+
+use super::arena::ArenaMap;
+use super::character::{Character, Passive, Regeneration};
+use super::components::Velocity;
+use super::events::{BossDefeated, EnemyKilled, PlayerHit};
+use super::obstacles::PLAYER_RADIUS;
+use super::powerup::{PlayerUpgrades, StartingBonuses};
+use super::replay::MoveInput;
+use crate::controls::{ActionInput, AimMode, InputAction};
+use crate::game::components::{Health, LevelEntity};
+use crate::game::constants::PLAYER_SIZE;
 use crate::game::ui::{HealthBarFill, HealthBarRoot};
+use crate::gamepad::{aim_stick_direction, stick_direction};
 use crate::{Difficulty, MainState};
 
-use rand::Rng;
-use std::f32::consts::TAU;
-
 #[derive(Component)]
 pub struct Player;
 
-#[derive(Resource)]
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
 pub struct PlayerStats {
     pub experience: u32,
+    pub enemies_killed: u32,
+    pub bosses_defeated: u32,
+    pub time_survived: f32,
+    pub damage_taken: u32,
 }
 
-#[derive(Resource)]
-pub struct ShootTimer(pub Timer);
-
-#[derive(Resource)]
-pub struct BombTimer(pub Timer);
-
-pub fn player_input(
-    mut query: Query<&mut Velocity, With<Player>>,
-    kb: Res<ButtonInput<KeyCode>>,
-    upgrades: Res<PlayerUpgrades>,
-) {
-    if let Ok(mut velocity) = query.single_mut() {
-        let mut dir = Vec2::ZERO;
-        if kb.any_pressed([KeyCode::KeyW, KeyCode::ArrowUp]) {
-            dir.y += 1.0;
-        }
-        if kb.any_pressed([KeyCode::KeyS, KeyCode::ArrowDown]) {
-            dir.y -= 1.0;
-        }
-        if kb.any_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]) {
-            dir.x -= 1.0;
-        }
-        if kb.any_pressed([KeyCode::KeyD, KeyCode::ArrowRight]) {
-            dir.x += 1.0;
-        }
-        let dir = dir.normalize_or_zero();
-        velocity.0 = dir * upgrades.movement_speed();
+/// Direction the player is steering in, sampled every frame. Ticks pick it up through
+/// `replay::feed_move_input`, so it only reaches the simulation as a recorded `MoveInput`.
+#[derive(Resource, Default)]
+pub struct MoveIntent(pub Vec2);
+
+/// Direction the player is aiming in with manual aim, sampled every frame and fed to ticks
+/// by `replay::feed_aim_input`. `None` leaves the weapons to aim themselves.
+#[derive(Resource, Default)]
+pub struct AimIntent(pub Option<Vec2>);
+
+/// Bound keys and buttons first; otherwise the first left stick being steered with, which can
+/// ask for less than full speed.
+pub fn player_input(mut intent: ResMut<MoveIntent>, input: ActionInput) {
+    let mut dir = Vec2::ZERO;
+    if input.pressed(InputAction::MoveUp) {
+        dir.y += 1.0;
+    }
+    if input.pressed(InputAction::MoveDown) {
+        dir.y -= 1.0;
+    }
+    if input.pressed(InputAction::MoveLeft) {
+        dir.x -= 1.0;
+    }
+    if input.pressed(InputAction::MoveRight) {
+        dir.x += 1.0;
+    }
+    intent.0 = dir.normalize_or_zero();
+    if intent.0 == Vec2::ZERO {
+        intent.0 = input
+            .gamepads()
+            .map(stick_direction)
+            .find(|direction| *direction != Vec2::ZERO)
+            .unwrap_or_default();
     }
 }
 
-pub fn player_auto_fire(
-    mut commands: Commands,
-    time: Res<Time>,
-    mut timer: ResMut<ShootTimer>,
-    player_query: Query<&Transform, With<Player>>,
-    enemies: Query<&Transform, With<Enemy>>,
-    shoot_sound: Res<ShootSound>,
-    se_volume: Res<SEVolume>,
-    upgrades: Res<PlayerUpgrades>,
+/// Aims at the mouse cursor, or along the right stick while it is held and until the mouse
+/// moves again. Before either has been used the weapons keep aiming themselves.
+pub fn player_aim(
+    mut intent: ResMut<AimIntent>,
+    aim_mode: Res<AimMode>,
+    input: ActionInput,
+    mut cursor_moved: MessageReader<CursorMoved>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform), With<Camera2d>>,
+    player: Single<&Transform, With<Player>>,
+    mut aiming_with_stick: Local<bool>,
 ) {
-    if timer.0.tick(time.delta()).just_finished() {
-        let Ok(transform) = player_query.single() else {
-            return;
-        };
-
-        let origin = transform.translation.xy();
-        let dir = enemies
-            .iter()
-            .map(|enemy_transform| enemy_transform.translation.xy())
-            .min_by(|a, b| {
-                origin
-                    .distance_squared(*a)
-                    .partial_cmp(&origin.distance_squared(*b))
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            })
-            .map(|target| (target - origin).normalize_or_zero())
-            .filter(|dir| dir.length_squared() > 0.0)
-            .unwrap_or_else(|| {
-                let mut rng = rand::rng();
-                let angle = rng.random_range(0.0..TAU);
-                Vec2::new(angle.cos(), angle.sin())
-            });
-
-        commands.spawn((
-            DespawnOnExit(MainState::Game),
-            LevelEntity,
-            Sprite {
-                color: Color::srgba(1.0, 1.0, 0.0, 0.8),
-                custom_size: Some(Vec2::new(12.0, 6.0)),
-                ..default()
-            },
-            Transform::from_translation(transform.translation + Vec3::new(0.0, 0.0, 1.0))
-                .with_rotation(Quat::from_rotation_z(dir.y.atan2(dir.x))),
-            Projectile {
-                damage: upgrades.projectile_damage(),
-            },
-            Velocity(dir * upgrades.projectile_speed()),
-            Lifetime {
-                timer: Timer::from_seconds(1.2, TimerMode::Once),
-            },
-        ));
-        spawn_se(&mut commands, &*se_volume, &shoot_sound.0);
+    let mouse_moved = cursor_moved.read().count() > 0;
+    if *aim_mode == AimMode::Auto {
+        intent.0 = None;
+        return;
+    }
+
+    if let Some(direction) = input.gamepads().find_map(aim_stick_direction) {
+        *aiming_with_stick = true;
+        intent.0 = Some(direction);
+        return;
+    }
+    if mouse_moved {
+        *aiming_with_stick = false;
+    }
+    if *aiming_with_stick {
+        return;
+    }
+
+    let (camera, camera_transform) = *camera;
+    if let Some(cursor) = window.cursor_position()
+        && let Ok(target) = camera.viewport_to_world_2d(camera_transform, cursor)
+    {
+        // Keep the last direction while the cursor is right on the player.
+        intent.0 = (target - player.translation.xy())
+            .try_normalize()
+            .or(intent.0);
     }
 }
 
-pub fn player_place_bomb(
-    mut commands: Commands,
-    time: Res<Time>,
-    mut timer: ResMut<BombTimer>,
-    player_query: Query<&Transform, With<Player>>,
+pub fn apply_move_input(
+    mut query: Query<&mut Velocity, With<Player>>,
+    input: Res<MoveInput>,
     upgrades: Res<PlayerUpgrades>,
 ) {
-    if timer.0.tick(time.delta()).just_finished() {
-        let Ok(transform) = player_query.single() else {
-            return;
-        };
-
-        let position = transform.translation;
-        commands.spawn((
-            DespawnOnExit(MainState::Game),
-            LevelEntity,
-            Sprite::from_color(Color::srgba(1.0, 0.45, 0.1, 0.9), Vec2::splat(24.0)),
-            Transform::from_translation(Vec3::new(position.x, position.y, 0.4)),
-            Bomb {
-                timer: Timer::from_seconds(BOMB_FUSE, TimerMode::Once),
-                blink_timer: Timer::from_seconds(0.2, TimerMode::Repeating),
-                radius: upgrades.bomb_explosion_radius(),
-                visible: true,
-            },
-        ));
+    if let Ok(mut velocity) = query.single_mut() {
+        velocity.0 = input.direction() * upgrades.movement_speed();
     }
 }
 
-pub fn update_bombs(
-    mut commands: Commands,
+pub fn track_run_stats(
     time: Res<Time>,
-    mut bombs: Query<(Entity, &Transform, &mut Sprite, &mut Bomb)>,
-    bomb_sound: Res<BombSound>,
-    se_volume: Res<SEVolume>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut stats: ResMut<PlayerStats>,
+    mut enemy_killed_messages: MessageReader<EnemyKilled>,
+    mut player_hit_messages: MessageReader<PlayerHit>,
+    mut boss_defeated_messages: MessageReader<BossDefeated>,
 ) {
-    for (entity, transform, mut sprite, mut bomb) in &mut bombs {
-        if bomb.blink_timer.tick(time.delta()).just_finished() {
-            bomb.visible = !bomb.visible;
-            sprite.color.set_alpha(if bomb.visible { 0.9 } else { 0.4 });
-        }
-
-        if bomb.timer.tick(time.delta()).just_finished() {
-            let position = transform.translation;
-            commands.entity(entity).despawn();
-            commands.spawn((
-                DespawnOnExit(MainState::Game),
-                LevelEntity,
-                Mesh2d(meshes.add(Circle {
-                    radius: bomb.radius,
-                })),
-                MeshMaterial2d(materials.add(Color::srgba(1.0, 0.6, 0.2, 0.35))),
-                Transform::from_translation(Vec3::new(position.x, position.y, 0.5)),
-                BombExplosion {
-                    radius: bomb.radius,
-                },
-                Lifetime {
-                    timer: Timer::from_seconds(BOMB_EXPLOSION_DURATION, TimerMode::Once),
-                },
-            ));
-            spawn_se(&mut commands, &*se_volume, &bomb_sound.0);
-        }
+    stats.time_survived += time.delta_secs();
+    stats.enemies_killed = stats
+        .enemies_killed
+        .saturating_add(enemy_killed_messages.read().count() as u32);
+    stats.bosses_defeated = stats
+        .bosses_defeated
+        .saturating_add(boss_defeated_messages.read().count() as u32);
+    for hit in player_hit_messages.read() {
+        stats.damage_taken = stats.damage_taken.saturating_add(hit.damage.max(0) as u32);
     }
 }
 
-pub fn constrain_to_arena(mut query: Query<&mut Transform, With<Player>>) {
+/// Keeps the player inside the map's bounds and out of obstacles near them.
+pub fn constrain_to_arena(map: Res<ArenaMap>, mut query: Query<&mut Transform, With<Player>>) {
     for mut transform in &mut query {
-        transform.translation.x = transform
-            .translation
-            .x
-            .clamp(-ARENA_HALF_SIZE, ARENA_HALF_SIZE);
-        transform.translation.y = transform
-            .translation
-            .y
-            .clamp(-ARENA_HALF_SIZE, ARENA_HALF_SIZE);
+        let position = map.bounds.clamp(transform.translation.xy(), PLAYER_RADIUS);
+        let position = map.push_out(position, PLAYER_RADIUS);
+        transform.translation = position.extend(transform.translation.z);
     }
 }
 
-pub fn spawn_player(commands: &mut Commands, difficulty: &Difficulty) {
+pub fn spawn_player(
+    commands: &mut Commands,
+    difficulty: &Difficulty,
+    bonuses: &StartingBonuses,
+    character: Character,
+) -> Entity {
     let health_bar_size = Vec2::new(64.0, 8.0);
+    let max_health = difficulty.player_max_health(character.max_health());
 
-    commands.spawn((
+    let mut player = commands.spawn((
         DespawnOnExit(MainState::Game),
         LevelEntity,
-        Sprite::from_color(Color::srgb(0.2, 0.8, 1.0), PLAYER_SIZE),
+        Sprite::from_color(character.color(), PLAYER_SIZE),
         Transform::default(),
         Player,
-        Health::new(difficulty.player_max_health(PLAYER_MAX_HEALTH)),
+        Health::new(bonuses.player_max_health(max_health)),
         Velocity(Vec2::ZERO),
         children!((
             Transform::from_translation(Vec3::new(0.0, 28.0, 1.0)),
@@ -217,6 +179,10 @@ pub fn spawn_player(commands: &mut Commands, difficulty: &Difficulty) {
             ]
         )),
     ));
+    if character.passive() == Passive::Regeneration {
+        player.insert(Regeneration::default());
+    }
+    player.id()
 }
 
 pub fn update_health_bar(
@@ -1,11 +1,17 @@
 use bevy::prelude::*;
+use rand::Rng;
 use rand::seq::SliceRandom;
-use std::time::Duration;
-
-use super::constants::{
-    BOMB_EXPLOSION_RADIUS, BOMB_INTERVAL, FIRE_RATE, PLAYER_SPEED, PROJECTILE_SPEED,
+use serde::{Deserialize, Serialize};
+
+use super::character::{Character, Passive};
+use super::constants::PLAYER_SPEED;
+use super::events::PowerUpSelected;
+use super::replay::ActiveReplay;
+use super::rng::RunRng;
+use super::status::{ALL_INFUSIONS, Infusion, MAX_INFUSION_LEVEL};
+use super::weapon::{
+    ALL_WEAPON_KINDS, MAX_WEAPON_LEVEL, MAX_WEAPONS, Weapon, WeaponKind, spawn_weapon,
 };
-use super::player::{BombTimer, ShootTimer};
 use super::{GameState, OnGameScreen};
 
 const OVERLAY_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.75);
@@ -14,19 +20,40 @@ const BUTTON_COLOR: Color = Color::srgba(0.18, 0.18, 0.28, 0.95);
 const BUTTON_HOVER_COLOR: Color = Color::srgba(0.28, 0.28, 0.38, 0.95);
 const BUTTON_PRESSED_COLOR: Color = Color::srgba(0.35, 0.65, 0.35, 1.0);
 
-#[derive(Resource, Default, Debug)]
+#[derive(Resource, Clone, Default, Debug, Serialize, Deserialize)]
 pub struct PowerUpProgress {
     exp_pool: u32,
     pending: u32,
     collected: u32,
+    /// Extra experience gained, as a fraction of each orb's value.
+    experience_bonus: f32,
+    /// Fractional experience left over from the bonus.
+    experience_carry: f32,
 }
 
 impl PowerUpProgress {
+    const SCHOLAR_EXPERIENCE_BONUS: f32 = 0.2;
+
+    pub fn new(bonuses: &StartingBonuses, character: Character) -> Self {
+        let scholar_bonus = if character.passive() == Passive::Scholar {
+            Self::SCHOLAR_EXPERIENCE_BONUS
+        } else {
+            0.0
+        };
+        Self {
+            experience_bonus: StartingBonuses::EXPERIENCE_INCREMENT * bonuses.experience as f32
+                + scholar_bonus,
+            ..default()
+        }
+    }
+
     pub fn add_experience(&mut self, amount: u32) {
         if amount == 0 {
             return;
         }
-        self.exp_pool = self.exp_pool.saturating_add(amount);
+        let gained = amount as f32 * (1.0 + self.experience_bonus) + self.experience_carry;
+        self.experience_carry = gained.fract();
+        self.exp_pool = self.exp_pool.saturating_add(gained as u32);
         loop {
             let required = self.current_requirement();
             if required == 0 || self.exp_pool < required {
@@ -37,6 +64,11 @@ impl PowerUpProgress {
         }
     }
 
+    /// Queues a power-up selection without requiring experience.
+    pub fn grant_level(&mut self) {
+        self.pending += 1;
+    }
+
     pub fn has_pending(&self) -> bool {
         self.pending > 0
     }
@@ -55,61 +87,115 @@ impl PowerUpProgress {
     pub fn progress_to_next(&self) -> (u32, u32) {
         (self.exp_pool, self.current_requirement())
     }
+
+    pub fn level(&self) -> u32 {
+        1 + self.collected + self.pending
+    }
+}
+
+/// Permanent upgrade levels bought in the shop, applied when a run starts. Recorded in
+/// replays, as they change how the run plays out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StartingBonuses {
+    pub max_health: u32,
+    pub move_speed: u32,
+    pub experience: u32,
+    pub rerolls: u32,
+}
+
+impl StartingBonuses {
+    const MOVE_SPEED_INCREMENT: f32 = 0.05;
+    const EXPERIENCE_INCREMENT: f32 = 0.1;
+
+    pub fn player_max_health(&self, base: i32) -> i32 {
+        base + self.max_health as i32
+    }
 }
 
-#[derive(Resource, Default, Debug)]
+#[derive(Resource, Clone, Default, Debug, Serialize, Deserialize)]
 pub struct PlayerUpgrades {
-    pub rapid_fire_level: u32,
-    pub bomb_rate_level: u32,
+    pub character: Character,
+    /// Rerolls left this run.
+    pub rerolls: u32,
+    /// Base move speed bonus levels from the shop.
+    base_speed_level: u32,
     pub move_speed_level: u32,
-    pub projectile_damage_level: u32,
-    pub projectile_speed_level: u32,
-    pub explosion_radius_level: u32,
+    pub burn_level: u32,
+    pub frost_level: u32,
+    pub impact_level: u32,
+    pub crit_chance_level: u32,
+    pub crit_damage_level: u32,
 }
 
-impl PlayerUpgrades {
-    const RATE_MULTIPLIER: f32 = 0.9;
-    const SPEED_INCREMENT: f32 = 0.1;
-    const PROJECTILE_DAMAGE_BASE: i32 = 1;
-    const PROJECTILE_DAMAGE_INCREMENT: i32 = 1;
-    const PROJECTILE_SPEED_INCREMENT: f32 = 0.1;
-    const EXPLOSION_RADIUS_INCREMENT: f32 = 0.15;
+pub const MAX_CRIT_LEVEL: u32 = 5;
 
-    pub fn fire_rate_duration(&self) -> f32 {
-        FIRE_RATE * Self::RATE_MULTIPLIER.powi(self.rapid_fire_level as i32)
+impl PlayerUpgrades {
+    pub fn new(bonuses: &StartingBonuses, character: Character) -> Self {
+        Self {
+            character,
+            rerolls: bonuses.rerolls,
+            base_speed_level: bonuses.move_speed,
+            burn_level: u32::from(character.passive() == Passive::Kindling),
+            ..default()
+        }
     }
 
-    pub fn bomb_interval_duration(&self) -> f32 {
-        BOMB_INTERVAL * Self::RATE_MULTIPLIER.powi(self.bomb_rate_level as i32)
-    }
+    const SPEED_INCREMENT: f32 = 0.1;
+    const BASE_CRIT_CHANCE: f32 = 0.05;
+    const CRIT_CHANCE_INCREMENT: f32 = 0.05;
+    const BASE_CRIT_MULTIPLIER: f32 = 1.5;
+    const CRIT_MULTIPLIER_INCREMENT: f32 = 0.25;
+    const LUCKY_CRIT_CHANCE: f32 = 0.1;
 
     pub fn movement_speed(&self) -> f32 {
-        PLAYER_SPEED * (1.0 + Self::SPEED_INCREMENT * self.move_speed_level as f32)
+        PLAYER_SPEED
+            * self.character.speed()
+            * (1.0
+                + Self::SPEED_INCREMENT * self.move_speed_level as f32
+                + StartingBonuses::MOVE_SPEED_INCREMENT * self.base_speed_level as f32)
+    }
+
+    pub fn crit_chance(&self) -> f32 {
+        let lucky_bonus = if self.character.passive() == Passive::Lucky {
+            Self::LUCKY_CRIT_CHANCE
+        } else {
+            0.0
+        };
+        Self::BASE_CRIT_CHANCE
+            + Self::CRIT_CHANCE_INCREMENT * self.crit_chance_level as f32
+            + lucky_bonus
     }
 
-    pub fn projectile_damage(&self) -> i32 {
-        Self::PROJECTILE_DAMAGE_BASE
-            + Self::PROJECTILE_DAMAGE_INCREMENT * self.projectile_damage_level as i32
+    pub fn crit_multiplier(&self) -> f32 {
+        Self::BASE_CRIT_MULTIPLIER + Self::CRIT_MULTIPLIER_INCREMENT * self.crit_damage_level as f32
     }
 
-    pub fn projectile_speed(&self) -> f32 {
-        PROJECTILE_SPEED
-            * (1.0 + Self::PROJECTILE_SPEED_INCREMENT * self.projectile_speed_level as f32)
+    /// Rolls for a critical hit on `damage`, returning the damage dealt and whether it crit.
+    /// Always draws once from `rng`.
+    pub fn roll_damage(&self, damage: i32, rng: &mut impl Rng) -> (i32, bool) {
+        if rng.random_bool(self.crit_chance().min(1.0) as f64) {
+            (
+                (damage as f32 * self.crit_multiplier()).round() as i32,
+                true,
+            )
+        } else {
+            (damage, false)
+        }
     }
 
-    pub fn bomb_explosion_radius(&self) -> f32 {
-        BOMB_EXPLOSION_RADIUS
-            * (1.0 + Self::EXPLOSION_RADIUS_INCREMENT * self.explosion_radius_level as f32)
+    pub fn infusion_level(&self, infusion: Infusion) -> u32 {
+        match infusion {
+            Infusion::Burn => self.burn_level,
+            Infusion::Frost => self.frost_level,
+            Infusion::Impact => self.impact_level,
+        }
     }
 
-    pub fn get_level_by_choice(&self, choice: PowerUpChoice) -> u32 {
-        match choice {
-            PowerUpChoice::RapidFire => self.rapid_fire_level,
-            PowerUpChoice::BombRapidFire => self.bomb_rate_level,
-            PowerUpChoice::MoveSpeed => self.move_speed_level,
-            PowerUpChoice::ProjectileDamage => self.projectile_damage_level,
-            PowerUpChoice::ProjectileSpeed => self.projectile_speed_level,
-            PowerUpChoice::ExplosionRadius => self.explosion_radius_level,
+    fn infusion_level_mut(&mut self, infusion: Infusion) -> &mut u32 {
+        match infusion {
+            Infusion::Burn => &mut self.burn_level,
+            Infusion::Frost => &mut self.frost_level,
+            Infusion::Impact => &mut self.impact_level,
         }
     }
 }
@@ -122,76 +208,134 @@ pub struct PowerUpButton {
     pub choice: PowerUpChoice,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PowerUpChoice {
-    RapidFire,
-    BombRapidFire,
+    NewWeapon(WeaponKind),
+    LevelUp(WeaponKind),
     MoveSpeed,
-    ProjectileDamage,
-    ProjectileSpeed,
-    ExplosionRadius,
+    Infusion(Infusion),
+    CritChance,
+    CritDamage,
+    /// Not a power-up: swaps the offer for a new one, using up a reroll. Never offered itself.
+    Reroll,
 }
 
 impl PowerUpChoice {
-    fn label(&self) -> &'static str {
+    pub fn label(&self) -> &'static str {
         match self {
-            PowerUpChoice::RapidFire => "Rapid Fire",
-            PowerUpChoice::BombRapidFire => "Bomb Rapid Fire",
+            PowerUpChoice::NewWeapon(kind) | PowerUpChoice::LevelUp(kind) => kind.label(),
             PowerUpChoice::MoveSpeed => "Move Speed",
-            PowerUpChoice::ProjectileDamage => "Bullet Damage",
-            PowerUpChoice::ProjectileSpeed => "Bullet Speed",
-            PowerUpChoice::ExplosionRadius => "Blast Radius",
+            PowerUpChoice::Infusion(infusion) => infusion.label(),
+            PowerUpChoice::CritChance => "Critical Chance",
+            PowerUpChoice::CritDamage => "Critical Damage",
+            PowerUpChoice::Reroll => "Reroll",
         }
     }
 
     fn description(&self) -> &'static str {
         match self {
-            PowerUpChoice::RapidFire => "Shortens the cooldown between normal shots.",
-            PowerUpChoice::BombRapidFire => "Shortens the cooldown between bomb placements.",
+            PowerUpChoice::NewWeapon(kind) => kind.description(),
+            PowerUpChoice::LevelUp(kind) => kind.level_up_description(),
             PowerUpChoice::MoveSpeed => "Increases movement speed.",
-            PowerUpChoice::ProjectileDamage => "Increases projectile damage by one per level.",
-            PowerUpChoice::ProjectileSpeed => "Sends bullets flying faster.",
-            PowerUpChoice::ExplosionRadius => "Expands bomb explosion size.",
+            PowerUpChoice::Infusion(infusion) => infusion.description(),
+            PowerUpChoice::CritChance => "Gun shots and bombs crit more often.",
+            PowerUpChoice::CritDamage => "Critical hits deal more damage.",
+            PowerUpChoice::Reroll => "Offers a new set of choices.",
         }
     }
 }
 
-const ALL_POWER_UP_CHOICES: [PowerUpChoice; 6] = [
-    PowerUpChoice::RapidFire,
-    PowerUpChoice::BombRapidFire,
-    PowerUpChoice::MoveSpeed,
-    PowerUpChoice::ProjectileDamage,
-    PowerUpChoice::ProjectileSpeed,
-    PowerUpChoice::ExplosionRadius,
-];
-
-fn random_powerup_choices() -> Vec<PowerUpChoice> {
-    let mut choices = ALL_POWER_UP_CHOICES;
-    let mut rng = rand::rng();
-    choices.shuffle(&mut rng);
-    let count = choices.len().min(3);
-    choices[..count].to_vec()
+/// Kind and level of each weapon the player carries.
+fn loadout<'a>(weapons: impl Iterator<Item = &'a Weapon>) -> Vec<(WeaponKind, u32)> {
+    weapons.map(|weapon| (weapon.kind, weapon.level)).collect()
 }
 
-pub fn spawn_menu_when_ready(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
+/// Up to three random choices, each with the level it currently has.
+fn random_powerup_choices(
+    loadout: &[(WeaponKind, u32)],
+    upgrades: &PlayerUpgrades,
+    rng: &mut impl Rng,
+) -> Vec<(PowerUpChoice, u32)> {
+    // Listed in `ALL_WEAPON_KINDS` order rather than loadout order, which follows query
+    // iteration and may differ when a run is replayed.
+    let mut choices: Vec<(PowerUpChoice, u32)> = ALL_WEAPON_KINDS
+        .into_iter()
+        .filter_map(
+            |kind| match loadout.iter().find(|(owned, _)| *owned == kind) {
+                Some(&(_, level)) => {
+                    (level < MAX_WEAPON_LEVEL).then_some((PowerUpChoice::LevelUp(kind), level))
+                }
+                None => {
+                    (loadout.len() < MAX_WEAPONS).then_some((PowerUpChoice::NewWeapon(kind), 0))
+                }
+            },
+        )
+        .collect();
+    choices.push((PowerUpChoice::MoveSpeed, upgrades.move_speed_level));
+    choices.extend(ALL_INFUSIONS.into_iter().filter_map(|infusion| {
+        let level = upgrades.infusion_level(infusion);
+        (level < MAX_INFUSION_LEVEL).then_some((PowerUpChoice::Infusion(infusion), level))
+    }));
+    for (choice, level) in [
+        (PowerUpChoice::CritChance, upgrades.crit_chance_level),
+        (PowerUpChoice::CritDamage, upgrades.crit_damage_level),
+    ] {
+        if level < MAX_CRIT_LEVEL {
+            choices.push((choice, level));
+        }
+    }
+
+    choices.shuffle(rng);
+    choices.truncate(3);
+    choices
+}
+
+/// Power-ups currently on offer. Empty while no selection is open.
+#[derive(Resource, Default)]
+pub struct PowerUpOffer(pub Vec<(PowerUpChoice, u32)>);
+
+/// Opens a selection once enough experience is collected. The menu itself is spawned by
+/// `show_powerup_menu`.
+pub fn offer_powerups_when_ready(
     progress: Res<PowerUpProgress>,
-    menu_query: Query<Entity, With<PowerUpMenu>>,
-    screen_root: Single<Entity, With<OnGameScreen>>,
-    upgrades: ResMut<PlayerUpgrades>,
+    mut offer: ResMut<PowerUpOffer>,
+    upgrades: Res<PlayerUpgrades>,
+    weapons: Query<&Weapon>,
+    mut run_rng: ResMut<RunRng>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    if !progress.has_pending() || !menu_query.is_empty() {
+    if !progress.has_pending() || !offer.0.is_empty() {
         return;
     }
 
-    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
-    let options = random_powerup_choices();
-    spawn_menu(&mut commands, *screen_root, font, &options, &upgrades);
+    offer.0 = random_powerup_choices(&loadout(weapons.iter()), &upgrades, run_rng.gameplay());
     next_state.set(GameState::SelectingPowerUp);
 }
 
+/// Rebuilds the menu whenever the offer changes.
+pub fn show_powerup_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    offer: Res<PowerUpOffer>,
+    upgrades: Res<PlayerUpgrades>,
+    screen_root: Single<Entity, With<OnGameScreen>>,
+    menu_root: Query<Entity, With<PowerUpMenu>>,
+) {
+    for menu_entity in &menu_root {
+        commands.entity(menu_entity).despawn();
+    }
+    if !offer.0.is_empty() {
+        let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+        spawn_menu(
+            &mut commands,
+            *screen_root,
+            font,
+            &offer.0,
+            upgrades.rerolls,
+        );
+    }
+}
+
 pub fn powerup_button_visuals(
     mut query: Query<
         (&Interaction, &mut BackgroundColor),
@@ -207,43 +351,57 @@ pub fn powerup_button_visuals(
     }
 }
 
-pub fn handle_powerup_selection(
+pub fn handle_powerup_buttons(
+    interactions: Query<(&Interaction, &PowerUpButton), (Changed<Interaction>, With<Button>)>,
+    mut selections: MessageWriter<PowerUpSelected>,
+) {
+    if let Some((_, button)) = interactions
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+    {
+        selections.write(PowerUpSelected(button.choice));
+    }
+}
+
+/// Applies the selected power-up, then either rolls the next offer or resumes play.
+pub fn apply_powerup_selection(
     mut commands: Commands,
-    mut interactions: Query<(&Interaction, &PowerUpButton), (Changed<Interaction>, With<Button>)>,
+    mut selections: MessageReader<PowerUpSelected>,
+    mut offer: ResMut<PowerUpOffer>,
     mut progress: ResMut<PowerUpProgress>,
     mut upgrades: ResMut<PlayerUpgrades>,
-    mut shoot_timer: ResMut<ShootTimer>,
-    mut bomb_timer: ResMut<BombTimer>,
-    asset_server: Res<AssetServer>,
-    screen_root: Single<Entity, With<OnGameScreen>>,
-    menu_root: Query<Entity, With<PowerUpMenu>>,
+    mut weapons: Query<&mut Weapon>,
+    mut run_rng: ResMut<RunRng>,
+    mut replay: ResMut<ActiveReplay>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    for (interaction, button) in &mut interactions {
-        if *interaction != Interaction::Pressed {
-            continue;
-        }
-        apply_choice(
-            button.choice,
-            &mut upgrades,
-            &mut shoot_timer,
-            &mut bomb_timer,
-        );
-        progress.mark_selection_consumed();
-
-        if let Some(menu_entity) = menu_root.iter().next() {
-            commands.entity(menu_entity).despawn();
-        }
-
-        if progress.has_pending() {
-            let font = asset_server.load("fonts/FiraSans-Bold.ttf");
-            let options = random_powerup_choices();
-            spawn_menu(&mut commands, *screen_root, font, &options, &upgrades);
-        } else {
-            next_state.set(GameState::Playing);
+    let selected = selections.read().last().map(|selected| selected.0);
+    // A replay makes its recorded picks itself; selections only count once it runs out of them.
+    let Some(choice) = replay.next_choice().or(selected) else {
+        return;
+    };
+    if choice == PowerUpChoice::Reroll && upgrades.rerolls == 0 {
+        return;
+    }
+    replay.record_choice(choice);
+    if choice == PowerUpChoice::Reroll {
+        upgrades.rerolls -= 1;
+        offer.0 = random_powerup_choices(&loadout(weapons.iter()), &upgrades, run_rng.gameplay());
+        return;
+    }
+    apply_choice(&mut commands, choice, &mut upgrades, &mut weapons);
+    progress.mark_selection_consumed();
+
+    if progress.has_pending() {
+        let mut loadout = loadout(weapons.iter());
+        // A weapon picked just now is only spawned once commands are applied.
+        if let PowerUpChoice::NewWeapon(kind) = choice {
+            loadout.push((kind, 1));
         }
-
-        break;
+        offer.0 = random_powerup_choices(&loadout, &upgrades, run_rng.gameplay());
+    } else {
+        offer.0.clear();
+        next_state.set(GameState::Playing);
     }
 }
 
@@ -251,8 +409,8 @@ fn spawn_menu(
     commands: &mut Commands,
     parent: Entity,
     font: Handle<Font>,
-    choices: &[PowerUpChoice],
-    upgrades: &PlayerUpgrades,
+    choices: &[(PowerUpChoice, u32)],
+    rerolls: u32,
 ) {
     commands.entity(parent).with_children(|parent| {
         parent
@@ -306,11 +464,14 @@ fn spawn_menu(
                                 ..default()
                             },))
                             .with_children(|list| {
-                                for &choice in choices {
+                                for &(choice, level) in choices {
+                                    list.spawn(button_bundle(font.clone(), choice, level));
+                                }
+                                if rerolls > 0 {
                                     list.spawn(button_bundle(
                                         font.clone(),
-                                        choice,
-                                        upgrades.get_level_by_choice(choice),
+                                        PowerUpChoice::Reroll,
+                                        rerolls,
                                     ));
                                 }
                             });
@@ -320,6 +481,15 @@ fn spawn_menu(
 }
 
 fn button_bundle(font: Handle<Font>, choice: PowerUpChoice, level: u32) -> impl Bundle {
+    let title = match choice {
+        PowerUpChoice::NewWeapon(_) => format!("{} (New!)", choice.label()),
+        PowerUpChoice::LevelUp(_)
+        | PowerUpChoice::MoveSpeed
+        | PowerUpChoice::Infusion(_)
+        | PowerUpChoice::CritChance
+        | PowerUpChoice::CritDamage => format!("{} ({})", choice.label(), level),
+        PowerUpChoice::Reroll => format!("{} ({} left)", choice.label(), level),
+    };
     (
         Button,
         PowerUpButton { choice },
@@ -334,7 +504,7 @@ fn button_bundle(font: Handle<Font>, choice: PowerUpChoice, level: u32) -> impl
         BackgroundColor(BUTTON_COLOR),
         children![
             (
-                Text::new(format!("{} ({})", choice.label(), level)),
+                Text::new(title),
                 TextFont {
                     font: font.clone(),
                     font_size: 26.0,
@@ -356,41 +526,32 @@ fn button_bundle(font: Handle<Font>, choice: PowerUpChoice, level: u32) -> impl
 }
 
 fn apply_choice(
+    commands: &mut Commands,
     choice: PowerUpChoice,
     upgrades: &mut PlayerUpgrades,
-    shoot_timer: &mut ShootTimer,
-    bomb_timer: &mut BombTimer,
+    weapons: &mut Query<&mut Weapon>,
 ) {
     match choice {
-        PowerUpChoice::RapidFire => {
-            upgrades.rapid_fire_level = upgrades.rapid_fire_level.saturating_add(1);
-            refresh_shoot_timer(upgrades, shoot_timer);
-        }
-        PowerUpChoice::BombRapidFire => {
-            upgrades.bomb_rate_level = upgrades.bomb_rate_level.saturating_add(1);
-            refresh_bomb_timer(upgrades, bomb_timer);
+        PowerUpChoice::NewWeapon(kind) => spawn_weapon(commands, kind),
+        PowerUpChoice::LevelUp(kind) => {
+            if let Some(mut weapon) = weapons.iter_mut().find(|weapon| weapon.kind == kind) {
+                weapon.level_up();
+            }
         }
         PowerUpChoice::MoveSpeed => {
             upgrades.move_speed_level = upgrades.move_speed_level.saturating_add(1);
         }
-        PowerUpChoice::ProjectileDamage => {
-            upgrades.projectile_damage_level = upgrades.projectile_damage_level.saturating_add(1);
+        PowerUpChoice::Infusion(infusion) => {
+            let level = upgrades.infusion_level_mut(infusion);
+            *level = (*level + 1).min(MAX_INFUSION_LEVEL);
         }
-        PowerUpChoice::ProjectileSpeed => {
-            upgrades.projectile_speed_level = upgrades.projectile_speed_level.saturating_add(1);
+        PowerUpChoice::CritChance => {
+            upgrades.crit_chance_level = (upgrades.crit_chance_level + 1).min(MAX_CRIT_LEVEL);
         }
-        PowerUpChoice::ExplosionRadius => {
-            upgrades.explosion_radius_level = upgrades.explosion_radius_level.saturating_add(1);
+        PowerUpChoice::CritDamage => {
+            upgrades.crit_damage_level = (upgrades.crit_damage_level + 1).min(MAX_CRIT_LEVEL);
         }
+        // Handled by `apply_powerup_selection`.
+        PowerUpChoice::Reroll => {}
     }
 }
-
-fn refresh_shoot_timer(upgrades: &PlayerUpgrades, timer: &mut ShootTimer) {
-    let new_duration = upgrades.fire_rate_duration();
-    timer.0.set_duration(Duration::from_secs_f32(new_duration));
-}
-
-fn refresh_bomb_timer(upgrades: &PlayerUpgrades, timer: &mut BombTimer) {
-    let new_duration = upgrades.bomb_interval_duration();
-    timer.0.set_duration(Duration::from_secs_f32(new_duration));
-}
@@ -0,0 +1,532 @@
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::arena::SelectedMap;
+use super::character::Character;
+use super::player::{AimIntent, MoveIntent, Player};
+use super::powerup::{PowerUpChoice, StartingBonuses};
+use super::ui::Score;
+use crate::{Difficulty, cli_arg};
+
+/// Bump whenever a gameplay change would make existing replays play out differently.
+pub const REPLAY_VERSION: u32 = 10;
+/// Ticks between recorded checksums.
+const CHECKSUM_INTERVAL: u32 = 64;
+
+/// Everything needed to play a run back tick for tick: the seed, difficulty and map it started
+/// from, and every input that reached the simulation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    /// Id of the map, one of `MAPS`.
+    map: String,
+    character: Character,
+    /// Shop upgrades the run started with.
+    bonuses: StartingBonuses,
+    /// Movement input, run-length encoded as `(ticks, input)`.
+    movement: Vec<(u32, MoveInput)>,
+    /// Manual aim input, encoded like `movement`.
+    aim: Vec<(u32, AimInput)>,
+    /// Power-up picks in the order they were made.
+    choices: Vec<PowerUpChoice>,
+    /// `(tick, checksum)` taken every `CHECKSUM_INTERVAL` ticks and when the run ends.
+    checksums: Vec<(u32, u64)>,
+}
+
+impl Replay {
+    fn new(
+        seed: u64,
+        difficulty: Difficulty,
+        map: SelectedMap,
+        character: Character,
+        bonuses: StartingBonuses,
+    ) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            seed,
+            difficulty,
+            map: map.0.to_string(),
+            character,
+            bonuses,
+            movement: Vec::new(),
+            aim: Vec::new(),
+            choices: Vec::new(),
+            checksums: Vec::new(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ReplayError> {
+        if self.version != REPLAY_VERSION {
+            return Err(ReplayError::Version {
+                found: self.version,
+            });
+        }
+        if SelectedMap::from_id(&self.map).is_none() {
+            return Err(ReplayError::UnknownMap {
+                map: self.map.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The map the run was played on. Falls back to the default map for a replay that has
+    /// not been validated.
+    pub fn map(&self) -> SelectedMap {
+        SelectedMap::from_id(&self.map).unwrap_or_default()
+    }
+
+    pub fn character(&self) -> Character {
+        self.character
+    }
+
+    pub fn bonuses(&self) -> StartingBonuses {
+        self.bonuses
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        let replay: Replay = ron::from_str(&std::fs::read_to_string(path)?)?;
+        replay.validate()?;
+        Ok(replay)
+    }
+
+    /// Power-up picks in the order they were made.
+    pub fn choices(&self) -> &[PowerUpChoice] {
+        &self.choices
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("could not access replay file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse replay: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("could not serialize replay: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("replay version {found} is not supported by this build (expects {REPLAY_VERSION})")]
+    Version { found: u32 },
+    #[error("replay was recorded on unknown map `{map}`")]
+    UnknownMap { map: String },
+}
+
+/// Movement direction for one tick, quantized so a replay feeds back exactly the value the
+/// recorded run used.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveInput(pub [i8; 2]);
+
+impl MoveInput {
+    pub fn from_direction(direction: Vec2) -> Self {
+        let quantize = |value: f32| (value.clamp(-1.0, 1.0) * 127.0).round() as i8;
+        Self([quantize(direction.x), quantize(direction.y)])
+    }
+
+    pub fn direction(self) -> Vec2 {
+        (Vec2::new(self.0[0] as f32, self.0[1] as f32) / 127.0).clamp_length_max(1.0)
+    }
+}
+
+/// Manual aim direction for one tick, quantized like `MoveInput`. `None` lets the weapons
+/// aim themselves.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AimInput(pub Option<[i8; 2]>);
+
+impl AimInput {
+    pub fn from_direction(direction: Option<Vec2>) -> Self {
+        Self(
+            direction
+                .and_then(Vec2::try_normalize)
+                .map(|direction| MoveInput::from_direction(direction).0),
+        )
+    }
+
+    /// Unit vector to shoot along, if aiming manually.
+    pub fn direction(self) -> Option<Vec2> {
+        self.0
+            .and_then(|[x, y]| Vec2::new(x as f32, y as f32).try_normalize())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayMode {
+    Recording,
+    Playback,
+}
+
+/// The current run's replay, either being recorded from live input or being played back.
+/// Inserted by `reset_game`.
+#[derive(Resource)]
+pub struct ActiveReplay {
+    replay: Replay,
+    mode: ReplayMode,
+    /// Ticks simulated so far.
+    tick: u32,
+    /// Position in `replay.movement`: the current run and how many of its ticks are used.
+    movement_cursor: (usize, u32),
+    /// Position in `replay.aim`, like `movement_cursor`.
+    aim_cursor: (usize, u32),
+    choice_cursor: usize,
+    checksum_cursor: usize,
+    desynced_at: Option<u32>,
+    /// Set for a run resumed from a save. The ticks before the save are missing from the
+    /// recording, so it can't be played back and isn't kept.
+    resumed: bool,
+}
+
+impl ActiveReplay {
+    pub fn record(
+        seed: u64,
+        difficulty: Difficulty,
+        map: SelectedMap,
+        character: Character,
+        bonuses: StartingBonuses,
+    ) -> Self {
+        Self::new(
+            Replay::new(seed, difficulty, map, character, bonuses),
+            ReplayMode::Recording,
+        )
+    }
+
+    /// Records a run resumed from a save, from the moment it was resumed.
+    pub fn resume(
+        seed: u64,
+        difficulty: Difficulty,
+        map: SelectedMap,
+        character: Character,
+        bonuses: StartingBonuses,
+    ) -> Self {
+        Self {
+            resumed: true,
+            ..Self::record(seed, difficulty, map, character, bonuses)
+        }
+    }
+
+    pub fn play(replay: Replay) -> Self {
+        Self::new(replay, ReplayMode::Playback)
+    }
+
+    fn new(replay: Replay, mode: ReplayMode) -> Self {
+        Self {
+            replay,
+            mode,
+            tick: 0,
+            movement_cursor: (0, 0),
+            aim_cursor: (0, 0),
+            choice_cursor: 0,
+            checksum_cursor: 0,
+            desynced_at: None,
+            resumed: false,
+        }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn mode(&self) -> ReplayMode {
+        self.mode
+    }
+
+    pub fn is_resumed(&self) -> bool {
+        self.resumed
+    }
+
+    /// Tick at which playback first diverged from the recording.
+    pub fn desynced_at(&self) -> Option<u32> {
+        self.desynced_at
+    }
+
+    /// Records `live` and returns it, or returns the recorded input when playing back.
+    fn next_movement(&mut self, live: MoveInput) -> MoveInput {
+        let next = next_input(
+            self.mode,
+            &mut self.replay.movement,
+            &mut self.movement_cursor,
+            live,
+        );
+        next.unwrap_or_else(|| {
+            // The recorded run had already ended by this tick.
+            self.mark_desync();
+            MoveInput::default()
+        })
+    }
+
+    /// Like `next_movement`. Running out of aim input is not a desync, as replays recorded
+    /// before manual aim have none.
+    fn next_aim(&mut self, live: AimInput) -> AimInput {
+        next_input(self.mode, &mut self.replay.aim, &mut self.aim_cursor, live).unwrap_or_default()
+    }
+
+    /// The next recorded power-up pick when playing back.
+    pub fn next_choice(&mut self) -> Option<PowerUpChoice> {
+        if self.mode != ReplayMode::Playback {
+            return None;
+        }
+        let choice = self.replay.choices.get(self.choice_cursor).copied();
+        self.choice_cursor += 1;
+        choice
+    }
+
+    pub fn record_choice(&mut self, choice: PowerUpChoice) {
+        if self.mode == ReplayMode::Recording {
+            self.replay.choices.push(choice);
+        }
+    }
+
+    fn checkpoint(&mut self, checksum: u64) {
+        match self.mode {
+            ReplayMode::Recording => self.replay.checksums.push((self.tick, checksum)),
+            ReplayMode::Playback => {
+                let expected = self.replay.checksums.get(self.checksum_cursor).copied();
+                self.checksum_cursor += 1;
+                if expected != Some((self.tick, checksum)) {
+                    self.mark_desync();
+                }
+            }
+        }
+    }
+
+    fn mark_desync(&mut self) {
+        if self.desynced_at.is_none() {
+            error!("Replay desynced at tick {}", self.tick);
+            self.desynced_at = Some(self.tick);
+        }
+    }
+}
+
+/// Appends `live` to a run-length encoded input stream and returns it, or returns the next
+/// recorded input when playing back. `None` once playback reaches the end of the stream.
+fn next_input<T: Copy + PartialEq>(
+    mode: ReplayMode,
+    stream: &mut Vec<(u32, T)>,
+    cursor: &mut (usize, u32),
+    live: T,
+) -> Option<T> {
+    match mode {
+        ReplayMode::Recording => {
+            match stream.last_mut() {
+                Some((ticks, input)) if *input == live => *ticks += 1,
+                _ => stream.push((1, live)),
+            }
+            Some(live)
+        }
+        ReplayMode::Playback => {
+            let (index, used) = cursor;
+            let &(ticks, input) = stream.get(*index)?;
+            *used += 1;
+            if *used == ticks {
+                *index += 1;
+                *used = 0;
+            }
+            Some(input)
+        }
+    }
+}
+
+/// Replay of the last finished run, kept across restarts.
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct LastReplay(pub Option<Replay>);
+
+/// Replay to play when the game starts, from `--replay <path>` or `--replay last`.
+#[derive(Resource, Default)]
+pub struct RequestedReplay(pub Option<Replay>);
+
+/// File every finished run's replay is written to, from `--record <path>`.
+#[derive(Resource, Default)]
+pub struct ReplayExportPath(pub Option<PathBuf>);
+
+impl ReplayExportPath {
+    pub fn from_args() -> Self {
+        Self(cli_arg("--record").map(PathBuf::from))
+    }
+}
+
+pub fn load_requested_replay(mut commands: Commands, last_replay: Res<LastReplay>) {
+    let Some(source) = cli_arg("--replay") else {
+        return;
+    };
+    let replay = if source == "last" {
+        match &last_replay.0 {
+            Some(replay) => replay.validate().map(|_| replay.clone()),
+            None => {
+                warn!("--replay last: no run has been recorded yet");
+                return;
+            }
+        }
+    } else {
+        Replay::load(Path::new(&source))
+    };
+    match replay {
+        Ok(replay) => commands.insert_resource(RequestedReplay(Some(replay))),
+        Err(error) => error!("Could not load replay `{source}`: {error}"),
+    }
+}
+
+/// First system of every tick: decides which movement input the tick runs with.
+pub fn feed_move_input(
+    mut replay: ResMut<ActiveReplay>,
+    intent: Res<MoveIntent>,
+    mut input: ResMut<MoveInput>,
+) {
+    *input = replay.next_movement(MoveInput::from_direction(intent.0));
+}
+
+/// Runs right after `feed_move_input`: decides where the tick's weapons aim.
+pub fn feed_aim_input(
+    mut replay: ResMut<ActiveReplay>,
+    intent: Res<AimIntent>,
+    mut input: ResMut<AimInput>,
+) {
+    *input = replay.next_aim(AimInput::from_direction(intent.0));
+}
+
+/// Last system of every tick.
+pub fn checksum_tick(
+    mut replay: ResMut<ActiveReplay>,
+    score: Res<Score>,
+    player: Query<&Transform, With<Player>>,
+) {
+    replay.tick += 1;
+    if replay.tick.is_multiple_of(CHECKSUM_INTERVAL) {
+        let position = player
+            .single()
+            .ok()
+            .map(|transform| transform.translation.xy());
+        let checksum = checksum(replay.tick, score.0, position);
+        replay.checkpoint(checksum);
+    }
+}
+
+/// Takes the closing checksum, then keeps the replay of a recorded run.
+pub fn finish_replay(
+    mut replay: ResMut<ActiveReplay>,
+    score: Res<Score>,
+    mut last_replay: ResMut<LastReplay>,
+    export_path: Res<ReplayExportPath>,
+) {
+    // The player is gone by the time the run ends.
+    let checksum = checksum(replay.tick, score.0, None);
+    replay.checkpoint(checksum);
+    if replay.mode != ReplayMode::Recording || replay.resumed {
+        return;
+    }
+
+    last_replay.0 = Some(replay.replay.clone());
+    if let Some(path) = &export_path.0 {
+        match replay.replay.save(path) {
+            Ok(()) => info!("Saved replay to {}", path.display()),
+            Err(error) => error!("Could not save replay to {}: {error}", path.display()),
+        }
+    }
+}
+
+/// FNV-1a over the tick, score and player position.
+fn checksum(tick: u32, score: u32, player_position: Option<Vec2>) -> u64 {
+    let position = player_position.map_or([u32::MAX; 2], |position| {
+        [position.x.to_bits(), position.y.to_bits()]
+    });
+    [tick, score, position[0], position[1]]
+        .into_iter()
+        .flat_map(u32::to_le_bytes)
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_inputs_are_run_length_encoded_and_play_back() {
+        let inputs = [1, 1, 1, 2, 3, 3, 1];
+        let mut stream = Vec::new();
+        let mut cursor = (0, 0);
+        for input in inputs {
+            let recorded = next_input(ReplayMode::Recording, &mut stream, &mut cursor, input);
+            assert_eq!(recorded, Some(input));
+        }
+        assert_eq!(stream, [(3, 1), (1, 2), (2, 3), (1, 1)]);
+
+        let mut cursor = (0, 0);
+        let played: Vec<_> = (0..inputs.len())
+            .map(|_| next_input(ReplayMode::Playback, &mut stream, &mut cursor, 0))
+            .collect();
+        assert_eq!(played, inputs.map(Some));
+        assert_eq!(
+            next_input(ReplayMode::Playback, &mut stream, &mut cursor, 0),
+            None
+        );
+    }
+
+    #[test]
+    fn replay_feeds_back_recorded_movement_and_aim() {
+        let moves = [
+            Vec2::ZERO,
+            Vec2::new(0.3, -0.9),
+            Vec2::new(0.3, -0.9),
+            Vec2::X,
+        ];
+        let aims = [None, Some(Vec2::new(-2.0, 1.0)), None, Some(Vec2::Y)];
+        let mut recording = ActiveReplay::record(
+            7,
+            Difficulty::Normal,
+            SelectedMap::default(),
+            Character::default(),
+            StartingBonuses::default(),
+        );
+        let recorded: Vec<_> = moves
+            .iter()
+            .zip(aims)
+            .map(|(&movement, aim)| {
+                (
+                    recording.next_movement(MoveInput::from_direction(movement)),
+                    recording.next_aim(AimInput::from_direction(aim)),
+                )
+            })
+            .collect();
+
+        // Round-trip through the saved text like a replay loaded from disk.
+        let text = ron::to_string(recording.replay()).unwrap();
+        let mut playback = ActiveReplay::play(ron::from_str(&text).unwrap());
+        for (movement, aim) in recorded {
+            assert_eq!(playback.next_movement(MoveInput::default()), movement);
+            assert_eq!(playback.next_aim(AimInput::default()), aim);
+        }
+        assert_eq!(playback.desynced_at(), None);
+        playback.next_movement(MoveInput::default());
+        assert_eq!(playback.desynced_at(), Some(0));
+    }
+
+    #[test]
+    fn quantized_input_stays_within_range() {
+        let input = MoveInput::from_direction(Vec2::new(5.0, -5.0));
+        assert_eq!(input, MoveInput([127, -127]));
+        assert!(input.direction().length() <= 1.0 + f32::EPSILON);
+        assert_eq!(AimInput::from_direction(Some(Vec2::ZERO)), AimInput(None));
+        let aim = AimInput::from_direction(Some(Vec2::new(3.0, 4.0)))
+            .direction()
+            .unwrap();
+        assert!((aim - Vec2::new(0.6, 0.8)).length() < 0.01);
+    }
+
+    #[test]
+    fn checksum_depends_on_every_input() {
+        let base = checksum(64, 10, Some(Vec2::new(1.0, 2.0)));
+        assert_eq!(base, checksum(64, 10, Some(Vec2::new(1.0, 2.0))));
+        assert_ne!(base, checksum(128, 10, Some(Vec2::new(1.0, 2.0))));
+        assert_ne!(base, checksum(64, 11, Some(Vec2::new(1.0, 2.0))));
+        assert_ne!(base, checksum(64, 10, Some(Vec2::new(2.0, 1.0))));
+        assert_ne!(base, checksum(64, 10, None));
+    }
+}
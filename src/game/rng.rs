@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::cli_arg;
+
+/// Mixed into the run seed so the cosmetic stream doesn't mirror the gameplay one.
+const COSMETIC_STREAM: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// All randomness of a run, reseeded by `reset_game`. Anything that affects the simulation
+/// draws from `gameplay`; purely visual effects such as particles draw from `cosmetic`, so
+/// changing them never changes how a run plays out.
+#[derive(Resource)]
+pub struct RunRng {
+    seed: u64,
+    gameplay: StdRng,
+    cosmetic: StdRng,
+}
+
+impl RunRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            gameplay: StdRng::seed_from_u64(seed),
+            cosmetic: StdRng::seed_from_u64(seed ^ COSMETIC_STREAM),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn gameplay(&mut self) -> &mut StdRng {
+        &mut self.gameplay
+    }
+
+    pub fn cosmetic(&mut self) -> &mut StdRng {
+        &mut self.cosmetic
+    }
+}
+
+/// Seed for the next run started from the menu. Taken once; later runs get fresh seeds.
+#[derive(Resource, Default)]
+pub struct NextRunSeed(pub Option<u64>);
+
+impl NextRunSeed {
+    /// Reads `--seed <n>` from the command line.
+    pub fn from_args() -> Self {
+        let seed = cli_arg("--seed").and_then(|value| match value.parse() {
+            Ok(seed) => Some(seed),
+            Err(_) => {
+                warn!("Ignoring invalid --seed value `{value}`");
+                None
+            }
+        });
+        Self(seed)
+    }
+
+    pub fn take_or_random(&mut self) -> u64 {
+        self.0.take().unwrap_or_else(random_seed)
+    }
+}
+
+/// Seeds are kept to 32 bits so they are short enough to read off the screen and type back in.
+pub fn random_seed() -> u64 {
+    rand::random::<u32>() as u64
+}
@@ -0,0 +1,259 @@
+//! Saving a run in progress from the pause menu, and resuming it from the main menu.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::arena::{CameraView, SelectedMap};
+use super::behavior::EnemyBehavior;
+use super::boss::{Boss, BossSpawner};
+use super::character::{Character, Regeneration};
+use super::components::{Bomb, ExperienceOrb, Health, Velocity};
+use super::enemy::{Enemy, EnemyAttributes, spawn_enemy};
+use super::experience::{OrbMesh, spawn_orb};
+use super::pickup::EnemyFreeze;
+use super::player::{Player, PlayerStats, spawn_player};
+use super::powerup::{PlayerUpgrades, PowerUpProgress, StartingBonuses};
+use super::replay::ActiveReplay;
+use super::rng::{RunRng, random_seed};
+use super::ui::Score;
+use super::waves::WaveDirector;
+use super::weapon::{Weapon, restore_weapon, spawn_bomb};
+use super::{GameState, insert_run_resources};
+use crate::Difficulty;
+
+/// Bump whenever `RunSave` changes shape, so saves from older builds are dropped instead of
+/// resumed into the wrong state.
+pub const SAVE_VERSION: u32 = 1;
+
+/// A run frozen mid-game. Projectiles, explosions, pickups and status effects are short-lived
+/// and not kept; enemies' behavior timers and the boss' attack timers start over.
+#[derive(Serialize, Deserialize)]
+pub struct RunSave {
+    version: u32,
+    difficulty: Difficulty,
+    /// Id of the map, one of `MAPS`.
+    map: String,
+    character: Character,
+    bonuses: StartingBonuses,
+    player: SavedPlayer,
+    upgrades: PlayerUpgrades,
+    progress: PowerUpProgress,
+    stats: PlayerStats,
+    score: u32,
+    director: WaveDirector,
+    boss_spawner: BossSpawner,
+    freeze: EnemyFreeze,
+    view_center: Vec2,
+    weapons: Vec<Weapon>,
+    enemies: Vec<SavedEnemy>,
+    orbs: Vec<(Vec2, Vec2, ExperienceOrb)>,
+    bombs: Vec<(Vec2, Bomb)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedPlayer {
+    position: Vec2,
+    health: Health,
+    regeneration: Option<Timer>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEnemy {
+    name: String,
+    attributes: EnemyAttributes,
+    behavior: EnemyBehavior,
+    health: Health,
+    position: Vec2,
+    velocity: Vec2,
+    boss_phase: Option<usize>,
+}
+
+impl RunSave {
+    /// Whether this build can resume the save.
+    pub fn is_compatible(&self) -> bool {
+        self.version == SAVE_VERSION && SelectedMap::from_id(&self.map).is_some()
+    }
+}
+
+/// The saved run, kept across restarts. Taken when it is resumed, so each save can be
+/// continued only once and a resumed run that ends in death leaves nothing behind. Starting a
+/// new run instead leaves the save in place.
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct SavedRun(pub Option<RunSave>);
+
+/// Save to resume when the game starts, set by the main menu's Continue button.
+#[derive(Resource, Default)]
+pub struct RequestedResume(pub Option<RunSave>);
+
+/// Drops a save this build can't resume, so the menu doesn't offer it.
+pub fn discard_incompatible_save(mut saved: ResMut<SavedRun>) {
+    if saved.0.as_ref().is_some_and(|save| !save.is_compatible()) {
+        warn!("Discarding a saved run from an incompatible version");
+        saved.0 = None;
+    }
+}
+
+/// Stores the current run in `SavedRun`. Queued by the pause menu's Save & Quit just before
+/// leaving the game.
+pub fn save_run(
+    mut saved: ResMut<SavedRun>,
+    replay: Res<ActiveReplay>,
+    (upgrades, progress, stats, score): (
+        Res<PlayerUpgrades>,
+        Res<PowerUpProgress>,
+        Res<PlayerStats>,
+        Res<Score>,
+    ),
+    (director, boss_spawner, freeze, view): (
+        Res<WaveDirector>,
+        Res<BossSpawner>,
+        Res<EnemyFreeze>,
+        Res<CameraView>,
+    ),
+    player: Single<(&Transform, &Health, Option<&Regeneration>), With<Player>>,
+    weapons: Query<&Weapon>,
+    enemies: Query<
+        (
+            &Name,
+            &EnemyAttributes,
+            &EnemyBehavior,
+            &Health,
+            &Transform,
+            &Velocity,
+            Option<&Boss>,
+        ),
+        With<Enemy>,
+    >,
+    orbs: Query<(&Transform, &Velocity, &ExperienceOrb)>,
+    bombs: Query<(&Transform, &Bomb)>,
+) {
+    let (player_transform, player_health, regeneration) = *player;
+    let run = replay.replay();
+    saved.0 = Some(RunSave {
+        version: SAVE_VERSION,
+        difficulty: run.difficulty,
+        map: run.map().0.to_string(),
+        character: run.character(),
+        bonuses: run.bonuses(),
+        player: SavedPlayer {
+            position: player_transform.translation.xy(),
+            health: *player_health,
+            regeneration: regeneration.map(|regeneration| regeneration.timer.clone()),
+        },
+        upgrades: upgrades.clone(),
+        progress: progress.clone(),
+        stats: stats.clone(),
+        score: score.0,
+        director: director.clone(),
+        boss_spawner: boss_spawner.clone(),
+        freeze: freeze.clone(),
+        view_center: view.center,
+        weapons: weapons.iter().cloned().collect(),
+        enemies: enemies
+            .iter()
+            .map(
+                |(name, attributes, behavior, health, transform, velocity, boss)| SavedEnemy {
+                    name: name.to_string(),
+                    attributes: *attributes,
+                    behavior: *behavior,
+                    health: *health,
+                    position: transform.translation.xy(),
+                    velocity: velocity.0,
+                    boss_phase: boss.map(|boss| boss.phase),
+                },
+            )
+            .collect(),
+        orbs: orbs
+            .iter()
+            .map(|(transform, velocity, orb)| (transform.translation.xy(), velocity.0, *orb))
+            .collect(),
+        bombs: bombs
+            .iter()
+            .map(|(transform, bomb)| (transform.translation.xy(), bomb.clone()))
+            .collect(),
+    });
+    info!("Saved the run");
+}
+
+/// Starts the game from `save`. Queued by the game's setup once the orb mesh exists.
+pub fn resume_run(In(save): In<RunSave>, mut commands: Commands, orb_mesh: Res<OrbMesh>) {
+    let map = SelectedMap::from_id(&save.map).unwrap_or_default();
+    // The random generators' state isn't saved, so the rest of the run draws from a new seed.
+    let seed = random_seed();
+    info!(
+        "Resuming run on map `{}` as {} with seed {seed}",
+        map.0,
+        save.character.label()
+    );
+    commands.insert_resource(save.difficulty);
+    insert_run_resources(
+        &mut commands,
+        save.difficulty,
+        map,
+        save.character,
+        save.bonuses,
+        seed,
+    );
+    commands.insert_resource(ActiveReplay::resume(
+        seed,
+        save.difficulty,
+        map,
+        save.character,
+        save.bonuses,
+    ));
+    commands.insert_resource(save.upgrades);
+    commands.insert_resource(save.progress);
+    commands.insert_resource(save.stats);
+    commands.insert_resource(Score(save.score));
+    commands.insert_resource(save.director);
+    commands.insert_resource(save.boss_spawner);
+    commands.insert_resource(save.freeze);
+    commands.insert_resource(CameraView {
+        center: save.view_center,
+    });
+
+    let player = spawn_player(
+        &mut commands,
+        &save.difficulty,
+        &save.bonuses,
+        save.character,
+    );
+    let mut player = commands.entity(player);
+    player.insert((
+        Transform::from_translation(save.player.position.extend(0.0)),
+        save.player.health,
+    ));
+    if let Some(timer) = save.player.regeneration {
+        player.insert(Regeneration { timer });
+    }
+
+    for weapon in save.weapons {
+        restore_weapon(&mut commands, weapon);
+    }
+    let mut run_rng = RunRng::new(seed);
+    for enemy in save.enemies {
+        let entity = spawn_enemy(
+            &mut commands,
+            enemy.name,
+            enemy.attributes,
+            enemy.behavior,
+            enemy.position,
+            enemy.velocity,
+            run_rng.gameplay(),
+        );
+        let mut entity = commands.entity(entity);
+        entity.insert(enemy.health);
+        if let Some(phase) = enemy.boss_phase {
+            entity.insert(Boss::in_phase(phase));
+        }
+    }
+    for (position, velocity, orb) in save.orbs {
+        spawn_orb(&mut commands, &orb_mesh, position, velocity, orb);
+    }
+    for (position, bomb) in save.bombs {
+        spawn_bomb(&mut commands, position, bomb);
+    }
+    commands.insert_resource(run_rng);
+
+    commands.set_state(GameState::Playing);
+}
@@ -0,0 +1,170 @@
+use std::cmp::Ordering;
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+use super::components::ExperienceOrb;
+use super::constants::{EXPERIENCE_ORB_SIZE, SPATIAL_CELL_SIZE};
+use super::enemy::{Enemy, EnemyAttributes};
+
+#[derive(Clone, Copy, Debug)]
+pub struct SpatialEntry {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub radius: f32,
+}
+
+/// Uniform grid bucketing entities by position, so neighbourhood lookups only visit nearby
+/// cells instead of every entity.
+#[derive(Default)]
+pub struct SpatialGrid {
+    cells: HashMap<IVec2, Vec<SpatialEntry>>,
+    /// Smallest and largest occupied cell.
+    bounds: Option<(IVec2, IVec2)>,
+    max_radius: f32,
+}
+
+fn cell_of(position: Vec2) -> IVec2 {
+    (position / SPATIAL_CELL_SIZE).floor().as_ivec2()
+}
+
+impl SpatialGrid {
+    /// Empties the grid but keeps its allocations for the next rebuild.
+    pub fn clear(&mut self) {
+        for entries in self.cells.values_mut() {
+            entries.clear();
+        }
+        self.bounds = None;
+        self.max_radius = 0.0;
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec2, radius: f32) {
+        let cell = cell_of(position);
+        self.cells.entry(cell).or_default().push(SpatialEntry {
+            entity,
+            position,
+            radius,
+        });
+        self.bounds = Some(match self.bounds {
+            Some((min, max)) => (min.min(cell), max.max(cell)),
+            None => (cell, cell),
+        });
+        self.max_radius = self.max_radius.max(radius);
+    }
+
+    /// Orders each cell's entries by position. Query iteration order depends on entity ids and
+    /// archetype layout, which differ between a run and its replay, so without this the same
+    /// lookup could visit entries in a different order. Entries at the same position are
+    /// ordered by entity.
+    pub fn sort(&mut self) {
+        for entries in self.cells.values_mut() {
+            entries.sort_by(|a, b| {
+                compare_positions(a.position, b.position).then(a.entity.cmp(&b.entity))
+            });
+        }
+    }
+
+    /// Largest radius of any entry, for padding queries that test against entry extents.
+    pub fn max_radius(&self) -> f32 {
+        self.max_radius
+    }
+
+    /// Entries in the cells overlapping the square of half-size `reach` around `center`.
+    /// Every entry whose position is within `reach` is included, along with some that are
+    /// farther away, so callers still need an exact distance test.
+    pub fn candidates(&self, center: Vec2, reach: f32) -> impl Iterator<Item = &SpatialEntry> {
+        let min = cell_of(center - Vec2::splat(reach));
+        let max = cell_of(center + Vec2::splat(reach));
+        (min.y..=max.y)
+            .flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+    }
+
+    /// The entry closest to `center` that passes `filter`, searching outwards ring by ring.
+    pub fn nearest(
+        &self,
+        center: Vec2,
+        mut filter: impl FnMut(&SpatialEntry) -> bool,
+    ) -> Option<&SpatialEntry> {
+        let (min, max) = self.bounds?;
+        let origin = cell_of(center);
+        // Past this ring no occupied cell remains.
+        let last_ring = (origin - min).abs().max((origin - max).abs()).max_element();
+
+        let mut best: Option<(&SpatialEntry, f32)> = None;
+        for ring in 0..=last_ring {
+            // `center` may sit on the edge of its cell, so cells in this ring are at least
+            // `ring - 1` cells away.
+            if let Some((_, best_distance_sq)) = best {
+                let ring_distance = (ring - 1).max(0) as f32 * SPATIAL_CELL_SIZE;
+                if ring_distance * ring_distance > best_distance_sq {
+                    break;
+                }
+            }
+            for cell in ring_cells(origin, ring) {
+                let Some(entries) = self.cells.get(&cell) else {
+                    continue;
+                };
+                for entry in entries {
+                    let distance_sq = center.distance_squared(entry.position);
+                    if best.is_none_or(|(_, best_distance_sq)| distance_sq < best_distance_sq)
+                        && filter(entry)
+                    {
+                        best = Some((entry, distance_sq));
+                    }
+                }
+            }
+        }
+        best.map(|(entry, _)| entry)
+    }
+}
+
+/// Total order on positions, by `x` then `y`.
+pub fn compare_positions(a: Vec2, b: Vec2) -> Ordering {
+    a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y))
+}
+
+/// Cells at Chebyshev distance `ring` from `origin`.
+fn ring_cells(origin: IVec2, ring: i32) -> impl Iterator<Item = IVec2> {
+    // Ring 0 is a single cell, so its top and bottom rows coincide.
+    let rows = [-ring, ring]
+        .into_iter()
+        .take(if ring == 0 { 1 } else { 2 });
+    let top_and_bottom = rows.flat_map(move |y| (-ring..=ring).map(move |x| IVec2::new(x, y)));
+    let sides = (1 - ring..ring).flat_map(move |y| [IVec2::new(-ring, y), IVec2::new(ring, y)]);
+    top_and_bottom
+        .chain(sides)
+        .map(move |offset| origin + offset)
+}
+
+/// Positions of enemies and experience orbs as of the current fixed-update tick.
+#[derive(Resource, Default)]
+pub struct SpatialIndex {
+    pub enemies: SpatialGrid,
+    pub orbs: SpatialGrid,
+}
+
+/// Runs once per tick right after movement, so lookups later in the tick see current
+/// positions. Entities spawned during the tick are picked up on the next rebuild.
+pub fn rebuild_spatial_index(
+    mut index: ResMut<SpatialIndex>,
+    enemies: Query<(Entity, &Transform, &EnemyAttributes), With<Enemy>>,
+    orbs: Query<(Entity, &Transform), With<ExperienceOrb>>,
+) {
+    let index = &mut *index;
+    index.enemies.clear();
+    for (entity, transform, attributes) in &enemies {
+        index
+            .enemies
+            .insert(entity, transform.translation.xy(), attributes.radius());
+    }
+    index.enemies.sort();
+    index.orbs.clear();
+    for (entity, transform) in &orbs {
+        index
+            .orbs
+            .insert(entity, transform.translation.xy(), EXPERIENCE_ORB_SIZE);
+    }
+    index.orbs.sort();
+}
@@ -0,0 +1,290 @@
+//! Timed effects on enemies: burn, slow, freeze and knockback. Weapons apply them on hit once
+//! the player has taken the matching infusion power-up.
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::boss::Boss;
+use super::components::Velocity;
+use super::enemy::{Enemy, EnemyAttributes, EnemyHitFlash};
+use super::events::DamageEnemy;
+use super::powerup::PlayerUpgrades;
+
+pub const MAX_INFUSION_LEVEL: u32 = 5;
+/// Seconds between burn damage ticks.
+const BURN_INTERVAL: f32 = 0.5;
+const MAX_BURN_STACKS: u32 = 5;
+/// How quickly knockback fades, per second.
+const KNOCKBACK_DAMPING: f32 = 8.0;
+/// Knockback slower than this is dropped.
+const KNOCKBACK_MIN_SPEED: f32 = 5.0;
+/// How much of an affected enemy's colour the tint replaces.
+const TINT_STRENGTH: f32 = 0.55;
+const BURN_TINT: Color = Color::srgb(1.0, 0.45, 0.1);
+const SLOW_TINT: Color = Color::srgb(0.3, 0.5, 1.0);
+const FREEZE_TINT: Color = Color::srgb(0.8, 0.95, 1.0);
+
+/// An effect a damage source applies to an enemy.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatusEffect {
+    /// Deals `damage` per stack every `BURN_INTERVAL` for `duration` seconds. Each application
+    /// adds a stack, up to `MAX_BURN_STACKS`, and refreshes the duration.
+    Burn { damage: i32, duration: f32 },
+    /// Multiplies movement speed by `factor`. Only the strongest slow applies; its duration
+    /// extends to the longest applied.
+    Slow { factor: f32, duration: f32 },
+    /// Stops movement and attacks. Doesn't stack; the duration extends to the longest applied.
+    Freeze { duration: f32 },
+    /// Pushes the enemy with a velocity that fades out. Impulses add up.
+    Knockback { impulse: Vec2 },
+}
+
+/// Applies `effect` to `target`. Read by `apply_status_effects`.
+#[derive(Message)]
+pub struct ApplyStatus {
+    pub target: Entity,
+    pub effect: StatusEffect,
+}
+
+/// Power-ups that make every weapon hit apply a status effect.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Infusion {
+    Burn,
+    Frost,
+    Impact,
+}
+
+pub const ALL_INFUSIONS: [Infusion; 3] = [Infusion::Burn, Infusion::Frost, Infusion::Impact];
+
+impl Infusion {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Infusion::Burn => "Incendiary",
+            Infusion::Frost => "Frost",
+            Infusion::Impact => "Impact",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Infusion::Burn => "Hits set enemies on fire, stacking up to five times.",
+            Infusion::Frost => "Hits slow enemies, with a chance to freeze them solid.",
+            Infusion::Impact => "Hits knock enemies back.",
+        }
+    }
+
+    /// What one hit applies at `level`, knocking back along `away` (a unit vector). Frost's
+    /// freeze chance draws from `rng`.
+    fn effects(&self, level: u32, away: Vec2, rng: &mut impl Rng) -> Vec<StatusEffect> {
+        let level = level as f32;
+        match self {
+            Infusion::Burn => vec![StatusEffect::Burn {
+                damage: 1,
+                duration: 1.5 + 0.5 * level,
+            }],
+            Infusion::Frost => {
+                let mut effects = vec![StatusEffect::Slow {
+                    factor: (0.75 - 0.05 * level).max(0.4),
+                    duration: 1.5,
+                }];
+                if rng.random_bool((0.04 * level as f64).min(1.0)) {
+                    effects.push(StatusEffect::Freeze { duration: 1.0 });
+                }
+                effects
+            }
+            Infusion::Impact => vec![StatusEffect::Knockback {
+                impulse: away * (120.0 + 40.0 * level),
+            }],
+        }
+    }
+}
+
+/// Effects the player's infusions apply to an enemy hit at `enemy_pos`.
+pub fn on_hit_effects(
+    upgrades: &PlayerUpgrades,
+    player_pos: Vec2,
+    enemy_pos: Vec2,
+    rng: &mut impl Rng,
+) -> Vec<StatusEffect> {
+    let away = (enemy_pos - player_pos).normalize_or(Vec2::X);
+    ALL_INFUSIONS
+        .into_iter()
+        .filter(|infusion| upgrades.infusion_level(*infusion) > 0)
+        .flat_map(|infusion| infusion.effects(upgrades.infusion_level(infusion), away, rng))
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+struct Burn {
+    damage: i32,
+    stacks: u32,
+    remaining: f32,
+    tick: Timer,
+}
+
+/// Effects currently on an enemy. Removed once they have all worn off.
+#[derive(Component, Clone, Debug, Default)]
+pub struct StatusEffects {
+    burn: Option<Burn>,
+    /// Speed factor and seconds left.
+    slow: Option<(f32, f32)>,
+    /// Seconds left frozen.
+    freeze: f32,
+    knockback: Vec2,
+}
+
+impl StatusEffects {
+    fn apply(&mut self, effect: StatusEffect, is_boss: bool) {
+        match effect {
+            StatusEffect::Burn { damage, duration } => match &mut self.burn {
+                Some(burn) => {
+                    burn.stacks = (burn.stacks + 1).min(MAX_BURN_STACKS);
+                    burn.damage = burn.damage.max(damage);
+                    burn.remaining = burn.remaining.max(duration);
+                }
+                None => {
+                    self.burn = Some(Burn {
+                        damage,
+                        stacks: 1,
+                        remaining: duration,
+                        tick: Timer::from_seconds(BURN_INTERVAL, TimerMode::Repeating),
+                    });
+                }
+            },
+            StatusEffect::Slow { factor, duration } => {
+                let (current_factor, remaining) = self.slow.unwrap_or((1.0, 0.0));
+                self.slow = Some((current_factor.min(factor), remaining.max(duration)));
+            }
+            // Bosses can't be frozen or pushed around.
+            StatusEffect::Freeze { duration } if !is_boss => {
+                self.freeze = self.freeze.max(duration);
+            }
+            StatusEffect::Knockback { impulse } if !is_boss => self.knockback += impulse,
+            StatusEffect::Freeze { .. } | StatusEffect::Knockback { .. } => {}
+        }
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.freeze > 0.0
+    }
+
+    fn is_empty(&self) -> bool {
+        self.burn.is_none()
+            && self.slow.is_none()
+            && !self.is_frozen()
+            && self.knockback == Vec2::ZERO
+    }
+
+    /// `base` tinted by the most visible effect.
+    fn tint(&self, base: Color) -> Color {
+        let tint = if self.is_frozen() {
+            FREEZE_TINT
+        } else if self.burn.is_some() {
+            BURN_TINT
+        } else if self.slow.is_some() {
+            SLOW_TINT
+        } else {
+            return base;
+        };
+        base.mix(&tint, TINT_STRENGTH)
+    }
+}
+
+/// Adds effects from [`ApplyStatus`] messages, inserting [`StatusEffects`] where needed.
+pub fn apply_status_effects(
+    mut commands: Commands,
+    mut messages: MessageReader<ApplyStatus>,
+    mut enemies: Query<(Option<&mut StatusEffects>, Has<Boss>), With<Enemy>>,
+) {
+    // Enemies hit for the first time this tick, which get the component once commands apply.
+    let mut added: HashMap<Entity, StatusEffects> = HashMap::new();
+    for message in messages.read() {
+        let Ok((effects, is_boss)) = enemies.get_mut(message.target) else {
+            continue;
+        };
+        match effects {
+            Some(mut effects) => effects.apply(message.effect, is_boss),
+            None => added
+                .entry(message.target)
+                .or_default()
+                .apply(message.effect, is_boss),
+        }
+    }
+    for (entity, effects) in added {
+        commands.entity(entity).insert(effects);
+    }
+}
+
+/// Counts effects down and deals burn damage, which goes through `handle_collisions` like any
+/// other damage so kills score as usual.
+pub fn tick_status_effects(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut enemies: Query<(
+        Entity,
+        &mut StatusEffects,
+        &mut Sprite,
+        &EnemyAttributes,
+        Has<EnemyHitFlash>,
+    )>,
+    mut damage_messages: MessageWriter<DamageEnemy>,
+) {
+    let delta = time.delta_secs();
+    for (entity, mut effects, mut sprite, attributes, flashing) in &mut enemies {
+        if let Some(burn) = &mut effects.burn {
+            if burn.tick.tick(time.delta()).just_finished() {
+                damage_messages.write(DamageEnemy {
+                    target: entity,
+                    amount: burn.damage * burn.stacks as i32,
+                    on_hit: false,
+                });
+            }
+            burn.remaining -= delta;
+            if burn.remaining <= 0.0 {
+                effects.burn = None;
+            }
+        }
+        if let Some((_, remaining)) = &mut effects.slow {
+            *remaining -= delta;
+            if *remaining <= 0.0 {
+                effects.slow = None;
+            }
+        }
+        effects.freeze = (effects.freeze - delta).max(0.0);
+        effects.knockback *= (-KNOCKBACK_DAMPING * delta).exp();
+        if effects.knockback.length() < KNOCKBACK_MIN_SPEED {
+            effects.knockback = Vec2::ZERO;
+        }
+
+        if effects.is_empty() {
+            commands.entity(entity).remove::<StatusEffects>();
+            if !flashing {
+                sprite.color = attributes.color;
+            }
+        }
+    }
+}
+
+/// Runs after the behaviors and repulsion have set each enemy's velocity for the tick, so the
+/// effects apply on top of it instead of being overwritten.
+pub fn apply_status_movement(mut enemies: Query<(&mut Velocity, &StatusEffects), With<Enemy>>) {
+    for (mut velocity, effects) in &mut enemies {
+        velocity.0 = if effects.is_frozen() {
+            Vec2::ZERO
+        } else {
+            let speed_factor = effects.slow.map_or(1.0, |(factor, _)| factor);
+            velocity.0 * speed_factor + effects.knockback
+        };
+    }
+}
+
+/// Tints affected enemies. A hit flash takes precedence and the tint returns after it.
+pub fn tint_status_effects(
+    mut enemies: Query<(&mut Sprite, &StatusEffects, &EnemyAttributes), Without<EnemyHitFlash>>,
+) {
+    for (mut sprite, effects, attributes) in &mut enemies {
+        sprite.color = effects.tint(attributes.color);
+    }
+}
@@ -1,7 +1,10 @@
 use bevy::prelude::*;
 
 use super::powerup::PowerUpProgress;
+use super::replay::{ActiveReplay, ReplayMode};
+use super::waves::WaveDirector;
 use crate::MainState;
+use crate::highscore::format_duration;
 
 #[derive(Resource, Default)]
 pub struct Score(pub u32);
@@ -16,50 +19,150 @@ pub struct HealthBarFill {
     pub full_size: Vec2,
 }
 
+#[derive(Component)]
+pub struct BossHealthBar;
+
+#[derive(Component)]
+pub struct BossHealthBarFill;
+
+#[derive(Component)]
+pub struct BossNameText;
+
 #[derive(Component)]
 pub struct PauseOverlay;
 
 #[derive(Component)]
 pub struct ScoreboardUi;
 
-pub fn setup(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    camera: Single<Entity, With<Camera2d>>,
-) {
+#[derive(Component)]
+pub struct ReplayStatusText;
+
+pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     let font_handle = asset_server.load("fonts/FiraSans-Bold.ttf");
 
-    commands.entity(*camera).with_children(|parent| {
-        parent.spawn((
-            DespawnOnExit(MainState::Game),
-            ScoreboardUi,
-            Text2d("Score: ".to_string()),
+    commands.spawn((
+        DespawnOnExit(MainState::Game),
+        ScoreboardUi,
+        Text::new("Score: "),
+        TextFont {
+            font: font_handle.clone(),
+            font_size: 32.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            top: px(16),
+            left: px(16),
+            ..default()
+        },
+        ScoreText,
+        children![(
+            TextSpan::default(),
             TextFont {
-                font: font_handle.clone(),
                 font_size: 32.0,
                 ..default()
             },
             TextColor(Color::WHITE),
-            Transform::from_translation(Vec3::new(-300.0, 250.0, 1.0)),
-            ScoreText,
-            children![(
-                TextSpan::default(),
+        )],
+    ));
+
+    commands.spawn((
+        DespawnOnExit(MainState::Game),
+        ReplayStatusText,
+        Text::new(""),
+        TextFont {
+            font: font_handle.clone(),
+            font_size: 22.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            top: px(16),
+            right: px(16),
+            ..default()
+        },
+    ));
+
+    commands.spawn((
+        DespawnOnExit(MainState::Game),
+        BossHealthBar,
+        Visibility::Hidden,
+        Node {
+            position_type: PositionType::Absolute,
+            top: px(16),
+            left: percent(20),
+            width: percent(60),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: px(4),
+            ..default()
+        },
+        children![
+            (
+                BossNameText,
+                Text::new(""),
                 TextFont {
-                    font_size: 32.0,
+                    font: font_handle,
+                    font_size: 22.0,
                     ..default()
                 },
                 TextColor(Color::WHITE),
-            )],
-        ));
-    });
+            ),
+            (
+                Node {
+                    width: percent(100),
+                    height: px(14),
+                    ..default()
+                },
+                BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+                children![(
+                    BossHealthBarFill,
+                    Node {
+                        width: percent(100),
+                        height: percent(100),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgba(0.85, 0.15, 0.3, 0.95)),
+                )],
+            ),
+        ],
+    ));
 }
 
 pub fn update_score_text(
     score: Res<Score>,
     progress: Res<PowerUpProgress>,
-    score_root: Single<Entity, (With<ScoreboardUi>, With<Text2d>)>,
+    director: Res<WaveDirector>,
+    score_root: Single<Entity, (With<ScoreboardUi>, With<Text>)>,
     mut writer: TextUiWriter,
 ) {
     let (current, required) = progress.progress_to_next();
-    *writer.text(*score_root, 1) = format!("{}\nXP: {}/{}", score.0, current, required);
+    *writer.text(*score_root, 1) = format!(
+        "{}\nXP: {}/{}\nTime: {}",
+        score.0,
+        current,
+        required,
+        format_duration(director.elapsed())
+    );
+}
+
+pub fn update_replay_status(
+    replay: Res<ActiveReplay>,
+    status: Single<(&mut Text, &mut TextColor), With<ReplayStatusText>>,
+) {
+    let (mut text, mut color) = status.into_inner();
+    let (status, status_color) = match (replay.mode(), replay.desynced_at()) {
+        (ReplayMode::Recording, _) => (String::new(), Color::WHITE),
+        (ReplayMode::Playback, None) => ("Replay".to_string(), Color::WHITE),
+        (ReplayMode::Playback, Some(tick)) => (
+            format!("Replay desynced at tick {tick}"),
+            Color::srgb(1.0, 0.35, 0.35),
+        ),
+    };
+    if text.0 != status {
+        text.0 = status;
+        color.0 = status_color;
+    }
 }
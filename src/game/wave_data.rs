@@ -0,0 +1,168 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::enemy::EnemyCatalog;
+use super::waves::{WaveKeyframe, WaveTimeline, check_timeline_prototypes};
+
+pub const WAVE_TIMELINE_PATH: &str = "data/timeline.waves.ron";
+
+/// Validated wave keyframes loaded from a `*.waves.ron` file.
+#[derive(Asset, TypePath, Debug)]
+pub struct WaveTimelineAsset {
+    pub keyframes: Vec<WaveKeyframe>,
+}
+
+#[derive(Resource)]
+pub struct WaveTimelineHandle(pub Handle<WaveTimelineAsset>);
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WaveTimelineFile {
+    keyframes: Vec<WaveKeyframe>,
+}
+
+#[derive(Debug, Error)]
+pub enum WaveTimelineError {
+    #[error("could not read wave timeline: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse wave timeline: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("wave timeline must define at least one keyframe")]
+    Empty,
+    #[error("keyframe #{index}: `time` must come after the previous keyframe's")]
+    Unordered { index: usize },
+    #[error("keyframe #{index}: `{field}` {reason}")]
+    InvalidField {
+        index: usize,
+        field: &'static str,
+        reason: &'static str,
+    },
+    #[error("keyframe #{index}: weight multiplier of `{name}` must be finite and non-negative")]
+    InvalidWeight { index: usize, name: String },
+}
+
+fn validate_keyframe(index: usize, keyframe: &WaveKeyframe) -> Result<(), WaveTimelineError> {
+    let invalid = |field, reason| WaveTimelineError::InvalidField {
+        index,
+        field,
+        reason,
+    };
+
+    if !(keyframe.time.is_finite() && keyframe.time >= 0.0) {
+        return Err(invalid("time", "must be a finite, non-negative number"));
+    }
+    if !(keyframe.spawn_interval.is_finite() && keyframe.spawn_interval > 0.0) {
+        return Err(invalid(
+            "spawn_interval",
+            "must be a finite number greater than zero",
+        ));
+    }
+    if !(keyframe.batch_size.is_finite() && keyframe.batch_size >= 1.0) {
+        return Err(invalid(
+            "batch_size",
+            "must be a finite number of at least 1",
+        ));
+    }
+    if !(keyframe.health_multiplier.is_finite() && keyframe.health_multiplier > 0.0) {
+        return Err(invalid(
+            "health_multiplier",
+            "must be a finite number greater than zero",
+        ));
+    }
+    if !(keyframe.speed_multiplier.is_finite() && keyframe.speed_multiplier >= 0.0) {
+        return Err(invalid(
+            "speed_multiplier",
+            "must be a finite, non-negative number",
+        ));
+    }
+    if !(keyframe.damage_multiplier.is_finite() && keyframe.damage_multiplier >= 0.0) {
+        return Err(invalid(
+            "damage_multiplier",
+            "must be a finite, non-negative number",
+        ));
+    }
+    for (name, multiplier) in &keyframe.weight_multipliers {
+        if !(multiplier.is_finite() && *multiplier >= 0.0) {
+            return Err(WaveTimelineError::InvalidWeight {
+                index,
+                name: name.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[derive(Default, TypePath)]
+pub struct WaveTimelineLoader;
+
+impl AssetLoader for WaveTimelineLoader {
+    type Asset = WaveTimelineAsset;
+    type Settings = ();
+    type Error = WaveTimelineError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file: WaveTimelineFile = ron::de::from_bytes(&bytes)?;
+
+        if file.keyframes.is_empty() {
+            return Err(WaveTimelineError::Empty);
+        }
+        for (index, keyframe) in file.keyframes.iter().enumerate() {
+            validate_keyframe(index, keyframe)?;
+            if index > 0 && keyframe.time <= file.keyframes[index - 1].time {
+                return Err(WaveTimelineError::Unordered { index });
+            }
+        }
+
+        Ok(WaveTimelineAsset {
+            keyframes: file.keyframes,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["waves.ron"]
+    }
+}
+
+pub fn load_wave_timeline(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(WaveTimelineHandle(asset_server.load(WAVE_TIMELINE_PATH)));
+}
+
+/// Copies the timeline asset into [`WaveTimeline`] whenever it is (re)loaded. A file that fails
+/// validation leaves the previous timeline in place.
+pub fn sync_wave_timeline(
+    mut asset_events: MessageReader<AssetEvent<WaveTimelineAsset>>,
+    assets: Res<Assets<WaveTimelineAsset>>,
+    handle: Res<WaveTimelineHandle>,
+    mut timeline: ResMut<WaveTimeline>,
+    catalog: Res<EnemyCatalog>,
+) {
+    for event in asset_events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = *event
+        else {
+            continue;
+        };
+        if id != handle.0.id() {
+            continue;
+        }
+        let Some(asset) = assets.get(id) else {
+            continue;
+        };
+        *timeline = WaveTimeline::from_keyframes(asset.keyframes.clone());
+        info!(
+            "Loaded {} wave keyframes from {}",
+            timeline.len(),
+            WAVE_TIMELINE_PATH
+        );
+        check_timeline_prototypes(&timeline, &catalog);
+    }
+}
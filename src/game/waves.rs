@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::Difficulty;
+
+use super::constants::ENEMY_SPAWN_INTERVAL;
+use super::enemy::{EnemyAttributes, EnemyCatalog};
+
+/// A point on the wave timeline. Numeric values are interpolated linearly between keyframes,
+/// spawn weights switch over once a keyframe is reached.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WaveKeyframe {
+    /// Run time in seconds at which this keyframe is fully in effect.
+    pub time: f32,
+    pub spawn_interval: f32,
+    pub batch_size: f32,
+    pub health_multiplier: f32,
+    pub speed_multiplier: f32,
+    pub damage_multiplier: f32,
+    /// Spawn weight multipliers by prototype name. Unlisted prototypes keep their catalog weight.
+    #[serde(default)]
+    pub weight_multipliers: BTreeMap<String, f32>,
+}
+
+/// Sampled until the timeline has loaded: the base spawn rate with unscaled enemies.
+static BASE_KEYFRAME: WaveKeyframe = WaveKeyframe {
+    time: 0.0,
+    spawn_interval: ENEMY_SPAWN_INTERVAL,
+    batch_size: 1.0,
+    health_multiplier: 1.0,
+    speed_multiplier: 1.0,
+    damage_multiplier: 1.0,
+    weight_multipliers: BTreeMap::new(),
+};
+
+/// Keyframes loaded from `data/timeline.waves.ron`, ordered by time. Empty until it has loaded.
+#[derive(Resource, Default)]
+pub struct WaveTimeline {
+    keyframes: Vec<WaveKeyframe>,
+}
+
+impl WaveTimeline {
+    pub fn from_keyframes(keyframes: Vec<WaveKeyframe>) -> Self {
+        Self { keyframes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keyframes.len()
+    }
+}
+
+/// Warns about timeline weight multipliers naming a prototype the catalog doesn't have, since
+/// those silently do nothing. Called whenever either of them (re)loads, once both have.
+pub fn check_timeline_prototypes(timeline: &WaveTimeline, catalog: &EnemyCatalog) {
+    if timeline.is_empty() || catalog.is_empty() {
+        return;
+    }
+    for keyframe in &timeline.keyframes {
+        for name in keyframe.weight_multipliers.keys() {
+            if catalog.get(name).is_none() {
+                warn!(
+                    "Wave keyframe at {}s scales the weight of unknown enemy prototype `{name}`",
+                    keyframe.time
+                );
+            }
+        }
+    }
+}
+
+/// The wave parameters in effect at a given moment of a run.
+#[derive(Clone, Copy, Debug)]
+pub struct Wave<'a> {
+    pub spawn_interval: f32,
+    pub batch_size: u32,
+    pub health_multiplier: f32,
+    pub speed_multiplier: f32,
+    pub damage_multiplier: f32,
+    pub weight_multipliers: &'a BTreeMap<String, f32>,
+}
+
+impl Wave<'_> {
+    pub fn weight_multiplier(&self, prototype_name: &str) -> f32 {
+        self.weight_multipliers
+            .get(prototype_name)
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    pub fn scale_attributes(&self, attributes: EnemyAttributes) -> EnemyAttributes {
+        EnemyAttributes {
+            health: ((attributes.health as f32 * self.health_multiplier).round() as i32).max(1),
+            speed: attributes.speed * self.speed_multiplier,
+            damage: (attributes.damage as f32 * self.damage_multiplier).round() as i32,
+            ..attributes
+        }
+    }
+}
+
+/// Drives enemy spawning over the course of a run by walking the [`WaveTimeline`].
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct WaveDirector {
+    elapsed: f32,
+    spawn_timer: Timer,
+    difficulty: Difficulty,
+}
+
+impl WaveDirector {
+    /// The spawn interval is taken from the timeline on every tick, so the timer starts with
+    /// the base one.
+    pub fn new(difficulty: Difficulty) -> Self {
+        Self {
+            elapsed: 0.0,
+            spawn_timer: Timer::from_seconds(ENEMY_SPAWN_INTERVAL, TimerMode::Repeating),
+            difficulty,
+        }
+    }
+
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    /// Advances the run clock and returns how many spawn batches are due this tick.
+    pub fn tick(&mut self, delta: Duration, timeline: &WaveTimeline) -> u32 {
+        self.elapsed += delta.as_secs_f32();
+        let interval = self.wave(timeline).spawn_interval;
+        self.spawn_timer
+            .set_duration(Duration::from_secs_f32(interval));
+        self.spawn_timer.tick(delta).times_finished_this_tick()
+    }
+
+    /// Samples the timeline at the current run time. Harder difficulties move through the
+    /// timeline faster and spawn more often.
+    pub fn wave<'a>(&self, timeline: &'a WaveTimeline) -> Wave<'a> {
+        let time = self.elapsed * self.difficulty.wave_time_scale();
+        let keyframes = &timeline.keyframes;
+        let next_index = keyframes
+            .iter()
+            .position(|keyframe| keyframe.time > time)
+            .unwrap_or(keyframes.len());
+        let current = keyframes
+            .get(next_index.saturating_sub(1))
+            .unwrap_or(&BASE_KEYFRAME);
+        let next = keyframes.get(next_index).unwrap_or(current);
+
+        let t = if next.time > current.time {
+            ((time - current.time) / (next.time - current.time)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+
+        Wave {
+            spawn_interval: self
+                .difficulty
+                .enemy_spawn_interval(lerp(current.spawn_interval, next.spawn_interval)),
+            batch_size: lerp(current.batch_size, next.batch_size).floor().max(1.0) as u32,
+            health_multiplier: lerp(current.health_multiplier, next.health_multiplier),
+            speed_multiplier: lerp(current.speed_multiplier, next.speed_multiplier),
+            damage_multiplier: lerp(current.damage_multiplier, next.damage_multiplier),
+            weight_multipliers: &current.weight_multipliers,
+        }
+    }
+}
@@ -0,0 +1,586 @@
+use std::collections::HashSet;
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::arena::ArenaMap;
+use super::components::{Bomb, BombExplosion, LevelEntity, Lifetime, Projectile, Velocity};
+use super::constants::{
+    BOMB_EXPLOSION_DURATION, BOMB_EXPLOSION_RADIUS, BOMB_FUSE, BOMB_INTERVAL, FIRE_RATE,
+    PROJECTILE_SPEED,
+};
+use super::enemy::Enemy;
+use super::events::DamageEnemy;
+use super::player::Player;
+use super::powerup::PlayerUpgrades;
+use super::replay::AimInput;
+use super::resources::{BombSound, ShootSound};
+use super::rng::RunRng;
+use super::spatial::SpatialIndex;
+use crate::audio::{SEVolume, spawn_se};
+use crate::{DisplayQuality, MainState};
+
+pub const MAX_WEAPON_LEVEL: u32 = 8;
+/// How many different weapons the player can carry at once.
+pub const MAX_WEAPONS: usize = 6;
+const COOLDOWN_MULTIPLIER: f32 = 0.9;
+const GUN_SPEED_INCREMENT: f32 = 0.1;
+const GUN_PROJECTILE_LIFETIME: f32 = 1.2;
+const BOMB_RADIUS_INCREMENT: f32 = 0.15;
+const BLADE_SIZE: Vec2 = Vec2::new(18.0, 6.0);
+const BLADE_COLOR: Color = Color::srgb(0.75, 0.9, 1.0);
+const BEAM_COLOR: Color = Color::srgba(0.5, 1.0, 0.9, 0.8);
+const BEAM_DURATION: f32 = 0.15;
+const LIGHTNING_COLOR: Color = Color::srgba(0.8, 0.8, 1.0, 0.9);
+const LIGHTNING_WIDTH: f32 = 3.0;
+const LIGHTNING_DURATION: f32 = 0.12;
+/// Damage multiplier applied on every jump of a lightning chain.
+const LIGHTNING_FALLOFF: f32 = 0.75;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WeaponKind {
+    /// Shoots at the nearest enemy, or where the player aims with manual aim.
+    Gun,
+    /// Drops a bomb at the player's feet that explodes after a fuse.
+    Bomb,
+    /// Blades circling the player that cut enemies they touch.
+    Blades,
+    /// An instant beam that pierces every enemy along its line, aimed like the gun.
+    Beam,
+    /// A bolt that strikes the nearest enemy and jumps on to enemies next to it.
+    Lightning,
+}
+
+pub const ALL_WEAPON_KINDS: [WeaponKind; 5] = [
+    WeaponKind::Gun,
+    WeaponKind::Bomb,
+    WeaponKind::Blades,
+    WeaponKind::Beam,
+    WeaponKind::Lightning,
+];
+pub const STARTING_WEAPONS: [WeaponKind; 2] = [WeaponKind::Gun, WeaponKind::Bomb];
+
+/// What a weapon does at a given level. Fields a weapon has no use for are left at zero.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WeaponStats {
+    /// Seconds between attacks. For blades, how long an enemy is safe after being cut.
+    pub cooldown: f32,
+    pub damage: i32,
+    /// Bullet speed, or the blades' angular speed in radians per second.
+    pub speed: f32,
+    /// Blast radius, blade orbit radius, beam width or lightning jump distance.
+    pub area: f32,
+    /// Beam length, or how far away lightning can find its first target.
+    pub range: f32,
+    /// Number of blades, or lightning jumps after the first strike.
+    pub count: u32,
+}
+
+impl WeaponKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            WeaponKind::Gun => "Gun",
+            WeaponKind::Bomb => "Bomb",
+            WeaponKind::Blades => "Orbiting Blades",
+            WeaponKind::Beam => "Piercing Beam",
+            WeaponKind::Lightning => "Chain Lightning",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            WeaponKind::Gun => "Fires a bullet at the nearest enemy, or where you aim.",
+            WeaponKind::Bomb => "Drops a bomb that blasts everything around it.",
+            WeaponKind::Blades => "Blades circle you and cut any enemy they touch.",
+            WeaponKind::Beam => "Fires a beam that pierces every enemy in its path.",
+            WeaponKind::Lightning => "Strikes the nearest enemy and jumps to others nearby.",
+        }
+    }
+
+    pub fn level_up_description(&self) -> &'static str {
+        match self {
+            WeaponKind::Gun => "Shoots more often, and bullets hit harder and fly faster.",
+            WeaponKind::Bomb => "Drops bombs more often with a wider blast.",
+            WeaponKind::Blades => "More blades that spin faster on a wider orbit.",
+            WeaponKind::Beam => "Fires more often with a longer, wider and stronger beam.",
+            WeaponKind::Lightning => "Strikes more often, harder and jumps further.",
+        }
+    }
+
+    pub fn stats(&self, level: u32) -> WeaponStats {
+        let steps = level.saturating_sub(1);
+        let cooldown_scale = COOLDOWN_MULTIPLIER.powi(steps as i32);
+        match self {
+            WeaponKind::Gun => WeaponStats {
+                cooldown: FIRE_RATE * cooldown_scale,
+                damage: 1 + (steps / 2) as i32,
+                speed: PROJECTILE_SPEED * (1.0 + GUN_SPEED_INCREMENT * steps as f32),
+                ..default()
+            },
+            WeaponKind::Bomb => WeaponStats {
+                cooldown: BOMB_INTERVAL * cooldown_scale,
+                damage: 1,
+                area: BOMB_EXPLOSION_RADIUS * (1.0 + BOMB_RADIUS_INCREMENT * steps as f32),
+                ..default()
+            },
+            WeaponKind::Blades => WeaponStats {
+                cooldown: 0.6,
+                damage: 1 + (steps / 3) as i32,
+                speed: 3.0 + 0.2 * steps as f32,
+                area: 70.0 + 5.0 * steps as f32,
+                count: 2 + steps / 2,
+                ..default()
+            },
+            WeaponKind::Beam => WeaponStats {
+                cooldown: 2.0 * cooldown_scale,
+                damage: 2 + (steps / 2) as i32,
+                area: 10.0 + 2.0 * steps as f32,
+                range: 420.0 + 30.0 * steps as f32,
+                ..default()
+            },
+            WeaponKind::Lightning => WeaponStats {
+                cooldown: 1.5 * cooldown_scale,
+                damage: 3 + (steps / 2) as i32,
+                area: 140.0 + 10.0 * steps as f32,
+                range: 320.0,
+                count: 2 + steps / 2,
+                ..default()
+            },
+        }
+    }
+}
+
+/// A weapon carried by the player. Weapons are standalone level entities rather than children
+/// of the player, so the loadout outlives the player for the game-over summary.
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct Weapon {
+    pub kind: WeaponKind,
+    pub level: u32,
+    pub cooldown: Timer,
+}
+
+impl Weapon {
+    pub fn new(kind: WeaponKind) -> Self {
+        Self {
+            kind,
+            level: 1,
+            cooldown: Timer::from_seconds(kind.stats(1).cooldown, TimerMode::Repeating),
+        }
+    }
+
+    pub fn stats(&self) -> WeaponStats {
+        self.kind.stats(self.level)
+    }
+
+    pub fn is_max_level(&self) -> bool {
+        self.level >= MAX_WEAPON_LEVEL
+    }
+
+    pub fn level_up(&mut self) {
+        self.level = (self.level + 1).min(MAX_WEAPON_LEVEL);
+        let cooldown = self.stats().cooldown;
+        self.cooldown
+            .set_duration(Duration::from_secs_f32(cooldown));
+    }
+}
+
+/// Runtime state of a [`WeaponKind::Blades`] weapon.
+#[derive(Component, Default)]
+pub struct BladeOrbit {
+    angle: f32,
+    /// Seconds until each recently cut enemy can be cut again.
+    hit_cooldowns: HashMap<Entity, f32>,
+}
+
+/// One of the sprites of a [`WeaponKind::Blades`] weapon, positioned by `update_blades`.
+#[derive(Component)]
+pub struct OrbitingBlade {
+    slot: u32,
+}
+
+pub fn spawn_weapon(commands: &mut Commands, kind: WeaponKind) {
+    restore_weapon(commands, Weapon::new(kind));
+}
+
+/// Spawns `weapon` with the level and cooldown it already has, as when resuming a saved run.
+pub fn restore_weapon(commands: &mut Commands, weapon: Weapon) {
+    let kind = weapon.kind;
+    let mut weapon = commands.spawn((
+        DespawnOnExit(MainState::Game),
+        LevelEntity,
+        Name::new(kind.label()),
+        weapon,
+    ));
+    match kind {
+        WeaponKind::Blades => {
+            weapon.insert(BladeOrbit::default());
+        }
+        WeaponKind::Gun | WeaponKind::Bomb | WeaponKind::Beam | WeaponKind::Lightning => {}
+    }
+}
+
+/// Advances every weapon's cooldown. Firing systems run afterwards and act on
+/// `just_finished`.
+pub fn tick_weapon_cooldowns(time: Res<Time>, mut weapons: Query<&mut Weapon>) {
+    for mut weapon in &mut weapons {
+        weapon.cooldown.tick(time.delta());
+    }
+}
+
+pub fn fire_guns(
+    mut commands: Commands,
+    weapons: Query<&Weapon>,
+    player: Single<&Transform, With<Player>>,
+    enemies: Query<(), With<Enemy>>,
+    index: Res<SpatialIndex>,
+    shoot_sound: Res<ShootSound>,
+    se_volume: Res<SEVolume>,
+    mut run_rng: ResMut<RunRng>,
+    aim: Res<AimInput>,
+    upgrades: Res<PlayerUpgrades>,
+) {
+    for weapon in &weapons {
+        if weapon.kind != WeaponKind::Gun || !weapon.cooldown.just_finished() {
+            continue;
+        }
+        let stats = weapon.stats();
+
+        let origin = player.translation.xy();
+        // Enemies killed earlier this tick are still in the index until the next rebuild.
+        let dir = aim
+            .direction()
+            .or_else(|| {
+                index
+                    .enemies
+                    .nearest(origin, |entry| enemies.contains(entry.entity))
+                    .map(|target| (target.position - origin).normalize_or_zero())
+                    .filter(|dir| dir.length_squared() > 0.0)
+            })
+            .unwrap_or_else(|| {
+                let angle = run_rng.gameplay().random_range(0.0..TAU);
+                Vec2::new(angle.cos(), angle.sin())
+            });
+        let (damage, crit) = upgrades.roll_damage(stats.damage, run_rng.gameplay());
+
+        commands.spawn((
+            DespawnOnExit(MainState::Game),
+            LevelEntity,
+            Sprite {
+                color: Color::srgba(1.0, 1.0, 0.0, 0.8),
+                custom_size: Some(Vec2::new(12.0, 6.0)),
+                ..default()
+            },
+            Transform::from_translation(player.translation + Vec3::new(0.0, 0.0, 1.0))
+                .with_rotation(Quat::from_rotation_z(dir.y.atan2(dir.x))),
+            Projectile { damage, crit },
+            Velocity(dir * stats.speed),
+            Lifetime {
+                timer: Timer::from_seconds(GUN_PROJECTILE_LIFETIME, TimerMode::Once),
+            },
+        ));
+        spawn_se(&mut commands, &se_volume, &shoot_sound.0);
+    }
+}
+
+pub fn place_bombs(
+    mut commands: Commands,
+    weapons: Query<&Weapon>,
+    player: Single<&Transform, With<Player>>,
+    upgrades: Res<PlayerUpgrades>,
+    mut run_rng: ResMut<RunRng>,
+) {
+    for weapon in &weapons {
+        if weapon.kind != WeaponKind::Bomb || !weapon.cooldown.just_finished() {
+            continue;
+        }
+        let stats = weapon.stats();
+        let (damage, crit) = upgrades.roll_damage(stats.damage, run_rng.gameplay());
+
+        spawn_bomb(
+            &mut commands,
+            player.translation.xy(),
+            Bomb {
+                timer: Timer::from_seconds(BOMB_FUSE, TimerMode::Once),
+                blink_timer: Timer::from_seconds(0.2, TimerMode::Repeating),
+                radius: stats.area,
+                damage,
+                crit,
+                visible: true,
+            },
+        );
+    }
+}
+
+/// Spawns `bomb` at `position`, with its fuse as far along as it already is.
+pub fn spawn_bomb(commands: &mut Commands, position: Vec2, bomb: Bomb) {
+    let alpha = if bomb.visible { 0.9 } else { 0.4 };
+    commands.spawn((
+        DespawnOnExit(MainState::Game),
+        LevelEntity,
+        Sprite::from_color(Color::srgba(1.0, 0.45, 0.1, alpha), Vec2::splat(24.0)),
+        Transform::from_translation(position.extend(0.4)),
+        bomb,
+    ));
+}
+
+pub fn update_bombs(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut bombs: Query<(Entity, &Transform, &mut Sprite, &mut Bomb)>,
+    bomb_sound: Res<BombSound>,
+    se_volume: Res<SEVolume>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    quality: Res<DisplayQuality>,
+) {
+    for (entity, transform, mut sprite, mut bomb) in &mut bombs {
+        if bomb.blink_timer.tick(time.delta()).just_finished() {
+            bomb.visible = !bomb.visible;
+            if quality.flashes() {
+                sprite.color.set_alpha(if bomb.visible { 0.9 } else { 0.4 });
+            }
+        }
+
+        if bomb.timer.tick(time.delta()).just_finished() {
+            let position = transform.translation;
+            commands.entity(entity).despawn();
+            commands.spawn((
+                DespawnOnExit(MainState::Game),
+                LevelEntity,
+                Mesh2d(meshes.add(Circle {
+                    radius: bomb.radius,
+                })),
+                MeshMaterial2d(materials.add(Color::srgba(1.0, 0.6, 0.2, 0.35))),
+                Transform::from_translation(Vec3::new(position.x, position.y, 0.5)),
+                BombExplosion {
+                    radius: bomb.radius,
+                    damage: bomb.damage,
+                    crit: bomb.crit,
+                },
+                Lifetime {
+                    timer: Timer::from_seconds(BOMB_EXPLOSION_DURATION, TimerMode::Once),
+                },
+            ));
+            spawn_se(&mut commands, &se_volume, &bomb_sound.0);
+        }
+    }
+}
+
+fn blade_position(center: Vec2, angle: f32, radius: f32, slot: u32, count: u32) -> Vec2 {
+    let angle = angle + TAU * slot as f32 / count.max(1) as f32;
+    center + Vec2::new(angle.cos(), angle.sin()) * radius
+}
+
+pub fn update_blades(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut weapons: Query<(&Weapon, &mut BladeOrbit)>,
+    mut blades: Query<(Entity, &OrbitingBlade, &mut Transform)>,
+    player: Single<&Transform, (With<Player>, Without<OrbitingBlade>)>,
+    enemies: Query<(), With<Enemy>>,
+    index: Res<SpatialIndex>,
+    mut damage_messages: MessageWriter<DamageEnemy>,
+) {
+    let Some((weapon, mut orbit)) = weapons.iter_mut().next() else {
+        return;
+    };
+    let stats = weapon.stats();
+    let delta = time.delta_secs();
+    let center = player.translation.xy();
+
+    orbit.angle = (orbit.angle + stats.speed * delta) % TAU;
+    orbit.hit_cooldowns.retain(|_, remaining| {
+        *remaining -= delta;
+        *remaining > 0.0
+    });
+
+    // Respawn the sprites whenever a level up changes the blade count.
+    if blades.iter().count() != stats.count as usize {
+        for (entity, ..) in &blades {
+            commands.entity(entity).despawn();
+        }
+        for slot in 0..stats.count {
+            let position = blade_position(center, orbit.angle, stats.area, slot, stats.count);
+            commands.spawn((
+                DespawnOnExit(MainState::Game),
+                LevelEntity,
+                OrbitingBlade { slot },
+                Sprite::from_color(BLADE_COLOR, BLADE_SIZE),
+                Transform::from_translation(position.extend(1.0)),
+            ));
+        }
+    } else {
+        for (_, blade, mut transform) in &mut blades {
+            let position = blade_position(center, orbit.angle, stats.area, blade.slot, stats.count);
+            let tangent = position - center;
+            transform.translation = position.extend(1.0);
+            transform.rotation = Quat::from_rotation_z(tangent.y.atan2(tangent.x));
+        }
+    }
+
+    let blade_radius = BLADE_SIZE.max_element() * 0.5;
+    for slot in 0..stats.count {
+        let position = blade_position(center, orbit.angle, stats.area, slot, stats.count);
+        let reach = blade_radius + index.enemies.max_radius();
+        for candidate in index.enemies.candidates(position, reach) {
+            if orbit.hit_cooldowns.contains_key(&candidate.entity)
+                || position.distance(candidate.position) > blade_radius + candidate.radius
+                || !enemies.contains(candidate.entity)
+            {
+                continue;
+            }
+            orbit.hit_cooldowns.insert(candidate.entity, stats.cooldown);
+            damage_messages.write(DamageEnemy {
+                target: candidate.entity,
+                amount: stats.damage,
+                on_hit: true,
+            });
+        }
+    }
+}
+
+pub fn fire_beams(
+    mut commands: Commands,
+    weapons: Query<&Weapon>,
+    player: Single<&Transform, With<Player>>,
+    enemies: Query<(), With<Enemy>>,
+    index: Res<SpatialIndex>,
+    mut damage_messages: MessageWriter<DamageEnemy>,
+    shoot_sound: Res<ShootSound>,
+    se_volume: Res<SEVolume>,
+    aim: Res<AimInput>,
+    map: Res<ArenaMap>,
+) {
+    for weapon in &weapons {
+        if weapon.kind != WeaponKind::Beam || !weapon.cooldown.just_finished() {
+            continue;
+        }
+        let stats = weapon.stats();
+        let origin = player.translation.xy();
+        // Without manual aim the beam waits for something to hit.
+        let Some(dir) = aim.direction().or_else(|| {
+            index
+                .enemies
+                .nearest(origin, |entry| enemies.contains(entry.entity))
+                .map(|target| (target.position - origin).normalize_or(Vec2::X))
+        }) else {
+            continue;
+        };
+        // Obstacles stop the beam.
+        let range = map.ray_distance(origin, dir, stats.range);
+        let end = origin + dir * range;
+
+        let half_width = stats.area * 0.5;
+        let midpoint = (origin + end) * 0.5;
+        let reach = range * 0.5 + half_width + index.enemies.max_radius();
+        for candidate in index.enemies.candidates(midpoint, reach) {
+            let distance = distance_to_segment(candidate.position, origin, end);
+            if distance > half_width + candidate.radius || !enemies.contains(candidate.entity) {
+                continue;
+            }
+            damage_messages.write(DamageEnemy {
+                target: candidate.entity,
+                amount: stats.damage,
+                on_hit: true,
+            });
+        }
+
+        spawn_line(
+            &mut commands,
+            origin,
+            end,
+            stats.area,
+            BEAM_COLOR,
+            BEAM_DURATION,
+        );
+        spawn_se(&mut commands, &se_volume, &shoot_sound.0);
+    }
+}
+
+pub fn fire_lightning(
+    mut commands: Commands,
+    weapons: Query<&Weapon>,
+    player: Single<&Transform, With<Player>>,
+    enemies: Query<(), With<Enemy>>,
+    index: Res<SpatialIndex>,
+    mut damage_messages: MessageWriter<DamageEnemy>,
+    shoot_sound: Res<ShootSound>,
+    se_volume: Res<SEVolume>,
+) {
+    for weapon in &weapons {
+        if weapon.kind != WeaponKind::Lightning || !weapon.cooldown.just_finished() {
+            continue;
+        }
+        let stats = weapon.stats();
+
+        let mut struck: HashSet<Entity> = HashSet::new();
+        let mut from = player.translation.xy();
+        let mut reach = stats.range;
+        let mut damage = stats.damage as f32;
+        for _ in 0..=stats.count {
+            let Some(target) = index.enemies.nearest(from, |entry| {
+                !struck.contains(&entry.entity) && enemies.contains(entry.entity)
+            }) else {
+                break;
+            };
+            if from.distance(target.position) > reach {
+                break;
+            }
+
+            struck.insert(target.entity);
+            damage_messages.write(DamageEnemy {
+                target: target.entity,
+                amount: (damage.round() as i32).max(1),
+                on_hit: true,
+            });
+            spawn_line(
+                &mut commands,
+                from,
+                target.position,
+                LIGHTNING_WIDTH,
+                LIGHTNING_COLOR,
+                LIGHTNING_DURATION,
+            );
+
+            from = target.position;
+            reach = stats.area;
+            damage *= LIGHTNING_FALLOFF;
+        }
+        if !struck.is_empty() {
+            spawn_se(&mut commands, &se_volume, &shoot_sound.0);
+        }
+    }
+}
+
+fn distance_to_segment(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let t =
+        ((point - start).dot(segment) / segment.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+    point.distance(start + segment * t)
+}
+
+/// A short-lived straight line used to draw beams and lightning.
+fn spawn_line(
+    commands: &mut Commands,
+    from: Vec2,
+    to: Vec2,
+    width: f32,
+    color: Color,
+    duration: f32,
+) {
+    let offset = to - from;
+    commands.spawn((
+        DespawnOnExit(MainState::Game),
+        LevelEntity,
+        Sprite::from_color(color, Vec2::new(offset.length(), width)),
+        Transform::from_translation(((from + to) * 0.5).extend(1.5))
+            .with_rotation(Quat::from_rotation_z(offset.y.atan2(offset.x))),
+        Lifetime {
+            timer: Timer::from_seconds(duration, TimerMode::Once),
+        },
+    ));
+}
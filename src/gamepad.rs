@@ -0,0 +1,236 @@
+//! Gamepad support shared by every screen: the directions the sticks point in, and
+//! face-button navigation of whatever buttons are on screen.
+//!
+//! Screens don't need to know about the gamepad. The focused button gets an outline, and
+//! confirming presses it by setting its `Interaction` for a frame, exactly as a mouse click
+//! would, so the existing `Changed<Interaction>` handlers pick it up.
+
+use bevy::prelude::*;
+use bevy::ui::UiSystems;
+
+use crate::controls::{ActionInput, InputAction, not_rebinding};
+
+/// Stick deflection below this is ignored. Above it, the stick is rescaled to cover 0..1 so
+/// movement speed follows how far it is pushed.
+const STICK_DEADZONE: f32 = 0.2;
+/// Stick deflection that counts as a step when navigating menus.
+const STICK_NAVIGATION_THRESHOLD: f32 = 0.6;
+/// How much sideways distance counts against a candidate when moving focus.
+const NAVIGATION_SIDEWAYS_PENALTY: f32 = 2.0;
+const FOCUS_OUTLINE_COLOR: Color = Color::srgb(1.0, 0.85, 0.3);
+
+/// The button the gamepad is on.
+#[derive(Component)]
+pub struct GamepadFocus;
+
+/// Button focused first when a screen opens, instead of its top-left one.
+#[derive(Component)]
+pub struct DefaultFocus;
+
+/// Button pressed by the Back action on the screen it belongs to.
+#[derive(Component)]
+pub struct CancelButton;
+
+/// A field that takes typed keys. While one is on screen the keyboard's Back key edits it
+/// instead of pressing the `CancelButton`; the gamepad's Back button still does.
+#[derive(Component)]
+pub struct TextEntry;
+
+/// A button `navigate_buttons` can move to, in UI coordinates.
+struct Candidate {
+    entity: Entity,
+    position: Vec2,
+    focused: bool,
+    default_focus: bool,
+    cancel: bool,
+}
+
+type NavigableButtons<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Interaction,
+        &'static UiGlobalTransform,
+        &'static ComputedNode,
+        &'static InheritedVisibility,
+        Has<GamepadFocus>,
+        Has<DefaultFocus>,
+        Has<CancelButton>,
+    ),
+    With<Button>,
+>;
+
+/// Button pressed through the gamepad last frame, released again this frame.
+#[derive(Resource, Default)]
+struct PadPress(Option<Entity>);
+
+pub fn plugin(app: &mut App) {
+    app.init_resource::<PadPress>().add_systems(
+        PreUpdate,
+        navigate_buttons
+            .after(UiSystems::Focus)
+            .run_if(not_rebinding),
+    );
+}
+
+/// Movement direction from the left stick. Its length is kept, so a half-pushed stick walks
+/// at half speed.
+pub fn stick_direction(gamepad: &Gamepad) -> Vec2 {
+    let stick = gamepad.left_stick();
+    let length = stick.length();
+    if length <= STICK_DEADZONE {
+        return Vec2::ZERO;
+    }
+    let scaled = ((length - STICK_DEADZONE) / (1.0 - STICK_DEADZONE)).min(1.0);
+    stick / length * scaled
+}
+
+/// Aiming direction from the right stick, or `None` while it is centred.
+pub fn aim_stick_direction(gamepad: &Gamepad) -> Option<Vec2> {
+    let stick = gamepad.right_stick();
+    (stick.length() > STICK_DEADZONE).then(|| stick.normalize())
+}
+
+fn navigate_buttons(
+    mut commands: Commands,
+    input: ActionInput,
+    mut buttons: NavigableButtons,
+    text_entries: Query<(), With<TextEntry>>,
+    mut pad_press: ResMut<PadPress>,
+    mut stick_held: Local<bool>,
+) {
+    if let Some(entity) = pad_press.0.take()
+        && let Ok((_, mut interaction, ..)) = buttons.get_mut(entity)
+        && *interaction == Interaction::Pressed
+    {
+        *interaction = Interaction::None;
+    }
+
+    // Confirm only comes from the gamepad: the keyboard's confirm key already has a meaning
+    // on each screen and doesn't follow the gamepad's focus.
+    let confirm = input.button_just_pressed(InputAction::Confirm);
+    let cancel = input.button_just_pressed(InputAction::Back)
+        || (text_entries.is_empty() && input.key_just_pressed(InputAction::Back));
+    let mut step = Vec2::ZERO;
+    let mut stick = Vec2::ZERO;
+    let mut pads_connected = false;
+    for gamepad in input.gamepads() {
+        pads_connected = true;
+        for (button, direction) in [
+            (GamepadButton::DPadUp, Vec2::NEG_Y),
+            (GamepadButton::DPadDown, Vec2::Y),
+            (GamepadButton::DPadLeft, Vec2::NEG_X),
+            (GamepadButton::DPadRight, Vec2::X),
+        ] {
+            if gamepad.just_pressed(button) {
+                step = direction;
+            }
+        }
+        if stick == Vec2::ZERO {
+            stick = gamepad.left_stick();
+        }
+    }
+    // The stick steps once per push, like a D-pad press. UI space points down, the stick up.
+    if stick.length() > STICK_NAVIGATION_THRESHOLD {
+        if !*stick_held {
+            step = if stick.x.abs() > stick.y.abs() {
+                Vec2::new(stick.x.signum(), 0.0)
+            } else {
+                Vec2::new(0.0, -stick.y.signum())
+            };
+        }
+        *stick_held = true;
+    } else {
+        *stick_held = false;
+    }
+
+    // Buttons that are on screen and laid out, in reading order.
+    let mut candidates: Vec<Candidate> = buttons
+        .iter()
+        .filter(|(_, _, _, computed, visibility, ..)| {
+            visibility.get() && computed.size() != Vec2::ZERO
+        })
+        .map(
+            |(entity, _, transform, _, _, focused, default_focus, cancel)| Candidate {
+                entity,
+                position: transform.translation,
+                focused,
+                default_focus,
+                cancel,
+            },
+        )
+        .collect();
+    if cancel && let Some(candidate) = candidates.iter().find(|candidate| candidate.cancel) {
+        press(&mut buttons, &mut pad_press, candidate.entity);
+        return;
+    }
+    if candidates.is_empty() || !pads_connected {
+        return;
+    }
+    candidates.sort_by(|a, b| {
+        (a.position.y, a.position.x)
+            .partial_cmp(&(b.position.y, b.position.x))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let Some(&Candidate {
+        entity: current,
+        position,
+        ..
+    }) = candidates.iter().find(|candidate| candidate.focused)
+    else {
+        // Nothing is focused yet on this screen.
+        let first = candidates
+            .iter()
+            .find(|candidate| candidate.default_focus)
+            .unwrap_or(&candidates[0]);
+        focus(&mut commands, None, first.entity);
+        return;
+    };
+
+    if step != Vec2::ZERO {
+        let next = candidates
+            .iter()
+            .filter(|candidate| candidate.entity != current)
+            .filter_map(|candidate| {
+                let offset = candidate.position - position;
+                let forward = offset.dot(step);
+                (forward > 0.0).then(|| {
+                    let sideways = offset.perp_dot(step).abs();
+                    (
+                        candidate.entity,
+                        forward + sideways * NAVIGATION_SIDEWAYS_PENALTY,
+                    )
+                })
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((next, _)) = next {
+            focus(&mut commands, Some(current), next);
+        }
+    }
+
+    if confirm {
+        press(&mut buttons, &mut pad_press, current);
+    }
+}
+
+/// Presses `entity` for a frame, as a click would.
+fn press(buttons: &mut NavigableButtons, pad_press: &mut PadPress, entity: Entity) {
+    if let Ok((_, mut interaction, ..)) = buttons.get_mut(entity) {
+        *interaction = Interaction::Pressed;
+        pad_press.0 = Some(entity);
+    }
+}
+
+fn focus(commands: &mut Commands, previous: Option<Entity>, next: Entity) {
+    if let Some(previous) = previous {
+        commands
+            .entity(previous)
+            .remove::<(GamepadFocus, Outline)>();
+    }
+    commands.entity(next).insert((
+        GamepadFocus,
+        Outline::new(px(3), px(2), FOCUS_OUTLINE_COLOR),
+    ));
+}
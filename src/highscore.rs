@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use bevy_pkv::PersistentResourceAppExtensions;
+use serde::{Deserialize, Serialize};
+
+use crate::Difficulty;
+
+/// Number of entries kept per difficulty.
+pub const HIGH_SCORE_LIMIT: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighScoreEntry {
+    pub score: u32,
+    pub time_survived: f32,
+    pub level: u32,
+    /// Seconds since the Unix epoch, or 0 when the clock is unavailable.
+    pub recorded_at: u64,
+}
+
+/// Leaderboards kept separately per difficulty so easier runs never push out harder ones.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    easy: Vec<HighScoreEntry>,
+    normal: Vec<HighScoreEntry>,
+    hard: Vec<HighScoreEntry>,
+}
+
+impl HighScores {
+    pub fn entries(&self, difficulty: Difficulty) -> &[HighScoreEntry] {
+        match difficulty {
+            Difficulty::Easy => &self.easy,
+            Difficulty::Normal => &self.normal,
+            Difficulty::Hard => &self.hard,
+        }
+    }
+
+    fn entries_mut(&mut self, difficulty: Difficulty) -> &mut Vec<HighScoreEntry> {
+        match difficulty {
+            Difficulty::Easy => &mut self.easy,
+            Difficulty::Normal => &mut self.normal,
+            Difficulty::Hard => &mut self.hard,
+        }
+    }
+
+    /// Inserts the entry and returns its rank if it made it onto the table.
+    pub fn record(&mut self, difficulty: Difficulty, entry: HighScoreEntry) -> Option<usize> {
+        let entries = self.entries_mut(difficulty);
+        let rank = entries
+            .iter()
+            .position(|existing| {
+                entry.score > existing.score
+                    || (entry.score == existing.score
+                        && entry.time_survived > existing.time_survived)
+            })
+            .unwrap_or(entries.len());
+        if rank >= HIGH_SCORE_LIMIT {
+            return None;
+        }
+        entries.insert(rank, entry);
+        entries.truncate(HIGH_SCORE_LIMIT);
+        Some(rank)
+    }
+}
+
+pub fn plugin(app: &mut App) {
+    app.init_persistent_resource::<HighScores>();
+}
+
+pub fn now_unix_secs() -> u64 {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0)
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        0
+    }
+}
+
+pub fn format_duration(seconds: f32) -> String {
+    let total = seconds.max(0.0) as u32;
+    format!("{:02}:{:02}", total / 60, total % 60)
+}
+
+/// Formats a Unix timestamp as `YYYY-MM-DD` (UTC).
+pub fn format_date(unix_secs: u64) -> String {
+    if unix_secs == 0 {
+        return "-".to_string();
+    }
+
+    // Civil-from-days conversion, see http://howardhinnant.github.io/date_algorithms.html
+    let z = (unix_secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
@@ -0,0 +1,169 @@
+pub mod audio;
+mod controls;
+pub mod game;
+mod gamepad;
+mod highscore;
+mod menu;
+mod persist;
+mod shop;
+mod splash;
+mod unlocks;
+
+use bevy::prelude::*;
+use bevy::window::{PresentMode, PrimaryWindow};
+use bevy_pkv::PersistentResourceAppExtensions;
+use serde::{Deserialize, Serialize};
+
+#[derive(States, Default, Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum MainState {
+    #[default]
+    Splash,
+    Menu,
+    Game,
+}
+
+// One of the two settings that can be set through the menu. It will be a resource in the app
+#[derive(
+    Resource, Debug, Component, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize,
+)]
+pub enum DisplayQuality {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl DisplayQuality {
+    /// Share of the full particle count spawned for an effect.
+    pub fn particle_share(self) -> f32 {
+        match self {
+            DisplayQuality::Low => 0.25,
+            DisplayQuality::Medium => 0.5,
+            DisplayQuality::High => 1.0,
+        }
+    }
+
+    /// Multiplier on how long particles last.
+    pub fn particle_lifetime_scale(self) -> f32 {
+        match self {
+            DisplayQuality::Low => 0.5,
+            DisplayQuality::Medium => 0.75,
+            DisplayQuality::High => 1.0,
+        }
+    }
+
+    /// Whether enemies flash when hit and bombs blink while their fuse burns.
+    pub fn flashes(self) -> bool {
+        self != DisplayQuality::Low
+    }
+
+    /// Most sound effects playing at once. Further ones are dropped.
+    pub fn max_sound_effects(self) -> usize {
+        match self {
+            DisplayQuality::Low => 8,
+            DisplayQuality::Medium => 16,
+            DisplayQuality::High => 32,
+        }
+    }
+
+    /// WebGL only supports 1 or 4 samples, so Medium drops MSAA there instead of halving it.
+    pub fn msaa(self) -> Msaa {
+        match self {
+            DisplayQuality::Low => Msaa::Off,
+            DisplayQuality::Medium if cfg!(target_arch = "wasm32") => Msaa::Off,
+            DisplayQuality::Medium => Msaa::Sample2,
+            DisplayQuality::High => Msaa::Sample4,
+        }
+    }
+
+    /// Low doesn't wait for vsync, so a machine that can't keep up with the display isn't
+    /// held to a fraction of its refresh rate, at the cost of some tearing.
+    pub fn present_mode(self) -> PresentMode {
+        match self {
+            DisplayQuality::Low => PresentMode::AutoNoVsync,
+            DisplayQuality::Medium | DisplayQuality::High => PresentMode::AutoVsync,
+        }
+    }
+}
+
+#[derive(
+    Resource, Debug, Component, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize,
+)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn enemy_spawn_interval(self, base: f32) -> f32 {
+        match self {
+            Difficulty::Easy => base * 1.5,
+            Difficulty::Normal => base,
+            Difficulty::Hard => base * 0.75,
+        }
+    }
+
+    /// How fast a run moves along the wave timeline.
+    pub fn wave_time_scale(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.8,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.25,
+        }
+    }
+
+    pub fn player_max_health(self, base: i32) -> i32 {
+        match self {
+            Difficulty::Easy => base + 1,
+            Difficulty::Normal => base,
+            Difficulty::Hard => (base / 2).max(1),
+        }
+    }
+}
+
+/// Adds the whole game on top of Bevy's `DefaultPlugins`.
+pub fn plugin(app: &mut App) {
+    app.add_plugins(persist::plugin)
+        .insert_resource(ClearColor(Color::srgb(0.1, 0.1, 0.15)))
+        .init_persistent_resource::<DisplayQuality>()
+        .insert_resource(Difficulty::default())
+        .init_state::<MainState>()
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            apply_display_quality.run_if(resource_changed::<DisplayQuality>),
+        )
+        .add_plugins((
+            audio::plugin,
+            controls::plugin,
+            gamepad::plugin,
+            highscore::plugin,
+            shop::plugin,
+            unlocks::plugin,
+            splash::plugin,
+            menu::plugin,
+            game::plugin,
+        ));
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn(Camera2d);
+}
+
+fn apply_display_quality(
+    quality: Res<DisplayQuality>,
+    mut msaa: Single<&mut Msaa, With<Camera2d>>,
+    mut window: Single<&mut Window, With<PrimaryWindow>>,
+) {
+    **msaa = quality.msaa();
+    window.present_mode = quality.present_mode();
+}
+
+/// Value following `name` on the command line, e.g. `cli_arg("--seed")` for `--seed 42`.
+pub(crate) fn cli_arg(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != name);
+    args.next()?;
+    args.next()
+}
@@ -2,12 +2,30 @@ use bevy::{
     app::AppExit,
     color::palettes::css::CRIMSON,
     ecs::spawn::{SpawnIter, SpawnWith},
+    input::{
+        ButtonState,
+        keyboard::{Key, KeyboardInput},
+    },
     prelude::*,
 };
 
 use crate::{
     Difficulty, DisplayQuality,
     audio::{BGM, BGMVolume, SEVolume, spawn_se},
+    controls::{
+        ALL_INPUT_ACTIONS, AimMode, BindingSlot, BoundInput, Controls, InputAction,
+        KEYS_PER_ACTION, Rebinding, button_label, key_label,
+    },
+    game::{
+        arena::{MAPS, MapCatalog, SelectedMap},
+        character::{ALL_CHARACTERS, Character},
+        rng::NextRunSeed,
+        save::{RequestedResume, SavedRun},
+    },
+    gamepad::{CancelButton, DefaultFocus, TextEntry},
+    highscore::{HighScores, format_date, format_duration},
+    shop::{ALL_SHOP_UPGRADES, MetaProgress, ShopUpgrade},
+    unlocks::CareerStats,
 };
 
 use super::MainState;
@@ -18,6 +36,26 @@ pub fn plugin(app: &mut App) {
     app.init_state::<MenuState>()
         .add_systems(OnEnter(MainState::Menu), menu_setup)
         .add_systems(OnEnter(MenuState::Main), main_menu_setup)
+        .add_systems(
+            OnEnter(MenuState::CharacterSelect),
+            character_select_menu_setup,
+        )
+        .init_resource::<SeedEntry>()
+        .add_systems(
+            Update,
+            (setting_button::<Character>, type_seed).run_if(in_state(MenuState::CharacterSelect)),
+        )
+        .add_systems(OnEnter(MenuState::HighScores), high_scores_menu_setup)
+        .add_systems(OnEnter(MenuState::Shop), shop_menu_setup)
+        .add_systems(
+            Update,
+            (
+                buy_shop_upgrade,
+                update_shop_labels.run_if(resource_changed::<MetaProgress>),
+            )
+                .chain()
+                .run_if(in_state(MenuState::Shop)),
+        )
         .add_systems(OnEnter(MenuState::Settings), settings_menu_setup)
         .add_systems(
             OnEnter(MenuState::SettingsDisplay),
@@ -33,22 +71,44 @@ pub fn plugin(app: &mut App) {
             (setting_button::<BGMVolume>, setting_button::<SEVolume>)
                 .run_if(in_state(MenuState::SettingsSound)),
         )
+        .add_systems(
+            OnEnter(MenuState::SettingsControls),
+            controls_settings_menu_setup,
+        )
+        .add_systems(OnExit(MenuState::SettingsControls), cancel_rebinding)
         .add_systems(
             Update,
-            setting_button::<Difficulty>.run_if(in_state(MenuState::Main)),
+            (
+                setting_button::<AimMode>,
+                capture_binding,
+                start_rebinding,
+                update_binding_labels
+                    .run_if(resource_changed::<Controls>.or(resource_changed::<Rebinding>)),
+            )
+                .chain()
+                .run_if(in_state(MenuState::SettingsControls)),
         )
         .add_systems(
             Update,
-            (menu_action, button_system).run_if(in_state(MainState::Menu)),
+            (setting_button::<Difficulty>, setting_button::<SelectedMap>)
+                .run_if(in_state(MenuState::Main)),
+        )
+        .add_systems(
+            Update,
+            (menu_action, button_system, mark_back_buttons).run_if(in_state(MainState::Menu)),
         );
 }
 
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
 enum MenuState {
     Main,
+    CharacterSelect,
+    HighScores,
+    Shop,
     Settings,
     SettingsDisplay,
     SettingsSound,
+    SettingsControls,
     #[default]
     Disabled,
 }
@@ -56,6 +116,23 @@ enum MenuState {
 #[derive(Component)]
 struct OnMainMenuScreen;
 
+#[derive(Component)]
+struct OnCharacterSelectMenuScreen;
+
+// Seed typed on the character select screen, empty for a random one
+#[derive(Resource, Default)]
+struct SeedEntry(String);
+
+// Line on the character select screen showing the typed seed
+#[derive(Component)]
+struct SeedEntryText;
+
+#[derive(Component)]
+struct OnHighScoresMenuScreen;
+
+#[derive(Component)]
+struct OnShopMenuScreen;
+
 #[derive(Component)]
 struct OnSettingsMenuScreen;
 
@@ -65,6 +142,32 @@ struct OnDisplaySettingsMenuScreen;
 #[derive(Component)]
 struct OnSoundSettingsMenuScreen;
 
+#[derive(Component)]
+struct OnControlsSettingsMenuScreen;
+
+// A button showing one binding of an action, or the text inside it
+#[derive(Component, Clone, Copy)]
+struct BindingSlotButton {
+    action: InputAction,
+    slot: BindingSlot,
+}
+
+#[derive(Component)]
+struct BindingSlotLabel(BindingSlotButton);
+
+// Gold left to spend, on the shop screen
+#[derive(Component)]
+struct ShopGoldText;
+
+// Level and price of one shop upgrade, shown on its buy button
+#[derive(Component)]
+struct ShopUpgradeLabel(ShopUpgrade);
+
+// Line under the bindings telling what the controls screen is waiting for, or why a
+// binding was refused
+#[derive(Component)]
+struct ControlsStatusText;
+
 const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
 const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
 const HOVERED_PRESSED_BUTTON: Color = Color::srgb(0.25, 0.65, 0.25);
@@ -75,10 +178,17 @@ struct SelectedOption;
 
 #[derive(Component)]
 enum MenuButtonAction {
+    Continue,
     Play,
+    StartGame,
+    HighScores,
+    Shop,
+    RefundShop,
     Settings,
     SettingsDisplay,
     SettingsSound,
+    SettingsControls,
+    ResetControls,
     BackToMainMenu,
     BackToSettings,
     Quit,
@@ -147,6 +257,9 @@ fn main_menu_setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     difficulty: Res<Difficulty>,
+    selected_map: Res<SelectedMap>,
+    map_catalog: Res<MapCatalog>,
+    saved_run: Res<SavedRun>,
 ) {
     // Common style for all buttons on the screen
     let button_node = Node {
@@ -181,12 +294,40 @@ fn main_menu_setup(
         font_size: 26.0,
         ..default()
     };
+    // Map names are longer than difficulty names
+    let map_button_node = Node {
+        width: px(160),
+        ..difficulty_button_node.clone()
+    };
+    let map_text_font = difficulty_text_font.clone();
 
     let right_icon = asset_server.load("textures/Game Icons/right.png");
+    let trophy_icon = asset_server.load("textures/Game Icons/trophy.png");
+    let star_icon = asset_server.load("textures/Game Icons/star.png");
     let wrench_icon = asset_server.load("textures/Game Icons/wrench.png");
     let exit_icon = asset_server.load("textures/Game Icons/exitRight.png");
 
     let difficulty = *difficulty;
+    let selected_map = *selected_map;
+    let has_saved_run = saved_run.0.is_some();
+    let continue_button = (
+        Button,
+        button_node.clone(),
+        BackgroundColor(NORMAL_BUTTON),
+        MenuButtonAction::Continue,
+        children![
+            (ImageNode::new(right_icon.clone()), button_icon_node.clone()),
+            (
+                Text::new("Continue"),
+                button_text_font.clone(),
+                TextColor(TEXT_COLOR),
+            ),
+        ],
+    );
+    let map_choices: Vec<(SelectedMap, String)> = MAPS
+        .iter()
+        .map(|&(id, _)| (SelectedMap(id), map_catalog.display_name(id).to_string()))
+        .collect();
 
     commands.spawn((
         DespawnOnExit(MenuState::Main),
@@ -250,8 +391,45 @@ fn main_menu_setup(
                         }),
                     )),
                 ),
-                // Display three buttons for each action available from the main menu:
+                (
+                    Node {
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::Center,
+                        margin: UiRect::axes(px(24), px(0)),
+                        ..default()
+                    },
+                    Children::spawn(SpawnWith(move |parent: &mut ChildSpawner| {
+                        for (map_choice, name) in map_choices {
+                            let mut entity = parent.spawn((
+                                Button,
+                                map_button_node.clone(),
+                                BackgroundColor(NORMAL_BUTTON),
+                                map_choice,
+                                children![(
+                                    Text::new(name),
+                                    map_text_font.clone(),
+                                    TextColor(TEXT_COLOR),
+                                )],
+                            ));
+                            if selected_map == map_choice {
+                                entity.insert(SelectedOption);
+                            }
+                        }
+                    })),
+                ),
+                // Only offered while a run is saved
+                (
+                    Node::default(),
+                    Children::spawn(SpawnWith(move |parent: &mut ChildSpawner| {
+                        if has_saved_run {
+                            parent.spawn(continue_button);
+                        }
+                    })),
+                ),
+                // Display five buttons for each action available from the main menu:
                 // - new game
+                // - high scores
+                // - shop
                 // - settings
                 // - quit
                 (
@@ -259,6 +437,7 @@ fn main_menu_setup(
                     button_node.clone(),
                     BackgroundColor(NORMAL_BUTTON),
                     MenuButtonAction::Play,
+                    DefaultFocus,
                     children![
                         (ImageNode::new(right_icon), button_icon_node.clone()),
                         (
@@ -268,6 +447,34 @@ fn main_menu_setup(
                         ),
                     ]
                 ),
+                (
+                    Button,
+                    button_node.clone(),
+                    BackgroundColor(NORMAL_BUTTON),
+                    MenuButtonAction::HighScores,
+                    children![
+                        (ImageNode::new(trophy_icon), button_icon_node.clone()),
+                        (
+                            Text::new("High Scores"),
+                            button_text_font.clone(),
+                            TextColor(TEXT_COLOR),
+                        ),
+                    ]
+                ),
+                (
+                    Button,
+                    button_node.clone(),
+                    BackgroundColor(NORMAL_BUTTON),
+                    MenuButtonAction::Shop,
+                    children![
+                        (ImageNode::new(star_icon), button_icon_node.clone()),
+                        (
+                            Text::new("Shop"),
+                            button_text_font.clone(),
+                            TextColor(TEXT_COLOR),
+                        ),
+                    ]
+                ),
                 (
                     Button,
                     button_node.clone(),
@@ -297,6 +504,511 @@ fn main_menu_setup(
     ));
 }
 
+fn character_select_menu_setup(
+    mut commands: Commands,
+    mut character: ResMut<Character>,
+    career: Res<CareerStats>,
+    next_seed: Res<NextRunSeed>,
+    mut seed_entry: ResMut<SeedEntry>,
+) {
+    let button_node = Node {
+        width: px(200),
+        height: px(65),
+        margin: UiRect::all(px(20)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = (
+        TextFont {
+            font_size: 33.0,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+    );
+    let card_node = Node {
+        flex_direction: FlexDirection::Column,
+        width: px(200),
+        height: px(190),
+        margin: UiRect::all(px(8)),
+        padding: UiRect::all(px(10)),
+        row_gap: px(6),
+        ..default()
+    };
+    let name_font = TextFont {
+        font_size: 26.0,
+        ..default()
+    };
+    let detail_font = TextFont {
+        font_size: 16.0,
+        ..default()
+    };
+    let seed_font = detail_font.clone();
+
+    // A character that was picked before its unlock was lost can't stay picked
+    if !career.is_unlocked(*character) {
+        *character = Character::default();
+    }
+    let selected = *character;
+    // Start from the `--seed` flag if it hasn't been used yet
+    seed_entry.0 = next_seed.0.map(|seed| seed.to_string()).unwrap_or_default();
+    let cards: Vec<(Character, bool)> = ALL_CHARACTERS
+        .into_iter()
+        .map(|character| (character, career.is_unlocked(character)))
+        .collect();
+
+    commands.spawn((
+        DespawnOnExit(MenuState::CharacterSelect),
+        Node {
+            width: percent(100),
+            height: percent(100),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        OnCharacterSelectMenuScreen,
+        children![(
+            Node {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                padding: UiRect::all(px(20)),
+                ..default()
+            },
+            BackgroundColor(CRIMSON.into()),
+            children![
+                (
+                    Text::new("Choose a Character"),
+                    button_text_style.clone(),
+                    Node {
+                        margin: UiRect::bottom(px(12)),
+                        ..default()
+                    },
+                ),
+                (
+                    Node {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    // Locked characters show what unlocks them instead of being buttons
+                    Children::spawn(SpawnWith(move |parent: &mut ChildSpawner| {
+                        for (character, unlocked) in cards {
+                            let details = if unlocked {
+                                format!(
+                                    "Health {}\nSpeed {:.0}%\n{}\n{}",
+                                    character.max_health(),
+                                    character.speed() * 100.0,
+                                    character
+                                        .starting_weapons()
+                                        .iter()
+                                        .map(|kind| kind.label())
+                                        .collect::<Vec<_>>()
+                                        .join(", "),
+                                    character.passive().description(),
+                                )
+                            } else {
+                                format!("Locked\n{}", character.unlock().description())
+                            };
+                            let card = (
+                                card_node.clone(),
+                                BackgroundColor(NORMAL_BUTTON),
+                                children![
+                                    (
+                                        Text::new(character.label()),
+                                        name_font.clone(),
+                                        TextColor(if unlocked {
+                                            character.color()
+                                        } else {
+                                            Color::srgb(0.5, 0.5, 0.5)
+                                        }),
+                                    ),
+                                    (
+                                        Text::new(details),
+                                        detail_font.clone(),
+                                        TextColor(TEXT_COLOR)
+                                    ),
+                                ],
+                            );
+                            if !unlocked {
+                                parent.spawn(card);
+                                continue;
+                            }
+                            let mut entity = parent.spawn((Button, character, card));
+                            if character == selected {
+                                entity.insert((SelectedOption, DefaultFocus));
+                            }
+                        }
+                    })),
+                ),
+                (
+                    Text::new(seed_entry_label(&seed_entry.0)),
+                    seed_font,
+                    TextColor(TEXT_COLOR),
+                    SeedEntryText,
+                    TextEntry,
+                    Node {
+                        margin: UiRect::top(px(12)),
+                        ..default()
+                    },
+                ),
+                (
+                    Node {
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    children![
+                        (
+                            Button,
+                            button_node.clone(),
+                            BackgroundColor(NORMAL_BUTTON),
+                            MenuButtonAction::StartGame,
+                            children![(Text::new("Start"), button_text_style.clone())]
+                        ),
+                        (
+                            Button,
+                            button_node,
+                            BackgroundColor(NORMAL_BUTTON),
+                            MenuButtonAction::BackToMainMenu,
+                            children![(Text::new("Back"), button_text_style)]
+                        ),
+                    ],
+                ),
+            ]
+        )],
+    ));
+}
+
+fn seed_entry_label(entry: &str) -> String {
+    if entry.is_empty() {
+        "Seed: random (type digits to pick one)".to_string()
+    } else {
+        format!("Seed: {entry} (Backspace to edit)")
+    }
+}
+
+// Digits typed on the character select screen make up the seed of the next run
+fn type_seed(
+    mut keyboard_input: MessageReader<KeyboardInput>,
+    mut seed_entry: ResMut<SeedEntry>,
+    mut text: Single<&mut Text, With<SeedEntryText>>,
+) {
+    let mut changed = false;
+    for input in keyboard_input.read() {
+        if input.state != ButtonState::Pressed {
+            continue;
+        }
+        match &input.logical_key {
+            Key::Character(typed) if typed.chars().all(|c| c.is_ascii_digit()) => {
+                let entry = format!("{}{typed}", seed_entry.0);
+                // Keys that would overflow a seed are ignored
+                if entry.parse::<u64>().is_ok() {
+                    seed_entry.0 = entry;
+                    changed = true;
+                }
+            }
+            Key::Backspace => {
+                changed |= seed_entry.0.pop().is_some();
+            }
+            _ => {}
+        }
+    }
+    if changed {
+        text.0 = seed_entry_label(&seed_entry.0);
+    }
+}
+
+fn high_scores_menu_setup(mut commands: Commands, high_scores: Res<HighScores>) {
+    let button_node = Node {
+        width: px(200),
+        height: px(65),
+        margin: UiRect::all(px(20)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = (
+        TextFont {
+            font_size: 33.0,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+    );
+    let entry_text_font = TextFont {
+        font_size: 20.0,
+        ..default()
+    };
+
+    // One column per difficulty, each listing its own leaderboard
+    let columns: Vec<_> = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard]
+        .into_iter()
+        .map(|difficulty| {
+            let lines: Vec<String> = high_scores
+                .entries(difficulty)
+                .iter()
+                .enumerate()
+                .map(|(rank, entry)| {
+                    format!(
+                        "{:>2}. {:>6}  {}  Lv{:<2}  {}",
+                        rank + 1,
+                        entry.score,
+                        format_duration(entry.time_survived),
+                        entry.level,
+                        format_date(entry.recorded_at),
+                    )
+                })
+                .collect();
+            let body = if lines.is_empty() {
+                "No runs yet".to_string()
+            } else {
+                lines.join("\n")
+            };
+            (difficulty, body)
+        })
+        .collect();
+
+    commands.spawn((
+        DespawnOnExit(MenuState::HighScores),
+        Node {
+            width: percent(100),
+            height: percent(100),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        OnHighScoresMenuScreen,
+        children![(
+            Node {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(CRIMSON.into()),
+            children![
+                (
+                    Text::new("High Scores"),
+                    button_text_style.clone(),
+                    Node {
+                        margin: UiRect::all(px(20)),
+                        ..default()
+                    },
+                ),
+                (
+                    Node {
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::FlexStart,
+                        column_gap: px(24),
+                        margin: UiRect::horizontal(px(24)),
+                        ..default()
+                    },
+                    Children::spawn(SpawnIter(columns.into_iter().map(
+                        move |(difficulty, body)| {
+                            (
+                                Node {
+                                    flex_direction: FlexDirection::Column,
+                                    row_gap: px(8),
+                                    ..default()
+                                },
+                                children![
+                                    (
+                                        Text::new(format!("{difficulty:?}")),
+                                        TextFont {
+                                            font_size: 26.0,
+                                            ..default()
+                                        },
+                                        TextColor(TEXT_COLOR),
+                                    ),
+                                    (
+                                        Text::new(body),
+                                        entry_text_font.clone(),
+                                        TextColor(TEXT_COLOR)
+                                    ),
+                                ],
+                            )
+                        }
+                    ))),
+                ),
+                (
+                    Button,
+                    button_node,
+                    BackgroundColor(NORMAL_BUTTON),
+                    MenuButtonAction::BackToMainMenu,
+                    children![(Text::new("Back"), button_text_style)]
+                ),
+            ]
+        )],
+    ));
+}
+
+fn shop_menu_setup(mut commands: Commands, meta: Res<MetaProgress>) {
+    let button_node = Node {
+        width: px(200),
+        height: px(65),
+        margin: UiRect::all(px(20)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = (
+        TextFont {
+            font_size: 33.0,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+    );
+    let small_text_font = TextFont {
+        font_size: 20.0,
+        ..default()
+    };
+
+    let rows: Vec<_> = ALL_SHOP_UPGRADES
+        .into_iter()
+        .map(|upgrade| {
+            (
+                Node {
+                    align_items: AlignItems::Center,
+                    margin: UiRect::vertical(px(4)),
+                    ..default()
+                },
+                children![
+                    (
+                        Text::new(upgrade.label()),
+                        small_text_font.clone(),
+                        TextColor(TEXT_COLOR),
+                        Node {
+                            width: px(140),
+                            ..default()
+                        },
+                    ),
+                    (
+                        Text::new(upgrade.description()),
+                        small_text_font.clone(),
+                        TextColor(TEXT_COLOR),
+                        Node {
+                            width: px(280),
+                            ..default()
+                        },
+                    ),
+                    (
+                        Button,
+                        Node {
+                            width: px(220),
+                            height: px(40),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(NORMAL_BUTTON),
+                        upgrade,
+                        children![(
+                            Text::new(shop_upgrade_text(&meta, upgrade)),
+                            small_text_font.clone(),
+                            TextColor(TEXT_COLOR),
+                            ShopUpgradeLabel(upgrade),
+                        )],
+                    ),
+                ],
+            )
+        })
+        .collect();
+
+    commands.spawn((
+        DespawnOnExit(MenuState::Shop),
+        Node {
+            width: percent(100),
+            height: percent(100),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        OnShopMenuScreen,
+        children![(
+            Node {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                padding: UiRect::all(px(20)),
+                ..default()
+            },
+            BackgroundColor(CRIMSON.into()),
+            Children::spawn((
+                Spawn((
+                    Text::new("Shop"),
+                    button_text_style.clone(),
+                    Node {
+                        margin: UiRect::bottom(px(8)),
+                        ..default()
+                    },
+                )),
+                Spawn((
+                    Text::new(shop_gold_text(&meta)),
+                    small_text_font,
+                    TextColor(TEXT_COLOR),
+                    Node {
+                        margin: UiRect::bottom(px(12)),
+                        ..default()
+                    },
+                    ShopGoldText,
+                )),
+                SpawnIter(rows.into_iter()),
+                Spawn((
+                    Node {
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    children![
+                        (
+                            Button,
+                            button_node.clone(),
+                            BackgroundColor(NORMAL_BUTTON),
+                            MenuButtonAction::RefundShop,
+                            children![(Text::new("Refund All"), button_text_style.clone())]
+                        ),
+                        (
+                            Button,
+                            button_node,
+                            BackgroundColor(NORMAL_BUTTON),
+                            MenuButtonAction::BackToMainMenu,
+                            children![(Text::new("Back"), button_text_style)]
+                        ),
+                    ],
+                )),
+            )),
+        )],
+    ));
+}
+
+fn shop_gold_text(meta: &MetaProgress) -> String {
+    format!("Gold: {}", meta.gold)
+}
+
+fn shop_upgrade_text(meta: &MetaProgress, upgrade: ShopUpgrade) -> String {
+    let level = upgrade.level(&meta.bonuses);
+    match meta.next_cost(upgrade) {
+        Some(cost) => format!("Lv {level}/{}  Buy {cost}", upgrade.max_level()),
+        None => format!("Lv {level}  Maxed"),
+    }
+}
+
+fn buy_shop_upgrade(
+    interaction_query: Query<(&Interaction, &ShopUpgrade), (Changed<Interaction>, With<Button>)>,
+    mut meta: ResMut<MetaProgress>,
+) {
+    for (interaction, upgrade) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            meta.buy(*upgrade);
+        }
+    }
+}
+
+fn update_shop_labels(
+    meta: Res<MetaProgress>,
+    mut gold_text: Single<&mut Text, With<ShopGoldText>>,
+    mut labels: Query<(&mut Text, &ShopUpgradeLabel), Without<ShopGoldText>>,
+) {
+    gold_text.0 = shop_gold_text(&meta);
+    for (mut text, ShopUpgradeLabel(upgrade)) in &mut labels {
+        text.0 = shop_upgrade_text(&meta, *upgrade);
+    }
+}
+
 fn settings_menu_setup(mut commands: Commands) {
     let button_node = Node {
         width: px(200),
@@ -336,6 +1048,7 @@ fn settings_menu_setup(mut commands: Commands) {
                 [
                     (MenuButtonAction::SettingsDisplay, "Display"),
                     (MenuButtonAction::SettingsSound, "Sound"),
+                    (MenuButtonAction::SettingsControls, "Controls"),
                     (MenuButtonAction::BackToMainMenu, "Back"),
                 ]
                 .into_iter()
@@ -555,6 +1268,296 @@ fn sound_settings_menu_setup(
     ));
 }
 
+// The gamepad's back button goes back a screen, through whichever button does that here
+fn mark_back_buttons(
+    mut commands: Commands,
+    buttons: Query<(Entity, &MenuButtonAction), Added<MenuButtonAction>>,
+) {
+    for (entity, action) in &buttons {
+        if matches!(
+            action,
+            MenuButtonAction::BackToMainMenu | MenuButtonAction::BackToSettings
+        ) {
+            commands.entity(entity).insert(CancelButton);
+        }
+    }
+}
+
+fn controls_settings_menu_setup(
+    mut commands: Commands,
+    controls: Res<Controls>,
+    aim_mode: Res<AimMode>,
+) {
+    let button_node = Node {
+        width: px(200),
+        height: px(65),
+        margin: UiRect::all(px(20)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = (
+        TextFont {
+            font_size: 33.0,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+    );
+    let small_text_font = TextFont {
+        font_size: 20.0,
+        ..default()
+    };
+    let slot_node = Node {
+        width: px(140),
+        height: px(34),
+        margin: UiRect::horizontal(px(4)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    let aim_mode = *aim_mode;
+    let aim_slot_node = slot_node.clone();
+    let aim_text_font = small_text_font.clone();
+    let aim_row = (
+        Node {
+            align_items: AlignItems::Center,
+            margin: UiRect::bottom(px(8)),
+            ..default()
+        },
+        Children::spawn((
+            Spawn((
+                Text::new("Aim"),
+                small_text_font.clone(),
+                TextColor(TEXT_COLOR),
+                Node {
+                    width: px(200),
+                    ..default()
+                },
+            )),
+            SpawnWith(move |parent: &mut ChildSpawner| {
+                for (aim_setting, label) in
+                    [(AimMode::Auto, "Auto"), (AimMode::Manual, "Mouse / Stick")]
+                {
+                    let mut entity = parent.spawn((
+                        Button,
+                        Node {
+                            width: px(214),
+                            ..aim_slot_node.clone()
+                        },
+                        BackgroundColor(NORMAL_BUTTON),
+                        aim_setting,
+                        children![(
+                            Text::new(label),
+                            aim_text_font.clone(),
+                            TextColor(TEXT_COLOR)
+                        )],
+                    ));
+                    if aim_mode == aim_setting {
+                        entity.insert(SelectedOption);
+                    }
+                }
+            }),
+        )),
+    );
+
+    let slots = (0..KEYS_PER_ACTION)
+        .map(BindingSlot::Key)
+        .chain([BindingSlot::Button]);
+    let controls = controls.clone();
+    let rows: Vec<_> = ALL_INPUT_ACTIONS
+        .into_iter()
+        .map(|action| {
+            let slot_buttons: Vec<_> = slots
+                .clone()
+                .map(|slot| {
+                    let slot = BindingSlotButton { action, slot };
+                    (
+                        Button,
+                        slot_node.clone(),
+                        BackgroundColor(NORMAL_BUTTON),
+                        slot,
+                        children![(
+                            Text::new(binding_text(&controls, slot)),
+                            small_text_font.clone(),
+                            TextColor(TEXT_COLOR),
+                            BindingSlotLabel(slot),
+                        )],
+                    )
+                })
+                .collect();
+            (
+                Node {
+                    align_items: AlignItems::Center,
+                    margin: UiRect::vertical(px(3)),
+                    ..default()
+                },
+                Children::spawn((
+                    Spawn((
+                        Text::new(action.label()),
+                        small_text_font.clone(),
+                        TextColor(TEXT_COLOR),
+                        Node {
+                            width: px(200),
+                            ..default()
+                        },
+                    )),
+                    SpawnIter(slot_buttons.into_iter()),
+                )),
+            )
+        })
+        .collect();
+
+    commands.spawn((
+        DespawnOnExit(MenuState::SettingsControls),
+        Node {
+            width: percent(100),
+            height: percent(100),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        OnControlsSettingsMenuScreen,
+        children![(
+            Node {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                padding: UiRect::all(px(20)),
+                ..default()
+            },
+            BackgroundColor(CRIMSON.into()),
+            Children::spawn((
+                Spawn((
+                    Text::new("Controls"),
+                    button_text_style.clone(),
+                    Node {
+                        margin: UiRect::bottom(px(12)),
+                        ..default()
+                    },
+                )),
+                Spawn(aim_row),
+                SpawnIter(rows.into_iter()),
+                Spawn((
+                    Text::new("Click a slot, then press the key or gamepad button to use"),
+                    small_text_font,
+                    TextColor(TEXT_COLOR),
+                    Node {
+                        margin: UiRect::top(px(12)),
+                        ..default()
+                    },
+                    ControlsStatusText,
+                )),
+                Spawn((
+                    Node {
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    children![
+                        (
+                            Button,
+                            button_node.clone(),
+                            BackgroundColor(NORMAL_BUTTON),
+                            MenuButtonAction::ResetControls,
+                            children![(Text::new("Defaults"), button_text_style.clone())]
+                        ),
+                        (
+                            Button,
+                            button_node,
+                            BackgroundColor(NORMAL_BUTTON),
+                            MenuButtonAction::BackToSettings,
+                            children![(Text::new("Back"), button_text_style)]
+                        ),
+                    ],
+                )),
+            )),
+        )],
+    ));
+}
+
+fn binding_text(controls: &Controls, slot: BindingSlotButton) -> String {
+    let binding = controls.binding(slot.action);
+    let bound = match slot.slot {
+        BindingSlot::Key(index) => binding.keys[index].map(key_label),
+        BindingSlot::Button => binding.button.map(button_label),
+    };
+    bound.unwrap_or_else(|| "-".to_string())
+}
+
+// Clicking a binding slot makes the screen wait for the input to bind there
+fn start_rebinding(
+    interaction_query: Query<(&Interaction, &BindingSlotButton), Changed<Interaction>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut status: Single<&mut Text, With<ControlsStatusText>>,
+) {
+    for (interaction, slot) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            rebinding.0 = Some((slot.action, slot.slot));
+            let device = match slot.slot {
+                BindingSlot::Key(_) => "a key",
+                BindingSlot::Button => "a gamepad button",
+            };
+            status.0 = format!(
+                "Press {device} for {} (click to cancel)",
+                slot.action.label()
+            );
+        }
+    }
+}
+
+// Binds the first key or gamepad button pressed while a slot is waiting. Runs before
+// `start_rebinding`, so the press that picked the slot isn't bound to it.
+fn capture_binding(
+    mut rebinding: ResMut<Rebinding>,
+    mut controls: ResMut<Controls>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<&Gamepad>,
+    mut status: Single<&mut Text, With<ControlsStatusText>>,
+) {
+    let Some((action, slot)) = rebinding.0 else {
+        return;
+    };
+    if mouse.get_just_pressed().next().is_some() {
+        rebinding.0 = None;
+        status.0 = "Cancelled".to_string();
+        return;
+    }
+    let input = match slot {
+        BindingSlot::Key(_) => keys.get_just_pressed().next().copied().map(BoundInput::Key),
+        BindingSlot::Button => gamepads
+            .iter()
+            .find_map(|gamepad| gamepad.get_just_pressed().next().copied())
+            .map(BoundInput::Button),
+    };
+    let Some(input) = input else {
+        return;
+    };
+
+    rebinding.0 = None;
+    status.0 = match controls.bind(action, slot, input) {
+        Ok(()) => format!("{} bound", action.label()),
+        Err(error) => format!("Not bound: {error}"),
+    };
+}
+
+fn update_binding_labels(
+    controls: Res<Controls>,
+    rebinding: Res<Rebinding>,
+    mut labels: Query<(&mut Text, &BindingSlotLabel)>,
+) {
+    for (mut text, BindingSlotLabel(slot)) in &mut labels {
+        text.0 = if rebinding.0 == Some((slot.action, slot.slot)) {
+            "...".to_string()
+        } else {
+            binding_text(&controls, *slot)
+        };
+    }
+}
+
+fn cancel_rebinding(mut rebinding: ResMut<Rebinding>) {
+    rebinding.0 = None;
+}
+
 fn menu_action(
     interaction_query: Query<
         (&Interaction, &MenuButtonAction),
@@ -563,6 +1566,11 @@ fn menu_action(
     mut app_exit_writer: MessageWriter<AppExit>,
     mut menu_state: ResMut<NextState<MenuState>>,
     mut main_state: ResMut<NextState<MainState>>,
+    mut controls: ResMut<Controls>,
+    mut meta: ResMut<MetaProgress>,
+    mut saved_run: ResMut<SavedRun>,
+    mut requested_resume: ResMut<RequestedResume>,
+    (seed_entry, mut next_seed): (Res<SeedEntry>, ResMut<NextRunSeed>),
 ) {
     for (interaction, menu_button_action) in &interaction_query {
         if *interaction == Interaction::Pressed {
@@ -570,10 +1578,21 @@ fn menu_action(
                 MenuButtonAction::Quit => {
                     app_exit_writer.write(AppExit::Success);
                 }
-                MenuButtonAction::Play => {
+                MenuButtonAction::Continue => {
+                    // Taken out of the store, so the run can't be continued a second time.
+                    requested_resume.0 = saved_run.0.take();
+                    main_state.set(MainState::Game);
+                    menu_state.set(MenuState::Disabled);
+                }
+                MenuButtonAction::Play => menu_state.set(MenuState::CharacterSelect),
+                MenuButtonAction::StartGame => {
+                    next_seed.0 = seed_entry.0.parse().ok();
                     main_state.set(MainState::Game);
                     menu_state.set(MenuState::Disabled);
                 }
+                MenuButtonAction::HighScores => menu_state.set(MenuState::HighScores),
+                MenuButtonAction::Shop => menu_state.set(MenuState::Shop),
+                MenuButtonAction::RefundShop => meta.refund(),
                 MenuButtonAction::Settings => menu_state.set(MenuState::Settings),
                 MenuButtonAction::SettingsDisplay => {
                     menu_state.set(MenuState::SettingsDisplay);
@@ -581,6 +1600,10 @@ fn menu_action(
                 MenuButtonAction::SettingsSound => {
                     menu_state.set(MenuState::SettingsSound);
                 }
+                MenuButtonAction::SettingsControls => {
+                    menu_state.set(MenuState::SettingsControls);
+                }
+                MenuButtonAction::ResetControls => controls.reset(),
                 MenuButtonAction::BackToMainMenu => menu_state.set(MenuState::Main),
                 MenuButtonAction::BackToSettings => {
                     menu_state.set(MenuState::Settings);
@@ -589,3 +1612,67 @@ fn menu_action(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::{InputPlugin, InputSystems};
+    use bevy::state::app::StatesPlugin;
+    use bevy::ui::UiSystems;
+
+    use super::*;
+    use crate::controls::Rebinding;
+
+    fn press_key(app: &mut App, key_code: KeyCode, logical_key: Key) {
+        app.world_mut().write_message(KeyboardInput {
+            key_code,
+            logical_key,
+            state: ButtonState::Pressed,
+            text: None,
+            repeat: false,
+            window: Entity::PLACEHOLDER,
+        });
+        app.update();
+    }
+
+    #[test]
+    fn backspace_edits_seed_without_leaving_character_select() {
+        let mut app = App::new();
+        app.add_plugins((StatesPlugin, InputPlugin, crate::gamepad::plugin))
+            .configure_sets(PreUpdate, UiSystems::Focus.after(InputSystems))
+            .init_state::<MainState>()
+            .insert_state(MenuState::CharacterSelect)
+            .init_resource::<Controls>()
+            .init_resource::<Rebinding>()
+            .init_resource::<MetaProgress>()
+            .init_resource::<SavedRun>()
+            .init_resource::<RequestedResume>()
+            .init_resource::<NextRunSeed>()
+            .insert_resource(SeedEntry("12".to_string()))
+            .add_systems(
+                Update,
+                (menu_action, type_seed).run_if(in_state(MenuState::CharacterSelect)),
+            );
+        app.world_mut()
+            .spawn((Text::default(), SeedEntryText, TextEntry));
+        app.world_mut().spawn((
+            Button,
+            MenuButtonAction::BackToMainMenu,
+            CancelButton,
+            ComputedNode {
+                size: Vec2::splat(50.0),
+                ..default()
+            },
+            InheritedVisibility::VISIBLE,
+            UiGlobalTransform::default(),
+        ));
+
+        press_key(&mut app, KeyCode::Backspace, Key::Backspace);
+        app.update();
+
+        assert_eq!(app.world().resource::<SeedEntry>().0, "1");
+        assert_eq!(
+            *app.world().resource::<State<MenuState>>().get(),
+            MenuState::CharacterSelect
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use bevy::prelude::*;
+use bevy_pkv::PersistentResourceAppExtensions;
+use serde::{Deserialize, Serialize};
+
+use crate::game::powerup::StartingBonuses;
+
+/// Permanent upgrades sold in the shop. Each level adds to the matching `StartingBonuses`
+/// field.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShopUpgrade {
+    MaxHealth,
+    MoveSpeed,
+    Experience,
+    Rerolls,
+}
+
+pub const ALL_SHOP_UPGRADES: [ShopUpgrade; 4] = [
+    ShopUpgrade::MaxHealth,
+    ShopUpgrade::MoveSpeed,
+    ShopUpgrade::Experience,
+    ShopUpgrade::Rerolls,
+];
+
+impl ShopUpgrade {
+    pub fn label(self) -> &'static str {
+        match self {
+            ShopUpgrade::MaxHealth => "Vitality",
+            ShopUpgrade::MoveSpeed => "Swiftness",
+            ShopUpgrade::Experience => "Insight",
+            ShopUpgrade::Rerolls => "Fortune",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            ShopUpgrade::MaxHealth => "+1 starting health",
+            ShopUpgrade::MoveSpeed => "+5% base move speed",
+            ShopUpgrade::Experience => "+10% experience gained",
+            ShopUpgrade::Rerolls => "+1 power-up reroll per run",
+        }
+    }
+
+    pub fn max_level(self) -> u32 {
+        match self {
+            ShopUpgrade::Rerolls => 3,
+            _ => 5,
+        }
+    }
+
+    /// Price of the level after `level`.
+    pub fn cost(self, level: u32) -> u32 {
+        let base = match self {
+            ShopUpgrade::MaxHealth => 50,
+            ShopUpgrade::MoveSpeed => 40,
+            ShopUpgrade::Experience => 60,
+            ShopUpgrade::Rerolls => 80,
+        };
+        base * (level + 1)
+    }
+
+    pub fn level(self, bonuses: &StartingBonuses) -> u32 {
+        match self {
+            ShopUpgrade::MaxHealth => bonuses.max_health,
+            ShopUpgrade::MoveSpeed => bonuses.move_speed,
+            ShopUpgrade::Experience => bonuses.experience,
+            ShopUpgrade::Rerolls => bonuses.rerolls,
+        }
+    }
+
+    fn level_mut(self, bonuses: &mut StartingBonuses) -> &mut u32 {
+        match self {
+            ShopUpgrade::MaxHealth => &mut bonuses.max_health,
+            ShopUpgrade::MoveSpeed => &mut bonuses.move_speed,
+            ShopUpgrade::Experience => &mut bonuses.experience,
+            ShopUpgrade::Rerolls => &mut bonuses.rerolls,
+        }
+    }
+}
+
+/// Gold and the upgrades bought with it, kept across runs.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct MetaProgress {
+    pub gold: u32,
+    pub bonuses: StartingBonuses,
+    /// Gold spent on the current upgrades, given back by `refund`.
+    spent: u32,
+}
+
+impl MetaProgress {
+    /// Gold a finished run earns.
+    pub fn gold_for_run(score: u32, enemies_killed: u32) -> u32 {
+        score / 10 + enemies_killed / 5
+    }
+
+    /// Price of the next level of `upgrade`, or `None` once it is maxed out.
+    pub fn next_cost(&self, upgrade: ShopUpgrade) -> Option<u32> {
+        let level = upgrade.level(&self.bonuses);
+        (level < upgrade.max_level()).then(|| upgrade.cost(level))
+    }
+
+    /// Buys the next level of `upgrade`. Returns whether there was enough gold.
+    pub fn buy(&mut self, upgrade: ShopUpgrade) -> bool {
+        let Some(cost) = self.next_cost(upgrade).filter(|cost| *cost <= self.gold) else {
+            return false;
+        };
+        self.gold -= cost;
+        self.spent += cost;
+        *upgrade.level_mut(&mut self.bonuses) += 1;
+        true
+    }
+
+    /// Removes every upgrade and gives back all the gold spent on them.
+    pub fn refund(&mut self) {
+        self.gold += self.spent;
+        self.spent = 0;
+        self.bonuses = StartingBonuses::default();
+    }
+}
+
+pub fn plugin(app: &mut App) {
+    app.init_persistent_resource::<MetaProgress>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buy_spends_gold_and_raises_level() {
+        let mut meta = MetaProgress {
+            gold: 200,
+            ..default()
+        };
+        assert!(meta.buy(ShopUpgrade::MaxHealth));
+        assert!(meta.buy(ShopUpgrade::MaxHealth));
+        assert_eq!(meta.gold, 200 - 50 - 100);
+        assert_eq!(meta.bonuses.max_health, 2);
+        assert_eq!(meta.next_cost(ShopUpgrade::MaxHealth), Some(150));
+    }
+
+    #[test]
+    fn buy_fails_without_enough_gold_or_when_maxed() {
+        let mut meta = MetaProgress {
+            gold: 79,
+            ..default()
+        };
+        assert!(!meta.buy(ShopUpgrade::Rerolls));
+        assert_eq!(meta.gold, 79);
+        assert_eq!(meta.bonuses.rerolls, 0);
+
+        meta.gold = 1000;
+        while meta.buy(ShopUpgrade::Rerolls) {}
+        assert_eq!(meta.bonuses.rerolls, ShopUpgrade::Rerolls.max_level());
+        assert_eq!(meta.next_cost(ShopUpgrade::Rerolls), None);
+        assert_eq!(meta.gold, 1000 - 80 - 160 - 240);
+    }
+
+    #[test]
+    fn refund_returns_all_spent_gold() {
+        let mut meta = MetaProgress {
+            gold: 500,
+            ..default()
+        };
+        assert!(meta.buy(ShopUpgrade::MoveSpeed));
+        assert!(meta.buy(ShopUpgrade::Experience));
+        meta.gold += 30;
+        meta.refund();
+        assert_eq!(meta.gold, 530);
+        assert_eq!(meta.bonuses, StartingBonuses::default());
+
+        // A second refund has nothing left to give back.
+        meta.refund();
+        assert_eq!(meta.gold, 530);
+    }
+}
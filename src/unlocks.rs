@@ -0,0 +1,49 @@
+use bevy::prelude::*;
+use bevy_pkv::PersistentResourceAppExtensions;
+use serde::{Deserialize, Serialize};
+
+use crate::game::character::{ALL_CHARACTERS, Character, Unlock};
+
+/// Totals over every recorded run, kept across restarts. Characters unlock from these.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct CareerStats {
+    pub enemies_killed: u64,
+    pub bosses_defeated: u64,
+    /// Longest time survived in a single run, in seconds.
+    pub longest_run: f32,
+}
+
+impl CareerStats {
+    pub fn is_unlocked(&self, character: Character) -> bool {
+        match character.unlock() {
+            Unlock::Always => true,
+            Unlock::Survive(seconds) => self.longest_run >= seconds,
+            Unlock::TotalKills(kills) => self.enemies_killed >= kills,
+            Unlock::DefeatBoss => self.bosses_defeated > 0,
+        }
+    }
+
+    /// Adds a finished run and returns the characters it unlocked.
+    pub fn record_run(
+        &mut self,
+        enemies_killed: u32,
+        bosses_defeated: u32,
+        time_survived: f32,
+    ) -> Vec<Character> {
+        let locked: Vec<Character> = ALL_CHARACTERS
+            .into_iter()
+            .filter(|character| !self.is_unlocked(*character))
+            .collect();
+        self.enemies_killed += u64::from(enemies_killed);
+        self.bosses_defeated += u64::from(bosses_defeated);
+        self.longest_run = self.longest_run.max(time_survived);
+        locked
+            .into_iter()
+            .filter(|character| self.is_unlocked(*character))
+            .collect()
+    }
+}
+
+pub fn plugin(app: &mut App) {
+    app.init_persistent_resource::<CareerStats>();
+}